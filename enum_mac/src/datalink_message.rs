@@ -0,0 +1,245 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, ToTokens};
+use syn::{parse::ParseStream, spanned::Spanned, Attribute, DeriveInput, Error, Expr, Field, Ident, LitInt, Path, Token, Type};
+
+// Everything goes in one radio word, the message key takes the first 4 bits of it and the fleet channel the last 4
+const WORD_BITS: usize = 60;
+const KEY_BITS: usize = 4;
+
+enum Encoding {
+	// #[bits(N)] or #[bits(N, encode = f, decode = g)]
+	Bits { bits: usize, encode: Option<Path>, decode: Option<Path> },
+	// #[squash(bits = N, ratio = R, offset = O)], Vector3 fields get N bits per axis
+	Squash { bits: usize, ratio: TokenStream, offset: TokenStream },
+}
+
+struct MessageField {
+	ident: Ident,
+	ty: Type,
+	encoding: Encoding,
+}
+
+impl MessageField {
+	fn type_name(&self) -> String {
+		match &self.ty {
+			Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()).unwrap_or_default(),
+			_ => String::new(),
+		}
+	}
+
+	fn width(&self) -> usize {
+		match &self.encoding {
+			Encoding::Bits { bits, .. } => *bits,
+			Encoding::Squash { bits, .. } if self.type_name() == "Vector3" => bits * 3,
+			Encoding::Squash { bits, .. } => *bits,
+		}
+	}
+
+	fn write(&self) -> TokenStream {
+		let ident = &self.ident;
+		match &self.encoding {
+			Encoding::Bits { bits, encode: Some(encode), .. } => quote! { view.write(#encode(self.#ident) as u64, #bits); },
+			Encoding::Bits { bits, .. } if self.type_name() == "bool" => quote! { view.write(if self.#ident { 1 } else { 0 }, #bits); },
+			Encoding::Bits { bits, .. } => quote! { view.write(self.#ident as u64, #bits); },
+			Encoding::Squash { bits, ratio, offset } => {
+				let squash = |value: TokenStream| quote! { view.write(crate::datalink::messages::message::squash_f32(#value, #bits, #ratio, #offset), #bits); };
+				if self.type_name() == "Vector3" {
+					let x = squash(quote! { self.#ident.x });
+					let y = squash(quote! { self.#ident.y });
+					let z = squash(quote! { self.#ident.z });
+					quote! { #x #y #z }
+				} else {
+					squash(quote! { self.#ident })
+				}
+			}
+		}
+	}
+
+	fn read(&self) -> TokenStream {
+		let ident = &self.ident;
+		let ty = &self.ty;
+		let value = match &self.encoding {
+			Encoding::Bits { bits, decode: Some(decode), .. } => quote! { #decode(view.read(#bits) as _) },
+			Encoding::Bits { bits, .. } if self.type_name() == "bool" => quote! { view.read(#bits) == 1 },
+			Encoding::Bits { bits, .. } => quote! { view.read(#bits) as #ty },
+			Encoding::Squash { bits, ratio, offset } => {
+				let unsquash = quote! { crate::datalink::messages::message::unsquash_f32(view.read(#bits), #bits, #ratio, #offset) };
+				if self.type_name() == "Vector3" {
+					quote! {{
+						let x = #unsquash;
+						let y = #unsquash;
+						let z = #unsquash;
+						crate::math::vector3::Vector3::new(x, y, z)
+					}}
+				} else {
+					unsquash
+				}
+			}
+		};
+
+		quote! { let #ident = #value; }
+	}
+}
+
+fn parse_usize(lit: &LitInt) -> syn::Result<usize> {
+	lit.base10_parse::<usize>()
+}
+
+fn parse_bits(attr: &Attribute) -> syn::Result<Encoding> {
+	let parser = |input: ParseStream| {
+		let bits = parse_usize(&input.parse::<LitInt>()?)?;
+		let mut encode = None;
+		let mut decode = None;
+		while input.parse::<Option<Token![,]>>()?.is_some() {
+			let name: Ident = input.parse()?;
+			input.parse::<Token![=]>()?;
+			let path: Path = input.parse()?;
+			match name.to_string().as_str() {
+				"encode" => encode = Some(path),
+				"decode" => decode = Some(path),
+				_ => return Err(Error::new(name.span(), "expected `encode` or `decode`")),
+			}
+		}
+
+		if encode.is_some() != decode.is_some() {
+			return Err(Error::new(input.span(), "`encode` and `decode` have to be given together"));
+		}
+
+		Ok(Encoding::Bits { bits, encode, decode })
+	};
+
+	attr.parse_args_with(parser)
+}
+
+fn parse_squash(attr: &Attribute) -> syn::Result<Encoding> {
+	let mut bits = None;
+	let mut ratio = None;
+	let mut offset = None;
+	attr.parse_nested_meta(|meta| {
+		if meta.path.is_ident("bits") {
+			bits = Some(parse_usize(&meta.value()?.parse::<LitInt>()?)?);
+		} else if meta.path.is_ident("ratio") {
+			ratio = Some(meta.value()?.parse::<Expr>()?.to_token_stream());
+		} else if meta.path.is_ident("offset") {
+			offset = Some(meta.value()?.parse::<Expr>()?.to_token_stream());
+		} else {
+			return Err(meta.error("expected `bits`, `ratio` or `offset`"));
+		}
+		Ok(())
+	})?;
+
+	match (bits, ratio, offset) {
+		(Some(bits), Some(ratio), Some(offset)) => Ok(Encoding::Squash { bits, ratio, offset }),
+		_ => Err(Error::new(attr.span(), "#[squash] needs `bits`, `ratio` and `offset`")),
+	}
+}
+
+fn parse_field(field: &Field) -> syn::Result<MessageField> {
+	let mut encoding = None;
+	for attr in field.attrs.iter() {
+		let parsed = if attr.path().is_ident("bits") {
+			parse_bits(attr)?
+		} else if attr.path().is_ident("squash") {
+			parse_squash(attr)?
+		} else {
+			continue;
+		};
+
+		if encoding.replace(parsed).is_some() {
+			return Err(Error::new(attr.span(), "a field can only have one of #[bits] or #[squash]"));
+		}
+	}
+
+	let ident = field
+		.ident
+		.clone()
+		.ok_or_else(|| Error::new(field.span(), "DatalinkMessage needs named fields"))?;
+	let encoding = encoding.ok_or_else(|| Error::new(field.span(), format!("field `{}` needs a #[bits] or #[squash] width", ident)))?;
+
+	Ok(MessageField { ident, ty: field.ty.clone(), encoding })
+}
+
+// #[datalink(key = Name)], for when the MessageKey variant isn't named after the struct
+fn parse_key(ast: &DeriveInput) -> syn::Result<Ident> {
+	let mut key = ast.ident.clone();
+	for attr in ast.attrs.iter().filter(|a| a.path().is_ident("datalink")) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("key") {
+				key = meta.value()?.parse::<Ident>()?;
+				Ok(())
+			} else {
+				Err(meta.error("expected `key`"))
+			}
+		})?;
+	}
+
+	Ok(key)
+}
+
+pub fn derive(ast: DeriveInput) -> syn::Result<TokenStream> {
+	let name = &ast.ident;
+	let key = parse_key(&ast)?;
+	let fields = match &ast.data {
+		syn::Data::Struct(data) => data.fields.iter().map(parse_field).collect::<syn::Result<Vec<_>>>()?,
+		_ => return Err(Error::new(Span::call_site(), "DatalinkMessage can only be derived for structs")),
+	};
+
+	// Still generates the impl when too wide, so the only error is this one and not every use of the message
+	let total = KEY_BITS + fields.iter().map(|f| f.width()).sum::<usize>();
+	let width_error = if total > WORD_BITS {
+		let layout: Vec<String> = fields.iter().map(|f| format!("{} {}", f.ident, f.width())).collect();
+		let message = format!(
+			"{} is {} bits, over the {} bits a radio word has for messages (key {}, {})",
+			name,
+			total,
+			WORD_BITS,
+			KEY_BITS,
+			layout.join(", ")
+		);
+		Error::new(name.span(), message).to_compile_error()
+	} else {
+		TokenStream::new()
+	};
+
+	let writes = fields.iter().map(|f| f.write());
+	let reads = fields.iter().map(|f| f.read());
+	let idents = fields.iter().map(|f| &f.ident);
+
+	Ok(quote! {
+		#width_error
+
+		impl #name {
+			// Width on the wire, message key included
+			pub const BITS: usize = #total;
+		}
+
+		impl crate::datalink::messages::message::DatalinkMessage for #name {
+			fn serialize(&self) -> crate::datalink::u64_view::U64View {
+				let mut view = crate::datalink::u64_view::U64View::zero();
+
+				view.write(crate::datalink::messages::message::DatalinkMessage::message_type(self) as u64, #KEY_BITS);
+				#(#writes)*
+
+				view
+			}
+
+			fn parse(mut view: crate::datalink::u64_view::U64View) -> Self {
+				#(#reads)*
+
+				#name { #(#idents),* }
+			}
+
+			fn message_type(&self) -> crate::datalink::messages::message::MessageKey {
+				crate::datalink::messages::message::MessageKey::#key
+			}
+		}
+	})
+}
+
+// Used by the derive entry point, split out so errors come back as compile_error! at the right span
+pub fn derive_tokens(tokens: TokenStream) -> TokenStream {
+	match syn::parse2::<DeriveInput>(tokens).and_then(derive) {
+		Ok(tokens) => tokens,
+		Err(error) => error.to_compile_error(),
+	}
+}
@@ -2,6 +2,8 @@ use proc_macro2::{Ident, TokenStream};
 use quote::{quote, ToTokens};
 use syn::{parse_macro_input, DeriveInput};
 
+mod datalink_message;
+
 #[proc_macro_derive(OldEnumKeys)]
 pub fn enum_keys_fn_old(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
 	let empty: proc_macro::TokenStream = "".parse().unwrap();
@@ -41,10 +43,7 @@ pub fn enum_keys_fn_old(tokens: proc_macro::TokenStream) -> proc_macro::TokenStr
 	let keys = key_group
 		.stream()
 		.into_iter()
-		.filter(|f| match f {
-			proc_macro::TokenTree::Ident(_) => true,
-			_ => false,
-		})
+		.filter(|f| matches!(f, proc_macro::TokenTree::Ident(_)))
 		.map(|f| match f {
 			proc_macro::TokenTree::Ident(ident) => ident.to_string(),
 			_ => panic!("Shouldn't happen"),
@@ -61,13 +60,10 @@ pub fn enum_keys_fn_old(tokens: proc_macro::TokenStream) -> proc_macro::TokenStr
 	}
 	source_code += &("enum ".to_owned() + &name + "Key {\n");
 
-	let mut i: i32 = 0;
-	for key in &keys {
-		source_code += &("    ".to_owned() + &key + ",\n");
-
-		match_chain += &("        ".to_owned() + &i.to_string() + " => " + &name + "Key::" + &key + ",\n");
+	for (i, key) in keys.iter().enumerate() {
+		source_code += &("    ".to_owned() + key + ",\n");
 
-		i += 1;
+		match_chain += &("        ".to_owned() + &i.to_string() + " => " + &name + "Key::" + key + ",\n");
 	}
 
 	source_code += "}\n";
@@ -103,28 +99,38 @@ pub fn enum_keys_fn(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream
 	let mut enum_keys_to_u8 = Vec::<TokenStream>::new();
 	let mut u8_to_enum_keys = Vec::<TokenStream>::new();
 
-	let mut i: u8 = 0;
-	for variant in enum_data.variants {
+	for (i, variant) in (0u8..).zip(enum_data.variants) {
 		let ident = variant.ident.clone();
 		enum_keys.push(ident.to_token_stream());
 		enum_keys_to_u8.push(quote! { #enum_name::#ident => #i, });
-		u8_to_enum_keys.push(quote! { #i => #enum_name::#ident, });
-		i += 1;
+		u8_to_enum_keys.push(quote! { #i => Ok(#enum_name::#ident), });
 	}
 
 	quote! {
+		 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 		 pub enum #enum_name {
 			  #(#enum_keys,)*
 		 }
 
-		 impl From<u8> for #enum_name {
-			  fn from(key: u8) -> Self {
+		 impl TryFrom<u8> for #enum_name {
+			  type Error = u8;
+
+			  // Gives back the key when nothing matches it
+			  fn try_from(key: u8) -> Result<Self, u8> {
 					match key {
 						 #(#u8_to_enum_keys)*
-						 _ => panic!("Unknown key: {}", key),
+						 _ => Err(key),
 					}
 			  }
 		 }
 	}
 	.into()
 }
+
+// Generates DatalinkMessage serialize/parse from the field widths, in declaration order after the 4 bit key.
+// Integer and bool fields take #[bits(N)], types without a cast take #[bits(N, encode = f, decode = g)], and
+// f32 or Vector3 take #[squash(bits = N, ratio = R, offset = O)]. Going over 60 bits is a compile error
+#[proc_macro_derive(DatalinkMessage, attributes(bits, squash, datalink))]
+pub fn datalink_message_fn(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	datalink_message::derive_tokens(tokens.into()).into()
+}
@@ -0,0 +1,7 @@
+// Messages either side of the radio word limit, the wide one has to be a compile error
+#[test]
+fn message_width() {
+	let t = trybuild::TestCases::new();
+	t.pass("tests/ui/fits.rs");
+	t.compile_fail("tests/ui/too_wide.rs");
+}
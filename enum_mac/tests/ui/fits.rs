@@ -0,0 +1,16 @@
+use enum_mac::DatalinkMessage;
+
+include!("stubs.rs");
+
+// 4 key bits and 56 payload, exactly the 60 a message gets
+#[derive(DatalinkMessage)]
+struct Fits {
+	#[bits(32)]
+	a: u32,
+	#[bits(24)]
+	b: u32,
+}
+
+fn main() {
+	assert_eq!(Fits::BITS, 60);
+}
@@ -0,0 +1,35 @@
+// Just enough of the fleet crate's datalink module for the derive's output to resolve against
+mod datalink {
+	pub mod u64_view {
+		pub struct U64View(pub u64);
+
+		impl U64View {
+			pub fn zero() -> U64View {
+				U64View(0)
+			}
+
+			pub fn write(&mut self, _value: u64, _len: usize) {}
+
+			pub fn read(&mut self, _len: usize) -> u64 {
+				0
+			}
+		}
+	}
+
+	pub mod messages {
+		pub mod message {
+			use crate::datalink::u64_view::U64View;
+
+			pub enum MessageKey {
+				Fits,
+				TooWide,
+			}
+
+			pub trait DatalinkMessage {
+				fn serialize(&self) -> U64View;
+				fn parse(view: U64View) -> Self;
+				fn message_type(&self) -> MessageKey;
+			}
+		}
+	}
+}
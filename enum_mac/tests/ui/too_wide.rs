@@ -0,0 +1,14 @@
+use enum_mac::DatalinkMessage;
+
+include!("stubs.rs");
+
+// One bit more than fits
+#[derive(DatalinkMessage)]
+struct TooWide {
+	#[bits(32)]
+	a: u32,
+	#[bits(25)]
+	b: u32,
+}
+
+fn main() {}
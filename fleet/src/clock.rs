@@ -0,0 +1,80 @@
+use std::cell::Cell;
+
+// The game runs at a fixed rate, the datalink tick counts at the same one
+pub const TICKS_PER_SECOND: u32 = 100;
+pub const TICK_DT: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
+// Simulation time in seconds since the vehicle started running. Everything that timestamps or ages data reads
+// from this instead of the wall clock, so behaviour doesn't change when the sim runs faster or slower than real time
+pub trait Clock {
+	fn now(&self) -> f32;
+
+	// Called by Core once at the start of every game tick
+	fn advance(&self);
+}
+
+// Counts game ticks, the real backend
+#[derive(Default)]
+pub struct TickClock {
+	ticks: Cell<u32>,
+}
+
+impl TickClock {
+	pub fn new() -> TickClock {
+		TickClock { ticks: Cell::new(0) }
+	}
+
+	pub fn ticks(&self) -> u32 {
+		self.ticks.get()
+	}
+}
+
+impl Clock for TickClock {
+	fn now(&self) -> f32 {
+		self.ticks.get() as f32 * TICK_DT
+	}
+
+	fn advance(&self) {
+		self.ticks.set(self.ticks.get() + 1);
+	}
+}
+
+// Time is whatever the test says it is, advance() steps by a fixed amount (one tick unless changed)
+pub struct MockClock {
+	time: Cell<f32>,
+	step: Cell<f32>,
+}
+
+impl MockClock {
+	pub fn new() -> MockClock {
+		MockClock { time: Cell::new(0.0), step: Cell::new(TICK_DT) }
+	}
+
+	pub fn set(&self, time: f32) {
+		self.time.set(time);
+	}
+
+	pub fn set_step(&self, step: f32) {
+		self.step.set(step);
+	}
+
+	pub fn skip(&self, seconds: f32) {
+		self.time.set(self.time.get() + seconds);
+	}
+}
+
+impl Default for MockClock {
+	fn default() -> Self {
+		MockClock::new()
+	}
+}
+
+impl Clock for MockClock {
+	fn now(&self) -> f32 {
+		self.time.get()
+	}
+
+	fn advance(&self) {
+		self.skip(self.step.get());
+	}
+}
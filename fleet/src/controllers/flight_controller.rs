@@ -1,11 +1,7 @@
-use std::cell::RefCell;
-
-use protologic_core::{
-	maneuvering::{engine_get_fuel_amount, engine_set_throttle, wheel_set_torque},
-	physics::{vehicle_get_angular_velocity, vehicle_get_orientation, vehicle_get_position, vehicle_get_velocity},
-};
+use std::rc::Rc;
 
 use crate::{
+	hardware::traits::Hardware,
 	math::{
 		pid::PID,
 		quaternion::Quaternion,
@@ -30,6 +26,8 @@ pub enum VehicleType {
 }
 
 pub struct FlightController {
+	hw: Rc<dyn Hardware>,
+
 	pub target_point: Vector3,
 	pub target_point_velocity: Vector3,
 
@@ -58,8 +56,12 @@ pub struct FlightController {
 }
 
 impl FlightController {
-	fn new() -> FlightController {
+	pub fn new(hw: Rc<dyn Hardware>) -> FlightController {
 		FlightController {
+			command_line: UpdatableDebugLine::new(hw.clone()),
+			target_line: UpdatableDebugLine::new(hw.clone()),
+			hw,
+
 			target_point: Vector3::zero(),
 			target_point_velocity: Vector3::zero(),
 
@@ -82,13 +84,10 @@ impl FlightController {
 
 			guidance_mode: GuidanceMode::Drift,
 			point_at_while_idle: None,
-
-			command_line: UpdatableDebugLine::new(),
-			target_line: UpdatableDebugLine::new(),
 		}
 	}
 
-	fn setup_for_missile(&mut self) {
+	pub fn setup_for_missile(&mut self) {
 		self.pid_x = PID::new(16.77, 0.0, 30.82, 10.0);
 		self.pid_y = PID::new(16.77, 0.0, 30.82, 10.0);
 		self.pid_z = PID::new(16.77, 0.0, 30.82, 10.0);
@@ -98,7 +97,7 @@ impl FlightController {
 		self.stop_range_start = 3000.0;
 	}
 
-	fn update(&mut self, dt: f32) {
+	pub fn update(&mut self, dt: f32) {
 		self.target_point = self.target_point_velocity * dt + self.target_point;
 		self.using_non_pid_guidance = false;
 		self.max_throttle = 1.0;
@@ -115,30 +114,27 @@ impl FlightController {
 				return;
 			}
 
-			wheel_set_torque(0.0, 0.0, 0.0);
-			engine_set_throttle(0.0);
+			self.hw.wheel_set_torque(0.0, 0.0, 0.0);
+			self.hw.engine_set_throttle(0.0);
 			return;
 		}
 
 		let target_point = maybe_target_point.unwrap();
-		let dist_to_tp = (target_point - vehicle_get_position().into()).length();
-		if dist_to_tp < 100.0 {}
-
-		if self.current_tick % 25 == 0 {
-			self.command_line.set_a(vehicle_get_position().into());
+		if self.current_tick.is_multiple_of(25) {
+			self.command_line.set_a(self.hw.vehicle_get_position().into());
 			self.command_line.set_b(target_point);
 
-			self.target_line.set_a(vehicle_get_position().into());
+			self.target_line.set_a(self.hw.vehicle_get_position().into());
 			self.target_line.set_b(self.target_point);
 			self.target_line.set_color(1.0, 0.0, 0.0);
 		}
 
 		let angle_error_degrees = self.point_at(target_point, dt);
 
-		let fuel = engine_get_fuel_amount();
+		let fuel = self.hw.engine_get_fuel_amount();
 		if fuel < 0.1 {
 			// println!("Out of fuel!");
-			engine_set_throttle(0.0);
+			self.hw.engine_set_throttle(0.0);
 			return;
 		}
 
@@ -147,22 +143,22 @@ impl FlightController {
 			wanted_throttle = 1.0;
 		}
 		if self.current_tick % 500 < 250 || !self.do_engine_pulsing {
-			engine_set_throttle(wanted_throttle.min(self.max_throttle));
+			self.hw.engine_set_throttle(wanted_throttle.min(self.max_throttle));
 
 			if wanted_throttle > 0.0 {
 				self.last_pulse_tick = self.current_tick;
 			}
 		} else {
-			engine_set_throttle(0.0);
+			self.hw.engine_set_throttle(0.0);
 		}
 
 		if self.do_engine_pulsing {
 			if self.current_tick - self.last_pulse_tick > 1000 {
-				engine_set_throttle(1.0);
+				self.hw.engine_set_throttle(1.0);
 			}
 
 			if self.current_tick - self.last_pulse_tick > 1250 {
-				engine_set_throttle(0.0);
+				self.hw.engine_set_throttle(0.0);
 				self.last_pulse_tick = self.current_tick;
 			}
 		}
@@ -171,8 +167,8 @@ impl FlightController {
 	}
 
 	fn point_at(&mut self, point: Vector3, dt: f32) -> f32 {
-		let ship_pos: Vector3 = vehicle_get_position().into();
-		let ship_orientation: Quaternion = vehicle_get_orientation().into();
+		let ship_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let ship_orientation: Quaternion = self.hw.vehicle_get_orientation().into();
 
 		let target_dir = (point - ship_pos).normalized();
 		let local_dir = ship_orientation.invert() * target_dir;
@@ -196,32 +192,32 @@ impl FlightController {
 
 			let torque_vector = ship_orientation * (Vector3::new(p_x, p_y, p_z) / max);
 
-			wheel_set_torque(torque_vector.x, torque_vector.y, torque_vector.z);
+			self.hw.wheel_set_torque(torque_vector.x, torque_vector.y, torque_vector.z);
 		}
 
-		return angle_error_degrees;
+		angle_error_degrees
 	}
 
 	fn get_target_point(&mut self) -> Option<Vector3> {
 		match self.guidance_mode {
-			GuidanceMode::Drift => return None,
-			GuidanceMode::Stop => return self.get_stop_target_point(),
-			GuidanceMode::StopAtPoint => return self.get_vel_corrected_target_point_for_stop(),
-			GuidanceMode::FastStopAtPoint => return self.get_fast_vel_corrected_target_point_for_stop(),
-			GuidanceMode::Impact => return self.get_vel_corrected_target_point_for_impact(),
+			GuidanceMode::Drift => None,
+			GuidanceMode::Stop => self.get_stop_target_point(),
+			GuidanceMode::StopAtPoint => self.get_vel_corrected_target_point_for_stop(),
+			GuidanceMode::FastStopAtPoint => self.get_fast_vel_corrected_target_point_for_stop(),
+			GuidanceMode::Impact => self.get_vel_corrected_target_point_for_impact(),
 		}
 	}
 
 	fn get_stop_target_point(&self) -> Option<Vector3> {
-		let ship_pos: Vector3 = vehicle_get_position().into();
-		let ship_vel: Vector3 = vehicle_get_velocity().into();
+		let ship_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let ship_vel: Vector3 = self.hw.vehicle_get_velocity().into();
 
 		Some(ship_pos + ship_vel.normalized() * 10000.0)
 	}
 
 	fn get_vel_corrected_target_point_for_impact(&self) -> Option<Vector3> {
-		let ship_pos: Vector3 = vehicle_get_position().into();
-		let ship_vel: Vector3 = vehicle_get_velocity().into();
+		let ship_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let ship_vel: Vector3 = self.hw.vehicle_get_velocity().into();
 
 		let wanted_vel = (self.target_point - ship_pos).normalized();
 		let current_vel = (ship_vel - self.target_point_velocity).normalized();
@@ -231,8 +227,8 @@ impl FlightController {
 	}
 
 	fn get_vel_corrected_target_point_for_stop(&mut self) -> Option<Vector3> {
-		let ship_pos: Vector3 = vehicle_get_position().into();
-		let ship_vel: Vector3 = vehicle_get_velocity().into();
+		let ship_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let ship_vel: Vector3 = self.hw.vehicle_get_velocity().into();
 
 		let dist_to_tp = (self.target_point - ship_pos).length();
 		let speed = ship_vel.length();
@@ -247,7 +243,7 @@ impl FlightController {
 			if speed < 1.0 {
 				self.using_non_pid_guidance = true;
 				self.kill_angular_velocity();
-				engine_set_throttle(0.0);
+				self.hw.engine_set_throttle(0.0);
 
 				return None;
 			}
@@ -277,8 +273,8 @@ impl FlightController {
 	}
 
 	fn get_fast_vel_corrected_target_point_for_stop(&mut self) -> Option<Vector3> {
-		let ship_pos: Vector3 = vehicle_get_position().into();
-		let ship_vel: Vector3 = vehicle_get_velocity().into();
+		let ship_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let ship_vel: Vector3 = self.hw.vehicle_get_velocity().into();
 
 		let dist_to_tp = (self.target_point - ship_pos).length();
 		let speed = ship_vel.length();
@@ -297,46 +293,9 @@ impl FlightController {
 	}
 
 	fn kill_angular_velocity(&self) {
-		let mut angular_vel: Vector3 = vehicle_get_angular_velocity().into();
+		let mut angular_vel: Vector3 = self.hw.vehicle_get_angular_velocity().into();
 		angular_vel *= -10.0;
 
-		wheel_set_torque(angular_vel.x, angular_vel.y, angular_vel.z)
+		self.hw.wheel_set_torque(angular_vel.x, angular_vel.y, angular_vel.z)
 	}
 }
-
-thread_local! {
-	static FC: RefCell<FlightController> = RefCell::new(FlightController::new());
-}
-
-pub fn set_flight_mode(mode: GuidanceMode) {
-	FC.with(|rfc| {
-		let mut fc = rfc.borrow_mut();
-		fc.guidance_mode = mode;
-	});
-}
-
-pub fn flight_set_target_point(point: Vector3) {
-	FC.with(|rfc| rfc.borrow_mut().target_point = point);
-}
-
-pub fn flight_set_target_point_velocity(vel: Vector3) {
-	FC.with(|rfc| {
-		rfc.borrow_mut().target_point_velocity = vel;
-	});
-}
-
-pub fn update_flight_controller(dt: f32) {
-	FC.with(|rfc| rfc.borrow_mut().update(dt));
-}
-
-pub fn setup_flight_for_missile() {
-	FC.with(|rfc| rfc.borrow_mut().setup_for_missile());
-}
-
-pub fn get_max_flight_target_speed() -> f32 {
-	FC.with(|rfc| rfc.borrow().max_target_speed)
-}
-
-pub fn current_flight_target_point() -> Vector3 {
-	FC.with(|rfc| rfc.borrow().target_point)
-}
@@ -1,3 +1,5 @@
 pub mod flight_controller;
 pub mod radar_controller;
+pub mod radar_scheduler;
+pub mod track_association;
 pub mod turret_controller;
@@ -1,24 +1,29 @@
-use protologic_core::{
-	physics::{vehicle_get_orientation, vehicle_get_position},
-	radar::*,
-};
-use rand::seq::IteratorRandom;
+use protologic_core::radar::*;
 
 use crate::{
+	clock::Clock,
 	datalink::{
-		datalink::{dl_crunch_id, dl_net_id, is_position_friendly, own_dl_id, send_message},
-		messages::{message::Message, track_info::TrackInfo, track_position::TrackPosition, track_velocity::TrackVelocity},
+		datalink::{dl_crunch_id, Datalink},
+		framing::FramedMessage,
+		messages::track_state,
 	},
 	get,
+	hardware::traits::Hardware,
 	math::{
+		kalman::{KalmanFilter, MotionModel},
 		quaternion::{AxisAngle, Quaternion},
-		utils::{lerp, now},
+		utils::lerp,
 		vector3::Vector3,
 	},
-	radar_scan_pattern::RadarScanPattern,
+	radar_scan_pattern::{RadarScanPattern, ScanPatternSpec},
 	updatable_debug::UpdatableSphere,
 };
-use std::{cell::RefCell, collections::HashMap, f32::consts::PI};
+
+use super::{
+	radar_scheduler::{Dwell, DwellRequest, DwellRequester, DwellTarget, RadarScheduler},
+	track_association::{associate, AmbiguousAssociation, AssociationStrategy},
+};
+use std::{collections::HashMap, f32::consts::PI, rc::Rc};
 
 fn ctn(contact_type: RadarTargetType) -> &'static str {
 	match contact_type {
@@ -38,69 +43,198 @@ pub enum RadarMode {
 	RWS,
 }
 
-const TWS_UPDATE_INTERVAL: f32 = 0.5; // 2 times per second
+pub const TWS_UPDATE_INTERVAL: f32 = 0.5; // 2 times per second
 const TWS_MAX_AGE: f32 = 5.0; // 5 seconds
 const DL_UPDATE_RATE: f32 = 5.0; // Once every 5 seconds
+const DL_SATURATED_UPDATE_RATE: f32 = 10.0; // While the datalink can't keep up
+const DL_CUE_INTERVAL: f32 = 1.0; // How often we look for something only others are tracking
+const DL_CUE_RANGE: f32 = 20000.0; // About as far as our radar sees
+
+// Track lifecycle. A look is one TWS_UPDATE_INTERVAL long slot, a new track has to be seen in
+// CONFIRM_HITS of its first CONFIRM_LOOKS looks or it gets dropped as a spurious return
+const CONFIRM_HITS: u32 = 3;
+const CONFIRM_LOOKS: u32 = 5;
+pub const COAST_AGE: f32 = TWS_UPDATE_INTERVAL * 3.0; // Missed a few looks, still predicted but not trusted
+
+// Ids for tracks whose return came in under an id another track already has. The top 32 bits are what
+// dl_crunch_id keeps, so they start at LOCAL_ID_BASE and count up from there. That's well above anything
+// the simulator hands out, so the datalink never mixes one of ours up with a real contact
+const LOCAL_ID_BASE: i64 = 0x4000_0000;
+
+// Track filter tuning
+const MEASUREMENT_SIGMA: f32 = 10.0; // m, per axis
+const SHIP_MANEUVER_NOISE: f32 = 25.0; // (m/s^2)^2 per Hz, ships hardly accelerate
+const MISSILE_MANEUVER_NOISE: f32 = 2500.0; // (m/s^3)^2 per Hz
+const INITIAL_VELOCITY_SIGMA: f32 = 500.0; // m/s, nothing is known about a new contact's motion
+
+fn track_filter(rc_type: RadarTargetType, position: Vector3) -> KalmanFilter {
+	let variance = MEASUREMENT_SIGMA * MEASUREMENT_SIGMA;
+	let initial_velocity_variance = INITIAL_VELOCITY_SIGMA * INITIAL_VELOCITY_SIGMA;
+	match rc_type {
+		// Missiles burn hard and turn, so they get the acceleration model
+		RadarTargetType::Missile => KalmanFilter::new(
+			MotionModel::ConstantAcceleration,
+			position,
+			variance,
+			MISSILE_MANEUVER_NOISE,
+			initial_velocity_variance,
+		),
+		_ => KalmanFilter::new(
+			MotionModel::ConstantVelocity,
+			position,
+			variance,
+			SHIP_MANEUVER_NOISE,
+			initial_velocity_variance,
+		),
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrackLifecycle {
+	Tentative,
+	Confirmed,
+	Coasting,
+	Dropped,
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct RadarTrack {
 	pub id: i64,
 	pub rc_type: RadarTargetType,
 
+	// Filtered state as of the last update
 	pub position: Vector3,
 	pub velocity: Vector3,
+	pub acceleration: Vector3,
+	pub filter: KalmanFilter,
 
 	pub last_update_timestamp: f32,
+	pub range: f32,
 
 	pub is_allied: bool,
 	pub last_allied_hit_time: f32,
+
+	pub state: TrackLifecycle,
+	first_seen: f32,
+	look_hits: u32, // Bit per look since first_seen
 }
 
 impl RadarTrack {
-	pub fn new(contact: &RadarGetContactInfo) -> RadarTrack {
+	pub fn new(contact: &RadarGetContactInfo, own_pos: Vector3, now: f32) -> RadarTrack {
+		let position = Vector3::new(contact.x, contact.y, contact.z);
 		RadarTrack {
 			id: contact.id,
 			rc_type: contact.target_type,
 
-			position: Vector3::new(contact.x, contact.y, contact.z),
+			position,
 			velocity: Vector3::zero(),
+			acceleration: Vector3::zero(),
+			filter: track_filter(contact.target_type, position),
 
-			last_update_timestamp: now(),
+			last_update_timestamp: now,
+			range: (position - own_pos).length(),
 			is_allied: true,
-			last_allied_hit_time: now(),
+			last_allied_hit_time: now,
+
+			state: TrackLifecycle::Tentative,
+			first_seen: now,
+			look_hits: 1,
 		}
 	}
 
-	pub fn update_contact(&mut self, contact: &RadarGetContactInfo) {
-		let dt = now() - self.last_update_timestamp;
+	pub fn update_contact(&mut self, contact: &RadarGetContactInfo, own_pos: Vector3, is_friendly: bool, now: f32) {
+		let dt = now - self.last_update_timestamp;
 		let new_pos = Vector3::new(contact.x, contact.y, contact.z);
 
-		self.velocity = (new_pos - self.position) / dt;
-		self.position = new_pos;
+		self.filter.predict(dt);
+		self.filter.update(new_pos);
 
-		self.last_update_timestamp = now();
+		self.position = self.filter.position();
+		self.velocity = self.filter.velocity();
+		self.acceleration = self.filter.acceleration();
 
-		if is_position_friendly(self.position) {
-			self.last_allied_hit_time = now();
+		self.last_update_timestamp = now;
+		self.range = (self.position - own_pos).length();
+
+		if is_friendly {
+			self.last_allied_hit_time = now;
 		}
 
-		if now() - self.last_allied_hit_time > 2.0 {
+		if now - self.last_allied_hit_time > 2.0 {
 			self.is_allied = false;
 		}
+
+		let look = self.look_index(now);
+		if look < u32::BITS {
+			self.look_hits |= 1 << look;
+		}
+		self.update_state(now);
 	}
 
-	pub fn get_current_position(&self) -> Vector3 {
-		let dt = now() - self.last_update_timestamp;
-		return self.position + self.velocity * dt;
+	fn look_index(&self, now: f32) -> u32 {
+		((now - self.first_seen) / TWS_UPDATE_INTERVAL) as u32
 	}
 
-	pub fn time_since_last_update(&self) -> f32 {
-		now() - self.last_update_timestamp
+	// Moves the track along its lifecycle, returns the new state
+	pub fn update_state(&mut self, now: f32) -> TrackLifecycle {
+		let age = self.time_since_last_update(now);
+		self.state = match self.state {
+			TrackLifecycle::Tentative => {
+				if self.look_hits.count_ones() >= CONFIRM_HITS {
+					TrackLifecycle::Confirmed
+				} else if self.look_index(now) >= CONFIRM_LOOKS {
+					TrackLifecycle::Dropped
+				} else {
+					TrackLifecycle::Tentative
+				}
+			}
+			TrackLifecycle::Confirmed | TrackLifecycle::Coasting => {
+				if age > TWS_MAX_AGE {
+					TrackLifecycle::Dropped
+				} else if age > COAST_AGE {
+					TrackLifecycle::Coasting
+				} else {
+					TrackLifecycle::Confirmed
+				}
+			}
+			TrackLifecycle::Dropped => TrackLifecycle::Dropped,
+		};
+
+		self.state
 	}
 
-	pub fn dist(&self) -> f32 {
-		let ship_pos: Vector3 = vehicle_get_position().into();
-		(ship_pos - self.get_current_position()).length()
+	// Confirmed or coasting, anything worth acting on
+	pub fn is_established(&self) -> bool {
+		self.state == TrackLifecycle::Confirmed || self.state == TrackLifecycle::Coasting
+	}
+
+	pub fn get_current_position(&self, now: f32) -> Vector3 {
+		let dt = now - self.last_update_timestamp;
+		self.position + self.velocity * dt + self.acceleration * (0.5 * dt * dt)
+	}
+
+	pub fn get_current_velocity(&self, now: f32) -> Vector3 {
+		let dt = now - self.last_update_timestamp;
+		self.velocity + self.acceleration * dt
+	}
+
+	// 1 sigma radius of where the track is now, grows while it isn't being updated
+	pub fn position_uncertainty(&self, now: f32) -> f32 {
+		let variance = self.filter.predicted(now - self.last_update_timestamp).position_variance();
+		(variance.x + variance.y + variance.z).sqrt()
+	}
+
+	pub fn velocity_uncertainty(&self, now: f32) -> f32 {
+		let variance = self.filter.predicted(now - self.last_update_timestamp).velocity_variance();
+		(variance.x + variance.y + variance.z).sqrt()
+	}
+
+	pub fn time_since_last_update(&self, now: f32) -> f32 {
+		now - self.last_update_timestamp
+	}
+
+	pub fn dist(&self, own_pos: Vector3, now: f32) -> f32 {
+		(own_pos - self.get_current_position(now)).length()
 	}
 }
 
@@ -108,116 +242,163 @@ impl std::fmt::Display for RadarTrack {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		let id = self.id.to_string()[..4].to_string();
 		// let dist = self.dist().to_stri;
-		write!(f, "RT({}, {}, {:.2})", ctn(self.rc_type), id, self.dist() / 1000.0)
+		write!(f, "RT({}, {}, {:.2})", ctn(self.rc_type), id, self.range / 1000.0)
 	}
 }
 
+type TrackCallback = Box<dyn FnMut(&RadarTrack)>;
+type AmbiguityCallback = Box<dyn FnMut(&AmbiguousAssociation)>;
+
 const IFF_MARKS: bool = true;
 pub struct RadarController {
-	current_scan_index: usize,
+	hw: Rc<dyn Hardware>,
+	clock: Rc<dyn Clock>,
+
 	scan_pattern: RadarScanPattern,
+	scheduler: RadarScheduler,
 
 	tracks: Vec<RadarTrack>,
 	pub mode: RadarMode,
 
-	tws_in_row: i32,
+	association: AssociationStrategy,
+	ambiguous_associations: u32,
+	next_local_id: i64,
+
 	dl_update_times: HashMap<i64, f32>,
 
 	track_markers: HashMap<i64, UpdatableSphere>,
+	drop_callbacks: Vec<TrackCallback>,
+	ambiguity_callbacks: Vec<AmbiguityCallback>,
 }
 
 impl RadarController {
-	fn new() -> RadarController {
+	pub fn new(hw: Rc<dyn Hardware>, clock: Rc<dyn Clock>) -> RadarController {
 		RadarController {
-			current_scan_index: 0,
-			scan_pattern: RadarScanPattern::new(),
+			hw,
+			clock,
+
+			scan_pattern: RadarScanPattern::full_sphere(),
 			tracks: Vec::new(),
+			scheduler: RadarScheduler::new(),
 			mode: RadarMode::TWS,
-			tws_in_row: 0,
+
+			association: AssociationStrategy::ContactId,
+			ambiguous_associations: 0,
+			next_local_id: 0,
 
 			dl_update_times: HashMap::new(),
 			track_markers: HashMap::new(),
+			drop_callbacks: Vec::new(),
+			ambiguity_callbacks: Vec::new(),
 		}
 	}
 
-	fn update(&mut self) {
-		// Grab and update contacts seen last tick
-		let mut contacts: Vec<RadarGetContactInfo> = Vec::new();
-		radar_get_contacts(&mut contacts);
-		contacts.iter().for_each(|f| {
-			let t = self.update_contact(f);
-			self.update_track_marker(t.id, t.is_allied, t.position);
-		});
+	// Called with the final state of every track that gets dropped
+	pub fn on_track_dropped(&mut self, callback: impl FnMut(&RadarTrack) + 'static) {
+		self.drop_callbacks.push(Box::new(callback));
+	}
 
-		self.point_radar();
+	// Called for every return that had more than one plausible match, only gated strategies report these
+	pub fn on_ambiguous_association(&mut self, callback: impl FnMut(&AmbiguousAssociation) + 'static) {
+		self.ambiguity_callbacks.push(Box::new(callback));
+	}
 
-		radar_trigger();
+	// How returns are matched to tracks. Matching on contact id is only as good as the ids, gating on
+	// where the track should be keeps working when they are reused, hidden or jammed
+	pub fn set_association(&mut self, strategy: AssociationStrategy) {
+		self.association = strategy;
 	}
 
-	fn point_radar(&mut self) {
-		match self.mode {
-			RadarMode::STT(stt_id) => self.point_radar_for_stt(stt_id),
-			RadarMode::TWS => self.point_radar_for_tws(),
-			RadarMode::RWS => self.point_radar_for_rws(),
-		}
+	pub fn association(&self) -> AssociationStrategy {
+		self.association
 	}
 
-	fn point_radar_for_tws(&mut self) {
-		if self.tws_in_row >= 1 {
-			self.point_radar_for_rws();
-			self.tws_in_row = 0;
-			return;
-		}
+	pub fn ambiguous_associations(&self) -> u32 {
+		self.ambiguous_associations
+	}
 
-		// Find tracks that need an update
-		let tracks_to_update = self
-			.tracks
-			.iter()
-			.filter(|t| t.time_since_last_update() > TWS_UPDATE_INTERVAL && t.time_since_last_update() < TWS_MAX_AGE);
+	// Search somewhere else from now on, the new pattern starts from its beginning
+	pub fn set_scan_pattern(&mut self, spec: ScanPatternSpec) {
+		self.scan_pattern = RadarScanPattern::new(spec);
+	}
+
+	pub fn scan_pattern(&self) -> &RadarScanPattern {
+		&self.scan_pattern
+	}
 
-		// Choose a random one
-		let opt_track = tracks_to_update.choose(&mut rand::thread_rng());
-		match opt_track {
-			Some(track) => {
-				self.point_radar_direction(Vector3::from(vehicle_get_position()) - track.get_current_position());
+	// Renewed by the caller for as long as it wants the looks, see RadarScheduler
+	pub fn request_dwell(&mut self, request: DwellRequest) {
+		self.scheduler.request(request, self.clock.now());
+	}
 
-				radar_set_angle(2.0);
-				self.tws_in_row += 1;
-				// self.tws_line = Some(debug_line(Vector3::from(vehicle_get_position()), track.position, 0.0, 1.0, 1.0));
-			}
-			None => {
-				self.point_radar_for_rws();
-				self.tws_in_row = 0;
+	pub fn set_search_fraction(&mut self, fraction: f32) {
+		self.scheduler.set_search_fraction(fraction);
+	}
+
+	pub fn scheduler(&self) -> &RadarScheduler {
+		&self.scheduler
+	}
+
+	pub fn update(&mut self, datalink: &mut Datalink) {
+		// Grab and update contacts seen last tick
+		let mut contacts: Vec<RadarGetContactInfo> = Vec::new();
+		self.hw.radar_get_contacts(&mut contacts);
+
+		let association = associate(self.association, &contacts, &self.tracks, self.clock.now());
+		for ambiguous in association.ambiguous.iter() {
+			self.ambiguous_associations += 1;
+			for callback in self.ambiguity_callbacks.iter_mut() {
+				callback(ambiguous);
 			}
 		}
-	}
 
-	fn point_radar_for_stt(&mut self, stt_id: u32) {
-		if let Some(track) = self.get_contact_cr(stt_id) {
-			let time = track.time_since_last_update();
-			if time > 1.0 {
-				self.point_radar_for_rws();
-			} else {
-				self.point_radar_direction(track.get_current_position() - vehicle_get_position().into());
+		contacts.iter().zip(association.matches).for_each(|(f, track_id)| {
+			let t = self.update_contact(f, track_id, datalink);
+			self.update_track_marker(t.id, t.is_allied, t.position, datalink.id());
+		});
+
+		self.update_track_states(datalink);
+		self.request_datalink_cues(datalink);
+
+		self.point_radar();
 
-				// Increase angle longer not detected
-				let angle = lerp(0.0, 90.0, time);
-				radar_set_angle(angle);
+		self.hw.radar_trigger();
+	}
+
+	fn point_radar(&mut self) {
+		let own_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let now = self.clock.now();
+		match self.scheduler.next_dwell(&self.mode, &self.tracks, own_pos, now) {
+			Dwell::Search => self.point_radar_for_rws(),
+			Dwell::Track { target, beam_width, .. } => {
+				self.point_radar_direction(own_pos - target);
+				self.hw.radar_set_angle(beam_width);
 			}
-		} else {
-			self.point_radar_for_rws();
+			Dwell::Stt(contact_id) => self.point_radar_for_stt(contact_id),
 		}
 	}
 
+	fn point_radar_for_stt(&mut self, contact_id: i64) {
+		let own_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let now = self.clock.now();
+		let track = get!(self.get_contact(contact_id));
+		let time = track.time_since_last_update(now);
+		self.point_radar_direction(own_pos - track.get_current_position(now));
+
+		// Increase angle longer not detected
+		let angle = lerp(0.0, 90.0, time);
+		self.hw.radar_set_angle(angle);
+	}
+
 	fn point_radar_for_rws(&mut self) {
-		let dir = self.scan_pattern.get_point(self.current_scan_index);
-		self.point_radar_direction(dir);
-		radar_set_angle(40.0);
-		self.current_scan_index = (self.current_scan_index + 1) % self.scan_pattern.size;
+		// Pointing takes (own - target), the opposite of where the beam looks
+		let dir = self.scan_pattern.next_direction();
+		self.point_radar_direction(-dir);
+		self.hw.radar_set_angle(self.scan_pattern.beam_width());
 	}
 
 	fn point_radar_direction(&self, dir: Vector3) {
-		let orientation: Quaternion = vehicle_get_orientation().into();
+		let orientation: Quaternion = self.hw.vehicle_get_orientation().into();
 		let local_dir = orientation.invert() * dir;
 
 		let turret_rotation = Quaternion::from_axis_angle(&AxisAngle {
@@ -230,32 +411,88 @@ impl RadarController {
 
 		let angles = radar_dir.angles();
 
-		radar_set_bearing(angles.bearing);
-		radar_set_elevation(angles.elevation);
+		self.hw.radar_set_bearing(angles.bearing);
+		self.hw.radar_set_elevation(angles.elevation);
 	}
 
-	fn update_contact(&mut self, contact: &RadarGetContactInfo) -> RadarTrack {
-		let ut: RadarTrack = if let Some(track) = self.get_mut_contact(contact.id) {
-			track.update_contact(contact);
+	fn update_contact(&mut self, contact: &RadarGetContactInfo, track_id: Option<i64>, datalink: &mut Datalink) -> RadarTrack {
+		let own_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let is_friendly = datalink.is_position_friendly(Vector3::new(contact.x, contact.y, contact.z));
+		let now = self.clock.now();
+
+		let ut: RadarTrack = if let Some(track) = track_id.and_then(|id| self.get_mut_contact(id)) {
+			track.update_contact(contact, own_pos, is_friendly, now);
 			// if contact.target_type == RadarTargetType::Missile && own_dl_id() > 0 {
 			// debug_pause();
 			// }
 
-			track.clone()
+			*track
 		} else {
-			let track = RadarTrack::new(contact);
-			self.track_markers.insert(track.id, UpdatableSphere::new());
+			let mut track = RadarTrack::new(contact, own_pos, now);
+			// A return that didn't associate with the track already using its id gets an id of its own
+			if self.get_contact(contact.id).is_some() {
+				self.next_local_id += 1;
+				track.id = ((LOCAL_ID_BASE + self.next_local_id) << 32) | self.next_local_id;
+			}
+			self.track_markers.insert(track.id, UpdatableSphere::new(self.hw.clone()));
 			self.tracks.push(track);
-			track.clone()
+			track
 		};
 
-		self.maybe_update_dl_track(&ut);
+		self.maybe_update_dl_track(&ut, datalink);
 
 		ut
 	}
 
-	fn update_track_marker(&mut self, id: i64, is_allied: bool, pos: Vector3) {
-		if !IFF_MARKS || own_dl_id() != 0 {
+	// Hostiles others report that our radar doesn't hold get a look now and then, so we pick them up too
+	fn request_datalink_cues(&mut self, datalink: &Datalink) {
+		let own_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let now = self.clock.now();
+		for track in datalink.get_tracks() {
+			let requester = DwellRequester::Datalink(track.track_id);
+			let position = track.get_current_position(now);
+			let wanted = track.last_update_timestamp != f32::MAX
+				&& !track.is_allied
+				&& matches!(track.contact_type, RadarTargetType::Missile | RadarTargetType::SpaceBattleShip)
+				&& (position - own_pos).length() < DL_CUE_RANGE
+				&& self.get_contact_cr(track.contact_id).is_none();
+			if wanted {
+				self
+					.scheduler
+					.request(DwellRequest::new(DwellTarget::Position(position), requester, DL_CUE_INTERVAL, 0.0), now);
+			} else {
+				self.scheduler.cancel(requester);
+			}
+		}
+	}
+
+	fn update_track_states(&mut self, datalink: &mut Datalink) {
+		let now = self.clock.now();
+		for track in self.tracks.iter_mut() {
+			track.update_state(now);
+		}
+
+		let dropped: Vec<RadarTrack> = self.tracks.iter().filter(|t| t.state == TrackLifecycle::Dropped).copied().collect();
+		if dropped.is_empty() {
+			return;
+		}
+
+		self.tracks.retain(|t| t.state != TrackLifecycle::Dropped);
+		for track in dropped {
+			// Only tracks we told the datalink about need retracting
+			if self.dl_update_times.remove(&track.id).is_some() {
+				datalink.drop_local_track(track.id);
+			}
+			self.track_markers.remove(&track.id);
+
+			for callback in self.drop_callbacks.iter_mut() {
+				callback(&track);
+			}
+		}
+	}
+
+	fn update_track_marker(&mut self, id: i64, is_allied: bool, pos: Vector3, own_dl_id: u8) {
+		if !IFF_MARKS || own_dl_id != 0 {
 			return;
 		}
 
@@ -269,26 +506,36 @@ impl RadarController {
 		}
 	}
 
-	fn maybe_update_dl_track(&mut self, track: &RadarTrack) {
+	fn maybe_update_dl_track(&mut self, track: &RadarTrack, datalink: &mut Datalink) {
 		match track.rc_type {
 			RadarTargetType::SpaceBattleShip | RadarTargetType::Missile => {
-				if (track.rc_type == RadarTargetType::Missile && own_dl_id() > 0) || track.is_allied {
+				// Missiles only share missile tracks they are locked on to, that lock helps the ship's fire control
+				let is_stt_target = matches!(self.mode, RadarMode::STT(stt_id) if stt_id == dl_crunch_id(track.id));
+				if (track.rc_type == RadarTargetType::Missile && datalink.id() > 0 && !is_stt_target) || track.is_allied || !track.is_established() {
 					return;
 				}
-				let own_pos: Vector3 = vehicle_get_position().into();
-				let dist = (own_pos - track.get_current_position()).length();
-				let last_update = self.dl_update_times.get(&track.id).unwrap_or(&0.0);
-				if now() - last_update > DL_UPDATE_RATE || dist < 3000.0 {
-					let track_id: u16 = get!(dl_net_id(track.id));
-					let info_packet = TrackInfo::new(track_id, dl_crunch_id(track.id), track.rc_type, track.is_allied);
-					let pos_packet = TrackPosition::new(track_id, track.position);
-					let vel_packet = TrackVelocity::new(track_id, track.velocity);
 
-					send_message(Message::TrackInfo(info_packet));
-					send_message(Message::TrackPosition(pos_packet));
-					send_message(Message::TrackVelocity(vel_packet));
+				datalink.update_track_from_local_data(track.id, track.rc_type, track.position, track.velocity, track.range);
 
-					self.dl_update_times.insert(track.id, now());
+				let own_pos: Vector3 = self.hw.vehicle_get_position().into();
+				let now = self.clock.now();
+				let dist = (own_pos - track.get_current_position(now)).length();
+				let last_update = self.dl_update_times.get(&track.id).unwrap_or(&0.0);
+				let update_rate = if datalink.is_saturated() { DL_SATURATED_UPDATE_RATE } else { DL_UPDATE_RATE };
+				if now - last_update > update_rate || dist < 3000.0 {
+					let track_id: u16 = get!(datalink.net_id(track.id));
+					let state = track_state::TrackState::new(
+						track_id,
+						dl_crunch_id(track.id),
+						track.rc_type,
+						track.is_allied,
+						datalink.id(),
+						track.position,
+						track.velocity,
+					);
+					datalink.send_framed(FramedMessage::TrackState(state));
+
+					self.dl_update_times.insert(track.id, now);
 				}
 			}
 			_ => {}
@@ -299,7 +546,7 @@ impl RadarController {
 		self.tracks.iter_mut().find(|f| f.id == id)
 	}
 
-	fn get_contact(&self, id: i64) -> Option<&RadarTrack> {
+	pub fn get_contact(&self, id: i64) -> Option<&RadarTrack> {
 		self.tracks.iter().find(|f| f.id == id)
 	}
 
@@ -308,34 +555,56 @@ impl RadarController {
 	}
 
 	pub fn get_nearest_ship(&self) -> Option<&RadarTrack> {
+		let own_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let now = self.clock.now();
 		self
 			.tracks
 			.iter()
-			.filter(|f| f.rc_type == RadarTargetType::SpaceBattleShip)
-			.min_by(|a, b| a.dist().partial_cmp(&b.dist()).unwrap())
+			.filter(|f| f.rc_type == RadarTargetType::SpaceBattleShip && f.is_established())
+			.min_by(|a, b| a.dist(own_pos, now).partial_cmp(&b.dist(own_pos, now)).unwrap())
 	}
-}
 
-thread_local! {
-	static RADAR: RefCell<RadarController> = RefCell::new(RadarController::new());
-}
+	pub fn get_tracks(&self) -> Vec<RadarTrack> {
+		self.tracks.clone()
+	}
 
-pub fn radar_update() {
-	RADAR.with(|radar| radar.borrow_mut().update());
+	pub fn get_established_tracks(&self) -> Vec<RadarTrack> {
+		self.tracks.iter().filter(|t| t.is_established()).copied().collect()
+	}
 }
 
-pub fn radar_get_contact(id: i64) -> Option<RadarTrack> {
-	RADAR.with(|radar| radar.borrow().get_contact(id).map(|f| *f))
-}
+// Tracks for the scheduler and association tests, fed returns the way the radar would
+#[cfg(test)]
+pub mod test_tracks {
+	use super::*;
+
+	pub fn contact(id: i64, target_type: RadarTargetType, position: Vector3) -> RadarGetContactInfo {
+		RadarGetContactInfo {
+			id,
+			target_type,
+			signal_strength: 1.0,
+			x: position.x,
+			y: position.y,
+			z: position.z,
+		}
+	}
 
-pub fn get_radar_tracks() -> Vec<RadarTrack> {
-	RADAR.with(|radar| radar.borrow().tracks.clone())
-}
+	// Hostile, seen on each of the last CONFIRM_HITS looks up to now moving at a steady velocity
+	pub fn confirmed_track(id: i64, rc_type: RadarTargetType, position: Vector3, velocity: Vector3, now: f32) -> RadarTrack {
+		let at = |look: u32| {
+			let t = now - (CONFIRM_HITS - 1 - look) as f32 * TWS_UPDATE_INTERVAL;
+			(position + velocity * (t - now), t)
+		};
 
-pub fn get_nearest_ship() -> Option<RadarTrack> {
-	RADAR.with(|radar| radar.borrow().get_nearest_ship().map(|f| *f))
-}
+		let (first, first_time) = at(0);
+		let mut track = RadarTrack::new(&contact(id, rc_type, first), Vector3::zero(), first_time);
+		for look in 1..CONFIRM_HITS {
+			let (position, t) = at(look);
+			track.update_contact(&contact(id, rc_type, position), Vector3::zero(), false, t);
+		}
+		track.is_allied = false;
 
-pub fn set_radar_mode(mode: RadarMode) {
-	RADAR.with(|radar| radar.borrow_mut().mode = mode);
+		assert!(track.is_established());
+		track
+	}
 }
@@ -0,0 +1,371 @@
+use protologic_core::radar::RadarTargetType;
+
+use crate::{
+	datalink::datalink::dl_crunch_id,
+	math::{
+		utils::{deg, lerp},
+		vector3::Vector3,
+	},
+};
+
+use super::radar_controller::{RadarMode, RadarTrack, TrackLifecycle, COAST_AGE, TWS_UPDATE_INTERVAL};
+
+const DEFAULT_SEARCH_FRACTION: f32 = 0.5; // What the old one TWS look, one RWS look alternation gave search
+const SEARCH_CREDIT_WINDOW: f32 = 2.0; // Search looks that can be saved up while tracks are busy
+
+// Revisit intervals, seconds
+const MIN_REVISIT: f32 = 0.1; // A missile about to arrive
+const MAX_REVISIT: f32 = COAST_AGE * 0.7; // Anything else, often enough it never starts coasting
+const THREAT_HORIZON: f32 = 30.0; // Time to go at which a closing missile is no more urgent than any other track
+
+// Track looks use a beam just wide enough for where the track could be by now
+const MIN_TRACK_BEAM: f32 = 2.0; // Degrees
+const MAX_TRACK_BEAM: f32 = 20.0;
+const UNCERTAINTY_SIGMAS: f32 = 3.0;
+
+const STT_LOST_TIME: f32 = 1.0; // A lock not seen for this long goes back to searching
+const CUE_BEAM: f32 = 5.0; // Looking for something another vehicle reported
+const REQUEST_LIFETIME: f32 = 1.0; // Requests have to be renewed, whoever made one may be gone
+
+#[derive(Clone, Copy, Debug)]
+pub enum DwellTarget {
+	Contact(i64),      // One of our radar tracks
+	Position(Vector3), // Somewhere we have no track of our own, e.g. a datalink report
+}
+
+// Who wants a look, a newer request from the same one replaces the older
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DwellRequester {
+	Turret(u8),
+	Intercept(u8), // Interceptor dl_id
+	Datalink(u16), // Track id
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DwellRequest {
+	pub target: DwellTarget,
+	pub requester: DwellRequester,
+	pub interval: f32, // Look at least this often
+	pub priority: f32, // Added to how overdue the look is when picking between them
+	expires: f32,
+	last_served: f32,
+}
+
+impl DwellRequest {
+	pub fn new(target: DwellTarget, requester: DwellRequester, interval: f32, priority: f32) -> DwellRequest {
+		DwellRequest {
+			target,
+			requester,
+			interval,
+			priority,
+			expires: 0.0,
+			last_served: f32::MIN,
+		}
+	}
+}
+
+// What the radar does with one look
+#[derive(Clone, Copy, Debug)]
+pub enum Dwell {
+	Search,
+	Track {
+		target: Vector3,
+		beam_width: f32,
+		contact_id: Option<i64>,
+	},
+	Stt(i64),
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+pub struct DwellCounts {
+	pub search: u32,
+	pub track: u32,
+	pub stt: u32,
+	pub requested: u32, // Track looks that were for a request
+}
+
+// Decides what every radar look is spent on. A share of them is kept for search whatever else is going on,
+// the rest go to whichever track is most overdue for its revisit. Closing missiles are revisited most
+// often, and consumers can ask for looks of their own
+pub struct RadarScheduler {
+	search_fraction: f32,
+	search_credit: f32,
+	requests: Vec<DwellRequest>,
+	counts: DwellCounts,
+	last_dwell: Option<Dwell>,
+}
+
+impl Default for RadarScheduler {
+	fn default() -> Self {
+		RadarScheduler::new()
+	}
+}
+
+impl RadarScheduler {
+	pub fn new() -> RadarScheduler {
+		RadarScheduler {
+			search_fraction: DEFAULT_SEARCH_FRACTION,
+			search_credit: 0.0,
+			requests: Vec::new(),
+			counts: DwellCounts::default(),
+			last_dwell: None,
+		}
+	}
+
+	pub fn set_search_fraction(&mut self, fraction: f32) {
+		self.search_fraction = fraction.clamp(0.0, 1.0);
+	}
+
+	pub fn search_fraction(&self) -> f32 {
+		self.search_fraction
+	}
+
+	pub fn request(&mut self, mut request: DwellRequest, now: f32) {
+		request.expires = now + REQUEST_LIFETIME;
+		match self.requests.iter_mut().find(|r| r.requester == request.requester) {
+			Some(existing) => {
+				request.last_served = existing.last_served;
+				*existing = request;
+			}
+			None => self.requests.push(request),
+		}
+	}
+
+	pub fn cancel(&mut self, requester: DwellRequester) {
+		self.requests.retain(|r| r.requester != requester);
+	}
+
+	pub fn requests(&self) -> &[DwellRequest] {
+		&self.requests
+	}
+
+	pub fn counts(&self) -> DwellCounts {
+		self.counts
+	}
+
+	pub fn last_dwell(&self) -> Option<Dwell> {
+		self.last_dwell
+	}
+
+	pub fn next_dwell(&mut self, mode: &RadarMode, tracks: &[RadarTrack], own_pos: Vector3, now: f32) -> Dwell {
+		self.requests.retain(|r| r.expires > now);
+
+		let dwell = match mode {
+			RadarMode::RWS => Dwell::Search,
+			// A lock gets every look while it holds
+			RadarMode::STT(stt_id) => match tracks.iter().find(|t| dl_crunch_id(t.id) == *stt_id) {
+				Some(track) if track.time_since_last_update(now) <= STT_LOST_TIME => Dwell::Stt(track.id),
+				_ => Dwell::Search,
+			},
+			RadarMode::TWS => self.next_tws_dwell(tracks, own_pos, now),
+		};
+
+		match dwell {
+			Dwell::Search => self.counts.search += 1,
+			Dwell::Track { .. } => self.counts.track += 1,
+			Dwell::Stt(_) => self.counts.stt += 1,
+		}
+		self.last_dwell = Some(dwell);
+
+		dwell
+	}
+
+	fn next_tws_dwell(&mut self, tracks: &[RadarTrack], own_pos: Vector3, now: f32) -> Dwell {
+		self.search_credit = (self.search_credit + self.search_fraction).min(SEARCH_CREDIT_WINDOW);
+		if self.search_credit >= 1.0 {
+			self.search_credit -= 1.0;
+			return Dwell::Search;
+		}
+
+		// (urgency, request index, dwell)
+		let mut best: Option<(f32, Option<usize>, Dwell)> = None;
+		let mut consider = |urgency: f32, request: Option<usize>, dwell: Dwell| {
+			if best.as_ref().is_none_or(|(b, _, _)| urgency > *b) {
+				best = Some((urgency, request, dwell));
+			}
+		};
+
+		for track in tracks.iter().filter(|t| t.state != TrackLifecycle::Dropped) {
+			let request = self
+				.requests
+				.iter()
+				.enumerate()
+				.filter(|(_, r)| matches!(r.target, DwellTarget::Contact(id) if id == track.id))
+				.max_by(|(_, a), (_, b)| a.priority.total_cmp(&b.priority));
+
+			let mut interval = revisit_interval(track, own_pos, now);
+			let mut priority = 0.0;
+			if let Some((_, r)) = request {
+				interval = interval.min(r.interval);
+				priority = r.priority;
+			}
+
+			// Overdue, or it's drifted far enough that it's about to slip out of the widest beam we'd use
+			let age = track.time_since_last_update(now);
+			let beam_width = track_beam_width(track, own_pos, now);
+			if age < interval && beam_width < MAX_TRACK_BEAM {
+				continue;
+			}
+
+			let dwell = Dwell::Track {
+				target: track.get_current_position(now),
+				beam_width,
+				contact_id: Some(track.id),
+			};
+			consider(age / interval + priority, request.map(|(i, _)| i), dwell);
+		}
+
+		for (i, request) in self.requests.iter().enumerate() {
+			let position = match request.target {
+				DwellTarget::Position(position) => position,
+				DwellTarget::Contact(_) => continue,
+			};
+
+			let since = now - request.last_served;
+			if since < request.interval {
+				continue;
+			}
+
+			let dwell = Dwell::Track {
+				target: position,
+				beam_width: CUE_BEAM,
+				contact_id: None,
+			};
+			consider((since / request.interval).min(2.0) + request.priority, Some(i), dwell);
+		}
+
+		// Nothing is due, the look goes to search without using up the reserved share
+		let (_, request, dwell) = match best {
+			Some(best) => best,
+			None => return Dwell::Search,
+		};
+
+		if let Some(i) = request {
+			self.requests[i].last_served = now;
+			self.counts.requested += 1;
+		}
+
+		dwell
+	}
+}
+
+// How long a track can go between looks. Tentative tracks need a look every TWS_UPDATE_INTERVAL to get
+// confirmed, a missile coming at us is looked at more often the sooner it arrives
+pub fn revisit_interval(track: &RadarTrack, own_pos: Vector3, now: f32) -> f32 {
+	if !track.is_established() {
+		return TWS_UPDATE_INTERVAL;
+	}
+	if track.is_allied {
+		return MAX_REVISIT;
+	}
+
+	let offset = track.get_current_position(now) - own_pos;
+	let range = offset.length().max(1.0);
+	let closing_speed = -offset.dot(&track.get_current_velocity(now)) / range;
+
+	match track.rc_type {
+		RadarTargetType::Missile if closing_speed > 0.0 => {
+			let time_to_go = range / closing_speed;
+			lerp(MIN_REVISIT, TWS_UPDATE_INTERVAL, (time_to_go / THREAT_HORIZON).min(1.0))
+		}
+		RadarTargetType::Missile | RadarTargetType::SpaceBattleShip => TWS_UPDATE_INTERVAL,
+		_ => MAX_REVISIT,
+	}
+}
+
+// Degrees, enough to still catch the track wherever it could have got to since the last look
+pub fn track_beam_width(track: &RadarTrack, own_pos: Vector3, now: f32) -> f32 {
+	let range = track.dist(own_pos, now).max(1.0);
+	let spread = UNCERTAINTY_SIGMAS * track.position_uncertainty(now);
+	(2.0 * deg((spread / range).atan())).clamp(MIN_TRACK_BEAM, MAX_TRACK_BEAM)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::controllers::radar_controller::test_tracks::{confirmed_track, contact};
+
+	use super::*;
+
+	const LOOK: f32 = 0.05; // Time between dwells
+
+	#[test]
+	fn search_gets_its_fraction_of_the_looks() {
+		// Far more tracks than there are looks for, every one of them always overdue
+		let tracks: Vec<RadarTrack> = (0..50)
+			.map(|i| {
+				confirmed_track(
+					i,
+					RadarTargetType::SpaceBattleShip,
+					Vector3::new(5000.0, i as f32 * 100.0, 0.0),
+					Vector3::zero(),
+					0.0,
+				)
+			})
+			.collect();
+
+		for fraction in [0.0, 0.25, 0.5, 0.8] {
+			let mut scheduler = RadarScheduler::new();
+			scheduler.set_search_fraction(fraction);
+			for look in 0..1000 {
+				scheduler.next_dwell(&RadarMode::TWS, &tracks, Vector3::zero(), 10.0 + look as f32 * LOOK);
+			}
+
+			let counts = scheduler.counts();
+			assert!(
+				(counts.search as f32 - fraction * 1000.0).abs() <= 1.0,
+				"Search got {} of 1000 looks at {}",
+				counts.search,
+				fraction
+			);
+			assert_eq!(counts.search + counts.track, 1000);
+		}
+	}
+
+	#[test]
+	fn requests_expire_unless_renewed() {
+		let mut scheduler = RadarScheduler::new();
+		scheduler.set_search_fraction(0.0);
+		let target = DwellTarget::Position(Vector3::new(0.0, 8000.0, 0.0));
+		scheduler.request(DwellRequest::new(target, DwellRequester::Datalink(3), 0.2, 0.0), 0.0);
+
+		let dwell = scheduler.next_dwell(&RadarMode::TWS, &[], Vector3::zero(), REQUEST_LIFETIME * 0.5);
+		assert!(matches!(dwell, Dwell::Track { contact_id: None, .. }), "{:?}", dwell);
+		assert_eq!(scheduler.requests().len(), 1);
+
+		let dwell = scheduler.next_dwell(&RadarMode::TWS, &[], Vector3::zero(), REQUEST_LIFETIME + LOOK);
+		assert!(matches!(dwell, Dwell::Search), "{:?}", dwell);
+		assert!(scheduler.requests().is_empty());
+		assert_eq!(scheduler.counts().requested, 1);
+	}
+
+	#[test]
+	fn closing_missile_is_revisited_more_than_a_crossing_one() {
+		let closing_velocity = Vector3::new(-300.0, 0.0, 0.0);
+		let crossing_velocity = Vector3::new(0.0, 300.0, 0.0);
+		let start = [Vector3::new(6000.0, 0.0, 0.0), Vector3::new(0.0, 0.0, 6000.0)];
+		let velocity = [closing_velocity, crossing_velocity];
+		let truth = |i: usize, now: f32| start[i] + velocity[i] * now;
+		let mut tracks: Vec<RadarTrack> = (0..2)
+			.map(|i| confirmed_track(i as i64, RadarTargetType::Missile, truth(i, 0.0), velocity[i], 0.0))
+			.collect();
+
+		let mut scheduler = RadarScheduler::new();
+		let mut looks = [0; 2];
+		for step in 1..=200 {
+			let now = step as f32 * LOOK;
+			if let Dwell::Track { contact_id: Some(id), .. } = scheduler.next_dwell(&RadarMode::TWS, &tracks, Vector3::zero(), now) {
+				let i = id as usize;
+				tracks[i].update_contact(&contact(id, RadarTargetType::Missile, truth(i, now)), Vector3::zero(), false, now);
+				looks[i] += 1;
+			}
+		}
+
+		assert!(
+			looks[0] as f32 > looks[1] as f32 * 1.5,
+			"Closing missile got {} looks, crossing one {}",
+			looks[0],
+			looks[1]
+		);
+		assert!(looks[1] > 0);
+	}
+}
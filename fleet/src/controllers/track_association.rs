@@ -0,0 +1,238 @@
+use protologic_core::radar::RadarGetContactInfo;
+
+use crate::math::{assignment::min_cost_assignment, vector3::Vector3};
+
+use super::radar_controller::{RadarTrack, TrackLifecycle};
+
+pub const GATE: f32 = 11.34; // Squared Mahalanobis distance, chi-square 3 dof at 99%
+const AMBIGUITY_MARGIN: f32 = 4.0; // A second candidate this close to the best makes the choice a coin toss
+const OUTSIDE_GATE: f32 = 1.0e6; // Cost of pairs the gate rules out, never picked over a new track
+
+// How radar returns are matched to the tracks they update
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AssociationStrategy {
+	ContactId,              // Trust the simulator's ids
+	NearestNeighbour,       // Closest gated pairs first, one return per track
+	GlobalNearestNeighbour, // Lowest total distance over all returns at once
+}
+
+// A return that had more than one plausible match
+#[derive(Clone, Debug)]
+pub struct AmbiguousAssociation {
+	pub contact_id: i64,
+	pub track_id: Option<i64>, // What it was matched to, None started a new track
+	pub tracks: Vec<i64>,      // Tracks about as close to the return as the best one
+	pub contacts: Vec<i64>,    // Returns about as close to the matched track as this one, itself included
+}
+
+pub struct Association {
+	pub matches: Vec<Option<i64>>, // Track id per return, None for a new track
+	pub ambiguous: Vec<AmbiguousAssociation>,
+}
+
+// Squared Mahalanobis distance of a return from where the track should be now
+pub fn distance_sq(track: &RadarTrack, position: Vector3, now: f32) -> f32 {
+	let variance = track.filter.predicted(now - track.last_update_timestamp).innovation_variance();
+	let residual = position - track.get_current_position(now);
+	residual.x * residual.x / variance.x + residual.y * residual.y / variance.y + residual.z * residual.z / variance.z
+}
+
+pub fn associate(strategy: AssociationStrategy, contacts: &[RadarGetContactInfo], tracks: &[RadarTrack], now: f32) -> Association {
+	if strategy == AssociationStrategy::ContactId {
+		return Association {
+			matches: contacts.iter().map(|c| tracks.iter().find(|t| t.id == c.id).map(|t| t.id)).collect(),
+			ambiguous: Vec::new(),
+		};
+	}
+
+	// Gated distance per (return, track)
+	let distances: Vec<Vec<Option<f32>>> = contacts
+		.iter()
+		.map(|contact| {
+			let position = Vector3::new(contact.x, contact.y, contact.z);
+			tracks
+				.iter()
+				.map(|track| {
+					if track.state == TrackLifecycle::Dropped || track.rc_type != contact.target_type {
+						return None;
+					}
+					Some(distance_sq(track, position, now)).filter(|d| *d <= GATE)
+				})
+				.collect()
+		})
+		.collect();
+
+	let picks = match strategy {
+		AssociationStrategy::NearestNeighbour => nearest_neighbour(&distances, tracks.len()),
+		_ => global_nearest_neighbour(&distances, tracks.len()),
+	};
+
+	let ambiguous = picks
+		.iter()
+		.enumerate()
+		.filter_map(|(c, pick)| {
+			let best = distances[c].iter().flatten().copied().reduce(f32::min)?;
+			let close_tracks: Vec<i64> = (0..tracks.len())
+				.filter(|t| distances[c][*t].is_some_and(|d| d <= best + AMBIGUITY_MARGIN))
+				.map(|t| tracks[t].id)
+				.collect();
+			let close_contacts: Vec<i64> = match pick {
+				Some(t) => {
+					let own = distances[c][*t].unwrap();
+					(0..contacts.len())
+						.filter(|other| distances[*other][*t].is_some_and(|d| d <= own + AMBIGUITY_MARGIN))
+						.map(|other| contacts[other].id)
+						.collect()
+				}
+				None => vec![contacts[c].id],
+			};
+
+			if close_tracks.len() < 2 && close_contacts.len() < 2 {
+				return None;
+			}
+			Some(AmbiguousAssociation {
+				contact_id: contacts[c].id,
+				track_id: pick.map(|t| tracks[t].id),
+				tracks: close_tracks,
+				contacts: close_contacts,
+			})
+		})
+		.collect();
+
+	Association {
+		matches: picks.iter().map(|pick| pick.map(|t| tracks[t].id)).collect(),
+		ambiguous,
+	}
+}
+
+// Track index per return, closest pairs taken first
+fn nearest_neighbour(distances: &[Vec<Option<f32>>], track_count: usize) -> Vec<Option<usize>> {
+	let mut pairs: Vec<(f32, usize, usize)> = Vec::new();
+	for (c, row) in distances.iter().enumerate() {
+		for (t, d) in row.iter().enumerate() {
+			if let Some(d) = d {
+				pairs.push((*d, c, t));
+			}
+		}
+	}
+	pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+	let mut picks = vec![None; distances.len()];
+	let mut taken = vec![false; track_count];
+	for (_, c, t) in pairs {
+		if picks[c].is_none() && !taken[t] {
+			picks[c] = Some(t);
+			taken[t] = true;
+		}
+	}
+
+	picks
+}
+
+// Track index per return minimising the total distance. Every return also has a column of its own for
+// starting a new track, costed at the gate, so a far match is never forced just to make the sums work
+fn global_nearest_neighbour(distances: &[Vec<Option<f32>>], track_count: usize) -> Vec<Option<usize>> {
+	let contact_count = distances.len();
+	let costs: Vec<Vec<f32>> = distances
+		.iter()
+		.enumerate()
+		.map(|(c, row)| {
+			let mut costs: Vec<f32> = row.iter().map(|d| d.unwrap_or(OUTSIDE_GATE)).collect();
+			costs.extend((0..contact_count).map(|other| if other == c { GATE } else { OUTSIDE_GATE }));
+			costs
+		})
+		.collect();
+
+	min_cost_assignment(&costs)
+		.into_iter()
+		.enumerate()
+		.map(|(c, column)| {
+			if column < track_count && distances[c][column].is_some() {
+				Some(column)
+			} else {
+				None
+			}
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use protologic_core::radar::RadarTargetType;
+
+	use crate::controllers::radar_controller::test_tracks::{confirmed_track, contact};
+
+	use super::*;
+
+	const NOW: f32 = 10.0;
+
+	fn track_at(id: i64, x: f32) -> RadarTrack {
+		confirmed_track(id, RadarTargetType::Missile, Vector3::new(x, 0.0, 5000.0), Vector3::zero(), NOW)
+	}
+
+	// Standard deviation of a return around where a track should be, the same for every track made alike
+	fn sigma() -> f32 {
+		track_at(0, 0.0).filter.innovation_variance().x.sqrt()
+	}
+
+	fn contact_at(id: i64, x: f32) -> RadarGetContactInfo {
+		contact(id, RadarTargetType::Missile, Vector3::new(x, 0.0, 5000.0))
+	}
+
+	#[test]
+	fn returns_associate_only_inside_the_gate() {
+		let tracks = [track_at(1, 0.0)];
+		let edge = GATE.sqrt() * sigma();
+		for strategy in [AssociationStrategy::NearestNeighbour, AssociationStrategy::GlobalNearestNeighbour] {
+			let inside = associate(strategy, &[contact_at(7, edge * 0.95)], &tracks, NOW);
+			assert_eq!(inside.matches, vec![Some(1)], "{:?}", strategy);
+
+			let outside = associate(strategy, &[contact_at(7, edge * 1.05)], &tracks, NOW);
+			assert_eq!(outside.matches, vec![None], "{:?}", strategy);
+		}
+
+		let d = distance_sq(&tracks[0], Vector3::new(edge, 0.0, 5000.0), NOW);
+		assert!((d - GATE).abs() < 0.01, "Gate edge is {} away", d);
+	}
+
+	#[test]
+	fn returns_never_associate_with_another_type() {
+		let tracks = [track_at(1, 0.0)];
+		for strategy in [AssociationStrategy::NearestNeighbour, AssociationStrategy::GlobalNearestNeighbour] {
+			let ship = contact(7, RadarTargetType::SpaceBattleShip, Vector3::new(0.0, 0.0, 5000.0));
+			assert_eq!(associate(strategy, &[ship], &tracks, NOW).matches, vec![None], "{:?}", strategy);
+		}
+	}
+
+	#[test]
+	fn global_nearest_neighbour_beats_greedy_pairing() {
+		// In sigmas along x. Return 10 is closest to track 1, but taking it leaves return 11 without a track.
+		// Swapping it over to track 2 costs a little more for it and saves starting a new track for 11
+		let s = sigma();
+		let tracks = [track_at(1, 0.0), track_at(2, 2.5 * s)];
+		let contacts = [contact_at(10, 1.2 * s), contact_at(11, -1.5 * s)];
+
+		let nn = associate(AssociationStrategy::NearestNeighbour, &contacts, &tracks, NOW);
+		assert_eq!(nn.matches, vec![Some(1), None]);
+
+		let gnn = associate(AssociationStrategy::GlobalNearestNeighbour, &contacts, &tracks, NOW);
+		assert_eq!(gnn.matches, vec![Some(2), Some(1)]);
+
+		// Return 10 was about as close to either track
+		let ambiguous = gnn
+			.ambiguous
+			.iter()
+			.find(|a| a.contact_id == 10)
+			.expect("Return 10 wasn't flagged as ambiguous");
+		assert_eq!(ambiguous.tracks, vec![1, 2]);
+	}
+
+	#[test]
+	fn contact_id_strategy_goes_by_id_alone() {
+		let tracks = [track_at(1, 0.0)];
+		let far = contact_at(1, 100000.0);
+		let association = associate(AssociationStrategy::ContactId, &[far, contact_at(2, 0.0)], &tracks, NOW);
+		assert_eq!(association.matches, vec![Some(1), None]);
+		assert!(association.ambiguous.is_empty());
+	}
+}
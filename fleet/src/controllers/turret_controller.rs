@@ -1,22 +1,20 @@
-use std::f32::consts::PI;
-
-use protologic_core::{
-	constants::turret_shell_speed,
-	guns::{
-		gun_get_bearing, gun_get_elevation, gun_get_magazine_reloadtime, gun_get_magazine_remaining, gun_get_magazine_type, gun_get_refiretime, gun_reload,
-		gun_set_bearing, gun_set_elevation, gun_set_fuse, gun_trigger, AmmoType,
-	},
-	physics::{vehicle_get_orientation, vehicle_get_position, vehicle_get_velocity},
-};
+use std::{f32::consts::PI, rc::Rc};
+
+use protologic_core::guns::AmmoType;
 
 use crate::{
+	clock::Clock,
+	datalink::datalink::Datalink,
 	get,
-	math::{first_order_intercept::first_order_intercept, quaternion::*, utils::now, vector3::*},
+	hardware::traits::Hardware,
+	math::{first_order_intercept::first_order_intercept_accel, quaternion::*, vector3::*},
 };
 
-use super::radar_controller::{radar_get_contact, RadarTrack};
+use super::radar_controller::{RadarController, RadarTrack};
 
 pub struct TurretController {
+	hw: Rc<dyn Hardware>,
+	clock: Rc<dyn Clock>,
 	target_id: i64,
 	index: i32,
 
@@ -26,59 +24,78 @@ pub struct TurretController {
 const SHOT_INTERVAL: f32 = 0.5;
 
 impl TurretController {
-	pub fn new(index: i32) -> TurretController {
+	pub fn new(hw: Rc<dyn Hardware>, clock: Rc<dyn Clock>, index: i32) -> TurretController {
 		println!("Setting up turret {}!", index);
-		gun_reload(index, AmmoType::Flak);
-		TurretController { target_id: 0, last_shot_time: 0.0, index }
+		hw.gun_reload(index, AmmoType::Flak);
+		TurretController {
+			hw,
+			clock,
+			target_id: 0,
+			last_shot_time: 0.0,
+			index,
+		}
 	}
 
-	pub fn update(&mut self, last_shot_time: f32) {
-		gun_set_fuse(self.index, 0.1);
+	pub fn update(&mut self, radar: &RadarController, datalink: &Datalink, last_shot_time: f32) {
+		self.hw.gun_set_fuse(self.index, 0.1);
 		if self.target_id == 0 {
 			return;
 		}
 
-		let target = get!(radar_get_contact(self.target_id));
-		let lead_point = first_order_intercept(
-			vehicle_get_position().into(),
-			vehicle_get_velocity().into(),
-			turret_shell_speed(),
-			target.position,
-			target.velocity,
+		let target = get!(radar.get_contact(self.target_id));
+		let now = self.clock.now();
+
+		// Prefer the fused picture once someone else (e.g. a missile with a lock) is adding to it
+		let (target_position, target_velocity) = match datalink.get_track_for_contact(self.target_id) {
+			Some(fused) if fused.sources > 1 => (fused.get_current_position(now), fused.velocity),
+			_ => (target.get_current_position(now), target.get_current_velocity(now)),
+		};
+
+		let lead_point = first_order_intercept_accel(
+			self.hw.vehicle_get_position().into(),
+			self.hw.vehicle_get_velocity().into(),
+			self.hw.turret_shell_speed(),
+			target_position,
+			target_velocity,
+			target.acceleration,
 		);
 
-		let angles = TurretController::get_pointing_angles_for_position(lead_point);
+		let angles = self.get_pointing_angles_for_position(lead_point);
 
-		let bearing_error = angles.bearing - gun_get_bearing(self.index);
-		let elevation_error = angles.elevation - gun_get_elevation(self.index);
+		let bearing_error = angles.bearing - self.hw.gun_get_bearing(self.index);
+		let elevation_error = angles.elevation - self.hw.gun_get_elevation(self.index);
 		let acceptable_error = 0.1f32;
 
-		if bearing_error.abs() + elevation_error.abs() < acceptable_error && self.ready_to_fire() && now() - last_shot_time > SHOT_INTERVAL {
-			gun_trigger(self.index);
+		if bearing_error.abs() + elevation_error.abs() < acceptable_error && self.ready_to_fire() && self.clock.now() - last_shot_time > SHOT_INTERVAL {
+			self.hw.gun_trigger(self.index);
 			println!("Firing turret {}!", self.index);
-			self.last_shot_time = now();
+			self.last_shot_time = self.clock.now();
 		}
 
-		gun_set_bearing(self.index, angles.bearing);
-		gun_set_elevation(self.index, angles.elevation);
+		self.hw.gun_set_bearing(self.index, angles.bearing);
+		self.hw.gun_set_elevation(self.index, angles.elevation);
 
-		if gun_get_magazine_remaining(self.index) == 0 && gun_get_magazine_reloadtime(self.index) == 0.0 {
-			gun_reload(self.index, AmmoType::ArmourPiercing);
+		if self.hw.gun_get_magazine_remaining(self.index) == 0 && self.hw.gun_get_magazine_reloadtime(self.index) == 0.0 {
+			self.hw.gun_reload(self.index, AmmoType::ArmourPiercing);
 		}
 	}
 
 	pub fn self_det(&self) -> bool {
-		if gun_get_magazine_remaining(self.index) == 0 && gun_get_magazine_reloadtime(self.index) == 0.0 {
-			gun_reload(self.index, AmmoType::Flak);
+		if self.hw.gun_get_magazine_remaining(self.index) == 0 && self.hw.gun_get_magazine_reloadtime(self.index) == 0.0 {
+			self.hw.gun_reload(self.index, AmmoType::Flak);
 			return false;
 		}
 
 		if self.ready_to_fire() {
-			gun_set_fuse(self.index, 0.001);
-			gun_trigger(self.index);
+			self.hw.gun_set_fuse(self.index, 0.001);
+			self.hw.gun_trigger(self.index);
 			return true;
 		}
-		return false;
+		false
+	}
+
+	pub fn target(&self) -> Option<i64> {
+		(self.target_id != 0).then_some(self.target_id)
 	}
 
 	pub fn set_target(&mut self, target: &RadarTrack) {
@@ -87,21 +104,27 @@ impl TurretController {
 		}
 
 		self.target_id = target.id;
-		let current_ammo_type = gun_get_magazine_type(self.index);
+		let current_ammo_type = self.hw.gun_get_magazine_type(self.index);
 		if current_ammo_type != AmmoType::ArmourPiercing {
-			gun_reload(self.index, AmmoType::ArmourPiercing);
+			self.hw.gun_reload(self.index, AmmoType::ArmourPiercing);
 		}
 	}
 
+	pub fn clear_target(&mut self) {
+		self.target_id = 0;
+	}
+
 	fn ready_to_fire(&self) -> bool {
-		gun_get_refiretime(self.index) == 0.0 && gun_get_magazine_reloadtime(self.index) == 0.0 && gun_get_magazine_remaining(self.index) > 0
+		self.hw.gun_get_refiretime(self.index) == 0.0
+			&& self.hw.gun_get_magazine_reloadtime(self.index) == 0.0
+			&& self.hw.gun_get_magazine_remaining(self.index) > 0
 	}
 
-	fn get_pointing_angles_for_position(position: Vector3) -> Angles {
-		let ship_pos: Vector3 = vehicle_get_position().into();
+	fn get_pointing_angles_for_position(&self, position: Vector3) -> Angles {
+		let ship_pos: Vector3 = self.hw.vehicle_get_position().into();
 		let mut dir = (ship_pos - position).normalized();
 
-		let orientation: Quaternion = vehicle_get_orientation().into();
+		let orientation: Quaternion = self.hw.vehicle_get_orientation().into();
 		dir = orientation.invert() * dir;
 
 		let turret_rotation = Quaternion::from_axis_angle(&AxisAngle {
@@ -111,7 +134,6 @@ impl TurretController {
 		.normalized();
 		dir = turret_rotation.invert() * dir;
 
-		let angles = dir.angles();
-		return angles;
+		dir.angles()
 	}
 }
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use super::{bit_buffer::BitBuffer, messages::authenticated::Authenticated};
+
+const REPLAY_WINDOW: u16 = 500; // Ticks, signed messages older or newer than this are refused
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AuthError {
+	Unsigned, // An order that should have been signed wasn't
+	BadMac,
+	Stale, // Outside the replay window
+	Replayed,
+}
+
+// SipHash-2-4, small enough to carry around and a proper keyed PRF unlike the std hashers
+fn sip_round(v: &mut [u64; 4]) {
+	v[0] = v[0].wrapping_add(v[1]);
+	v[1] = v[1].rotate_left(13) ^ v[0];
+	v[0] = v[0].rotate_left(32);
+	v[2] = v[2].wrapping_add(v[3]);
+	v[3] = v[3].rotate_left(16) ^ v[2];
+	v[0] = v[0].wrapping_add(v[3]);
+	v[3] = v[3].rotate_left(21) ^ v[0];
+	v[2] = v[2].wrapping_add(v[1]);
+	v[1] = v[1].rotate_left(17) ^ v[2];
+	v[2] = v[2].rotate_left(32);
+}
+
+fn siphash(key: (u64, u64), words: &[u64]) -> u64 {
+	let mut v = [
+		key.0 ^ 0x736f6d6570736575,
+		key.1 ^ 0x646f72616e646f6d,
+		key.0 ^ 0x6c7967656e657261,
+		key.1 ^ 0x7465646279746573,
+	];
+
+	let compress = |m: u64, v: &mut [u64; 4]| {
+		v[3] ^= m;
+		sip_round(v);
+		sip_round(v);
+		v[0] ^= m;
+	};
+
+	for word in words {
+		compress(*word, &mut v);
+	}
+	compress((words.len() as u64 * 8) << 56, &mut v);
+
+	v[2] ^= 0xff;
+	for _ in 0..4 {
+		sip_round(&mut v);
+	}
+
+	v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+// Turns the shared secret from the environment into a SipHash key
+fn derive_key(secret: &str) -> (u64, u64) {
+	let words: Vec<u64> = secret.bytes().map(|b| b as u64).collect();
+	(siphash((0, 0), &words), siphash((0, 1), &words))
+}
+
+// Signs and checks orders with a MAC keyed from a pre-shared secret, over the message and the tick it was sent
+// on. Without a secret configured nothing is signed and everything is accepted
+pub struct Authenticator {
+	key: Option<(u64, u64)>,
+	seen: HashMap<u32, u16>, // MACs accepted recently, and their tick
+}
+
+impl Authenticator {
+	pub fn new(secret: Option<String>) -> Authenticator {
+		Authenticator {
+			key: secret.filter(|s| !s.is_empty()).map(|s| derive_key(&s)),
+			seen: HashMap::new(),
+		}
+	}
+
+	pub fn is_enabled(&self) -> bool {
+		self.key.is_some()
+	}
+
+	pub fn mac(&self, tick: u16, payload: &BitBuffer) -> u32 {
+		let key = match self.key {
+			Some(key) => key,
+			None => return 0,
+		};
+
+		let mut words = vec![tick as u64, payload.len() as u64];
+		words.extend(payload.words.iter());
+		siphash(key, &words) as u32
+	}
+
+	pub fn verify(&mut self, authenticated: &Authenticated, now_tick: u32) -> Result<(), AuthError> {
+		let now = now_tick as u16;
+		let age = now.wrapping_sub(authenticated.tick).min(authenticated.tick.wrapping_sub(now));
+		if age > REPLAY_WINDOW {
+			return Err(AuthError::Stale);
+		}
+
+		if self.mac(authenticated.tick, &authenticated.payload) != authenticated.mac {
+			return Err(AuthError::BadMac);
+		}
+
+		self.seen.retain(|_, tick| now.wrapping_sub(*tick).min(tick.wrapping_sub(now)) <= REPLAY_WINDOW);
+		if self.seen.insert(authenticated.mac, authenticated.tick).is_some() {
+			return Err(AuthError::Replayed);
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,77 @@
+// Like U64View, but grows as it is written to. Used for messages that don't fit in a single radio word
+#[derive(Clone, Debug)]
+pub struct BitBuffer {
+	pub words: Vec<u64>,
+
+	write_index: usize,
+	read_index: usize,
+	overflowed: bool,
+}
+
+impl BitBuffer {
+	pub fn new() -> BitBuffer {
+		BitBuffer::from_words(Vec::new(), 0)
+	}
+
+	pub fn from_words(words: Vec<u64>, len: usize) -> BitBuffer {
+		BitBuffer {
+			words,
+			write_index: len,
+			read_index: 0,
+			overflowed: false,
+		}
+	}
+
+	// Bits written so far
+	pub fn len(&self) -> usize {
+		self.write_index
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.write_index == 0
+	}
+
+	pub fn write(&mut self, value: u64, len: usize) {
+		for i in 0..len {
+			let bit = (value >> i) & 1;
+			let index = self.write_index + i;
+			if index / 64 >= self.words.len() {
+				self.words.push(0);
+			}
+			self.words[index / 64] |= bit << (index % 64);
+		}
+
+		self.write_index += len;
+	}
+
+	// Reading past what was written gives zeros and marks the buffer overflowed
+	pub fn read(&mut self, len: usize) -> u64 {
+		if self.read_index + len > self.write_index {
+			self.overflowed = true;
+			self.read_index += len;
+			return 0;
+		}
+
+		let mut value = 0;
+		for i in 0..len {
+			let index = self.read_index + i;
+			let bit = (self.words[index / 64] >> (index % 64)) & 1;
+			value |= bit << i;
+		}
+
+		self.read_index += len;
+		value
+	}
+}
+
+impl BitBuffer {
+	pub fn overflowed(&self) -> bool {
+		self.overflowed
+	}
+}
+
+impl Default for BitBuffer {
+	fn default() -> Self {
+		BitBuffer::new()
+	}
+}
@@ -1,25 +1,45 @@
-use std::{cell::RefCell, collections::HashMap};
-
-use protologic_core::{
-	radar::RadarTargetType,
-	radio::{radio_receive, radio_receive_filter, radio_transmit},
+use std::{
+	collections::{HashMap, HashSet},
+	rc::Rc,
 };
+
+use protologic_core::radar::RadarTargetType;
 use rand::random;
 
 use crate::{
-	core::configure_control_system,
+	clock::Clock,
 	datalink::messages::{join_request::JoinRequest, message::DatalinkMessage},
 	get,
-	math::{utils::now, vector3::Vector3},
+	hardware::traits::Hardware,
+	math::vector3::Vector3,
 	updatable_debug::UpdatableSphere,
 };
 
-use super::messages::{
-	iff_pos::IFFPosition,
-	leave_network::LeaveNetwork,
-	message::Message,
-	net_info::{self, NetInfo},
-	track_id::TrackId,
+use super::{
+	authenticator::{AuthError, Authenticator},
+	decode_error::DecodeError,
+	framing::{FramedMessage, FramedMessageKey, Oversized, Reassembler},
+	messages::{
+		ack::Ack,
+		authenticated::Authenticated,
+		iff_pos::IFFPosition,
+		leave_network::LeaveNetwork,
+		link_status::LinkStatus,
+		message::{Message, MessageKey},
+		net_info::{self, NetInfo},
+		reliable::Reliable,
+		single::Single,
+		slot_assign::SlotAssign,
+		track_drop::TrackDrop,
+		track_id::TrackId,
+		track_retire::TrackRetire,
+		track_state::TrackState,
+	},
+	reliable_channel::{Delivery, DeliveryStatus, ReliableChannel},
+	send_queue::{CoalesceKey, MessageClass, SendQueue, CLASSES},
+	slot_allocator::{self, LinkRole, BLOCK_CAPACITY},
+	stats::{DatalinkMember, DatalinkStats, LinkCounters},
+	track_fusion::TrackFusion,
 };
 
 #[derive(Clone, Copy)]
@@ -31,6 +51,7 @@ pub struct DatalinkTrack {
 	pub position: Vector3,
 	pub velocity: Vector3,
 	pub last_update_timestamp: f32,
+	pub sources: usize, // Vehicles whose reports went into the fused position
 
 	pub is_allied: bool,
 }
@@ -46,26 +67,18 @@ impl DatalinkTrack {
 			velocity: Vector3::zero(),
 
 			last_update_timestamp: f32::MAX,
+			sources: 0,
 			is_allied: true,
 		}
 	}
 
-	pub fn update(&mut self, dt: f32) {
-		self.position += self.velocity * dt;
-	}
-
-	pub fn update_position(&mut self, position: Vector3) {
-		self.position = position;
-		self.last_update_timestamp = now();
-	}
-
-	pub fn update_velocity(&mut self, velocity: Vector3) {
-		self.velocity = velocity;
-		self.last_update_timestamp = now();
+	// Extrapolated from the last report
+	pub fn get_current_position(&self, now: f32) -> Vector3 {
+		self.position + self.velocity * (now - self.last_update_timestamp)
 	}
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum DatalinkStatus {
 	None,
 	WaitingForId,
@@ -87,83 +100,173 @@ impl TimeBlock {
 pub struct FriendlyPosition {
 	pub position: Vector3,
 	pub dl_id: u8,
+	pub block: u8,
+	pub last_heard: f32,
 	pub sphere: UpdatableSphere,
 }
 
 pub struct Datalink {
+	hw: Rc<dyn Hardware>,
+	clock: Rc<dyn Clock>,
+	channel: u8, // Fleet channel, sent in the top bits of every word so other fleets' traffic is filtered out
+
 	status: DatalinkStatus,
 	blocks: Vec<TimeBlock>,
 
-	join_request_approve_id: u8,
-	our_join_request_id: u8,
+	join_requests: Vec<(u8, u8)>, // Host only, (slot, block) of every JoinRequest heard since the last NetInfo
+	our_join_slot: u8,
 	our_request_block: u8,
 
 	id: u8,
 
 	total_blocks: u8,
 	our_block: u8,
+	awaiting_block_since: Option<f32>, // Joined, but the host hasn't said which block is ours yet. We stay quiet until it does
+	extra_blocks: u16,                 // Spare slots the host lent us, a bit per block
+	schedule_epoch: u8,                // Host: of the current extra slots. Member: of the last SlotAssign we applied
+	role: LinkRole,
+	last_status_time: f32,
 
 	next_free_block: u8,
 
-	message_queue: Vec<Message>,
+	send_queue: SendQueue,
 	core_message_queue: Vec<Message>,
 	disconnect_on_next_send: bool,
 
+	reassembler: Reassembler,
+	next_frame_sequence: u8,
+	reliable: ReliableChannel,
+	auth: Authenticator,
+	unsigned_orders: Vec<FramedMessage>, // Held until we have the network's tick to sign them with
+
 	id_map: HashMap<u32, u16>,
 	next_track_id: u16,
-	next_id: u8,
+	// Host only. When each track id was handed out, so one nobody has reported on yet isn't taken back
+	track_id_assigned: HashMap<u16, f32>,
+	retired_track_ids: Vec<(u16, f32)>, // Taken back and when, they sit out a while like member ids
+	last_track_sweep: f32,
+	refused_track_ids: u32, // Since the last sweep
+	track_id_exhaustions: u32,
+	track_id_conflicts: u32,
+	next_id: u8, // Given to the last joiner approved
+
+	// Host only. When each member was put on the schedule, so a new one has time to send its first IFF
+	member_since: HashMap<u8, f32>,
+	retired_ids: Vec<(u8, f32)>,           // Freed ids and when, they sit out a while before being handed out again
+	pending_assignments: HashMap<u8, f32>, // Member -> when it was last sent its SlotAssign, until its LinkStatus shows it applied
+	extra_slots: HashMap<u8, u16>,         // Spare slots lent out, as a mask of blocks per member
+	member_demand: HashMap<u8, f32>,       // From each member's last LinkStatus
+	member_roles: HashMap<u8, LinkRole>,   // Likewise
+	last_allocation_time: f32,
 
 	tracks: Vec<DatalinkTrack>,
+	fusion: TrackFusion,
+	report_sources: HashMap<u16, u8>, // Who sent the last TrackInfo for a track
 	friendly_positions: Vec<FriendlyPosition>,
 
 	is_host: bool,
+	newly_joined: bool,
+	last_net_info_time: f32,
 	pub tick: u32,
 
-	messages_pushed_last_second: u32,
-	prev_mpls: u32,
-	last_count_reset_time: f32,
+	counters: LinkCounters,
+	decode_errors: u32,
+	auth_rejections: u32,
+	oversized_messages: u32,
 }
 
 const INVALID: u8 = u8::MAX;
 const IFF_FRIEND_DISTANCE: f32 = 150.0;
+const TRACK_MAX_AGE: f32 = 15.0; // Reporters refresh every 5 seconds, so this is 3 missed reports
+const HOST_TIMEOUT: f32 = 1.5; // No NetInfo for this long and the host is presumed dead
+const MEMBER_TIMEOUT: f32 = 2.0; // Members send IFF twice a second
+const UNKNOWN_SOURCE_DISTANCE: f32 = 10000.0; // Reporter we have no IFF position for, trust it like a far away one
+const EVICT_TIMEOUT: f32 = 5.0; // A member silent this long is taken off the schedule, it most likely blew up
+const ID_REUSE_DELAY: f32 = 30.0; // Longer than any retries or duplicate suppression still keyed on an old id
+const SLOT_ASSIGN_RETRY: f32 = 1.0;
+const SLOT_ASSIGN_TIMEOUT: f32 = 4.0; // Joined and never told our block, the host must have lost us. Under EVICT_TIMEOUT
+const ALLOCATION_INTERVAL: f32 = 1.0; // How often the host redoes the extra slots
+const LINK_STATUS_RATE: f32 = 1.0;
+const TICK_MASK: u32 = (1 << 20) - 1; // What NetInfo has room for
+const EPOCH_MASK: u8 = 0xF;
+const JOIN_SLOTS: u8 = 16;
+const TRACK_ID_LIMIT: u16 = 1 << 12; // Track ids go out in 12 bits
+const TRACK_SWEEP_INTERVAL: f32 = 1.0;
+const RETIRE_REPEAT: f32 = 2.0; // Retirements go out every sweep for this long, nobody acks a broadcast
+const CHANNEL_SHIFT: u32 = 60;
+const CHANNEL_MASK: u64 = 0xF << CHANNEL_SHIFT;
 
 impl Datalink {
-	fn new() -> Datalink {
+	pub fn new(hw: Rc<dyn Hardware>, clock: Rc<dyn Clock>) -> Datalink {
 		Datalink {
+			channel: fleet_channel(hw.as_ref()),
+			auth: Authenticator::new(hw.vehicle_env("DatalinkKey")),
+			role: LinkRole::of(hw.as_ref()),
+			hw,
+			clock,
+
 			status: DatalinkStatus::None,
 			blocks: Vec::new(),
 
-			join_request_approve_id: INVALID,
-			our_join_request_id: INVALID,
+			join_requests: Vec::new(),
+			our_join_slot: INVALID,
 			our_request_block: INVALID,
 
 			id: INVALID,
 
 			total_blocks: 0,
 			our_block: 0,
+			awaiting_block_since: None,
+			extra_blocks: 0,
+			schedule_epoch: 0,
+			last_status_time: 0.0,
 			next_free_block: INVALID,
 
-			message_queue: Vec::new(),
+			send_queue: SendQueue::new(),
 			core_message_queue: Vec::new(),
 			disconnect_on_next_send: false,
 
+			reassembler: Reassembler::new(),
+			next_frame_sequence: 0,
+			reliable: ReliableChannel::new(),
+			unsigned_orders: Vec::new(),
+
 			id_map: HashMap::new(),
-			next_track_id: 0,
+			next_track_id: 1, // 0 is how clients ask for an id
+			track_id_assigned: HashMap::new(),
+			retired_track_ids: Vec::new(),
+			last_track_sweep: 0.0,
+			refused_track_ids: 0,
+			track_id_exhaustions: 0,
+			track_id_conflicts: 0,
 			next_id: 0,
 
+			member_since: HashMap::new(),
+			retired_ids: Vec::new(),
+			pending_assignments: HashMap::new(),
+			extra_slots: HashMap::new(),
+			member_demand: HashMap::new(),
+			member_roles: HashMap::new(),
+			last_allocation_time: 0.0,
+
 			tracks: Vec::new(),
+			fusion: TrackFusion::new(),
+			report_sources: HashMap::new(),
 			friendly_positions: Vec::new(),
 
 			is_host: false,
+			newly_joined: false,
+			last_net_info_time: 0.0,
 			tick: 0,
 
-			messages_pushed_last_second: 0,
-			prev_mpls: 0,
-			last_count_reset_time: 0.0,
+			counters: LinkCounters::new(),
+			decode_errors: 0,
+			auth_rejections: 0,
+			oversized_messages: 0,
 		}
 	}
 
-	fn setup_as_host(&mut self) {
+	pub fn setup_as_host(&mut self) {
 		self.id = 0;
 		self.status = DatalinkStatus::Joined;
 
@@ -177,26 +280,30 @@ impl Datalink {
 		self.our_block = 1;
 
 		self.is_host = true;
-
-		configure_control_system();
 	}
 
 	fn is_our_turn(&self) -> bool {
-		u32::from(self.our_block) == self.tick % u32::from(self.total_blocks)
+		if self.awaiting_block_since.is_some() {
+			return false;
+		}
+
+		let block = self.tick % u32::from(self.total_blocks);
+		u32::from(self.our_block) == block || (block < 16 && self.extra_blocks & (1 << block) != 0)
 	}
 
 	fn is_host_transmit_turn(&self) -> bool {
-		0 == (self.tick) % u32::from(self.total_blocks) && self.is_host
+		self.tick.is_multiple_of(u32::from(self.total_blocks)) && self.is_host
 	}
 
-	fn update(&mut self) {
+	pub fn update(&mut self) {
 		if self.status == DatalinkStatus::Disconnected {
 			return;
 		}
 
 		let mut buffer: Vec<u64> = Vec::new();
-		radio_receive_filter(0, 0);
-		radio_receive(&mut buffer);
+		self.hw.radio_receive_filter((self.channel as u64) << CHANNEL_SHIFT, CHANNEL_MASK);
+		self.hw.radio_receive(&mut buffer);
+		self.counters.tick(buffer.len());
 
 		for message in buffer {
 			self.handle_packet(message);
@@ -206,6 +313,34 @@ impl Datalink {
 			return;
 		}
 
+		if !self.is_host && self.clock.now() - self.last_net_info_time > HOST_TIMEOUT {
+			self.elect_host();
+		}
+
+		if self.awaiting_block_since.is_some_and(|since| self.clock.now() - since > SLOT_ASSIGN_TIMEOUT) {
+			println!("Datalink {} never got its block, joining again", self.id);
+			self.awaiting_block_since = None;
+			self.status = DatalinkStatus::None;
+			return;
+		}
+
+		self.age_out_tracks();
+
+		if self.is_host {
+			self.maintain_schedule();
+			self.maintain_track_ids();
+		} else if self.clock.now() - self.last_status_time > LINK_STATUS_RATE {
+			self.send_link_status();
+		}
+
+		for message in std::mem::take(&mut self.unsigned_orders) {
+			self.send_framed(message);
+		}
+
+		for reliable in self.reliable.due_retransmits(self.id, self.clock.now()) {
+			self.send_framed(FramedMessage::Reliable(reliable));
+		}
+
 		// if self.is_host {
 		// 	let expected_pps = 100.0 / self.total_blocks as f32;
 		// 	println!(
@@ -220,11 +355,7 @@ impl Datalink {
 		// 	);
 		// }
 
-		if now() - self.last_count_reset_time > 1.0 {
-			self.prev_mpls = self.messages_pushed_last_second;
-			self.messages_pushed_last_second = 0;
-			self.last_count_reset_time = now();
-		}
+		self.counters.roll(self.clock.now());
 
 		if self.is_host_transmit_turn() {
 			self.send_net_info();
@@ -240,24 +371,25 @@ impl Datalink {
 				return;
 			}
 
-			if self.message_queue.len() > 0 {
-				let next_message = self.message_queue.remove(0);
+			let next_message = self.send_queue.pop();
+			self.counters.slot(next_message.is_some());
+			if let Some(next_message) = next_message {
 				self.transmit(next_message.serialize().value);
 			}
 
-			if self.message_queue.len() > 100 {
-				println!("Datalink {} has excessive message queue size: {}", self.id, self.message_queue.len());
-				println!("Datalink {} Previous MPLS: {}", self.id, self.prev_mpls);
+			if self.send_queue.len() > 100 {
+				println!("Datalink {} has excessive message queue size: {}", self.id, self.send_queue.len());
+				println!("Datalink {} Previous MPLS: {}", self.id, self.counters.pushed_last_second());
 				// for message in &self.message_queue {
 				// 	println!("{:?}", message);
 				// }
 			}
 		}
 
-		self.tick += 1;
+		self.tick = (self.tick + 1) & TICK_MASK;
 	}
 
-	fn get_core_message_queue(&mut self) -> Vec<Message> {
+	pub fn get_core_message_queue(&mut self) -> Vec<Message> {
 		let mut queue: Vec<Message> = Vec::new();
 
 		for message in &self.core_message_queue {
@@ -269,13 +401,74 @@ impl Datalink {
 		queue
 	}
 
-	fn send_message(&mut self, message: Message) {
-		self.message_queue.push(message);
-		self.messages_pushed_last_second += 1;
+	pub fn send_message(&mut self, message: Message) {
+		// Orders need room for a MAC, so they go out framed
+		if self.auth.is_enabled() && is_protected(&message) {
+			self.send_framed(FramedMessage::Single(Single::new(message)));
+			return;
+		}
+
+		self.send_queue.push(message);
+		self.counters.pushed();
+	}
+
+	// Fragments go out back to back in our slots, so they arrive in order unless one is lost
+	pub fn send_framed(&mut self, message: FramedMessage) {
+		let message = match self.sign(message) {
+			Some(message) => message,
+			None => return,
+		};
+		let fragments = match message.fragment(self.id, self.next_frame_sequence) {
+			Ok(fragments) => fragments.into_iter().map(Message::Fragment).collect(),
+			Err(Oversized(bits)) => {
+				self.oversized_messages += 1;
+				println!("Datalink {} dropped a {} bit framed message, too big to send", self.id, bits);
+				return;
+			}
+		};
+		self.next_frame_sequence = self.next_frame_sequence.wrapping_add(1);
+
+		self.counters.pushed();
+		self
+			.send_queue
+			.push_group(fragments, MessageClass::of_framed(&message), CoalesceKey::of_framed(&message));
+	}
+
+	// For orders that have to arrive. The destination acknowledges it, and it is sent again until it does or
+	// we give up. The returned handle is for checking on it with delivery_status
+	pub fn send_reliable(&mut self, destination: u8, message: Message) -> Delivery {
+		let (reliable, delivery) = self.reliable.send(self.id, destination, message, self.clock.now());
+		self.send_framed(FramedMessage::Reliable(reliable));
+
+		delivery
+	}
+
+	pub fn delivery_status(&self, delivery: Delivery) -> DeliveryStatus {
+		self.reliable.status(delivery)
 	}
 
-	fn transmit(&self, value: u64) {
-		radio_transmit(value, f32::MAX);
+	// Wraps orders with a MAC over them and the current tick, anything else goes as it is. None when the
+	// order has to wait to be signed
+	fn sign(&mut self, message: FramedMessage) -> Option<FramedMessage> {
+		if !self.auth.is_enabled() || !message.inner_message().is_some_and(is_protected) {
+			return Some(message);
+		}
+
+		// Until the first NetInfo our tick is our own, and the receiver would throw the message out as stale
+		if self.status != DatalinkStatus::Joined {
+			self.unsigned_orders.push(message);
+			return None;
+		}
+
+		let tick = self.tick as u16;
+		let payload = Authenticated::payload_of(&message);
+		let mac = self.auth.mac(tick, &payload);
+		Some(FramedMessage::Authenticated(Authenticated::new(tick, mac, payload)))
+	}
+
+	fn transmit(&mut self, value: u64) {
+		self.counters.sent += 1;
+		self.hw.radio_transmit(value | (self.channel as u64) << CHANNEL_SHIFT, f32::MAX);
 		// println!("Sending: {:?}", value);
 	}
 
@@ -284,7 +477,7 @@ impl Datalink {
 
 		for i in 0..self.blocks.len() {
 			let block = &self.blocks[i];
-			if block.clients.len() < 4 {
+			if block.clients.len() < BLOCK_CAPACITY {
 				next_free_block = block.index;
 				break;
 			}
@@ -293,16 +486,55 @@ impl Datalink {
 			next_free_block = self.blocks.len() as u8;
 		}
 
-		let net_info = NetInfo::new(self.next_id, self.total_blocks, next_free_block, self.tick, self.join_request_approve_id);
+		let join_approvals = self.approve_joins();
+		let net_info = NetInfo::new(self.next_id, self.total_blocks, next_free_block, self.tick, join_approvals, self.schedule_epoch);
 		self.transmit(net_info.serialize().value);
 
 		// println!("Sent net info: {:?}", net_info);
-		self.join_request_approve_id = INVALID;
 		self.total_blocks = self.blocks.len() as u8;
+		self.last_net_info_time = self.clock.now();
 	}
 
 	fn handle_packet(&mut self, message: u64) {
-		let packet = Message::parse(message);
+		match Message::parse(message) {
+			Ok(packet) => self.handle_message(packet, false),
+			Err(error) => self.drop_bad_packet(message, error),
+		}
+	}
+
+	fn drop_bad_packet(&mut self, message: u64, error: DecodeError) {
+		self.decode_errors += 1;
+		self.counters.dropped += 1;
+		println!("Datalink {} dropped bad packet {:#018x}: {}", self.id, message, error);
+	}
+
+	// Signed, so the MAC checked out, but what it wrapped didn't decode
+	fn drop_bad_signed_message(&mut self, authenticated: &Authenticated, error: DecodeError) {
+		self.decode_errors += 1;
+		self.counters.dropped += 1;
+		println!("Datalink {} dropped signed message with MAC {:#010x}: {}", self.id, authenticated.mac, error);
+	}
+
+	// Orders are only taken from inside a verified Authenticated message once a key is set
+	fn is_authorized(&mut self, message: &Message, authenticated: bool) -> bool {
+		if authenticated || !self.auth.is_enabled() || !is_protected(message) {
+			return true;
+		}
+
+		self.reject_unauthenticated(message.message_type() as u8, AuthError::Unsigned);
+		false
+	}
+
+	fn reject_unauthenticated(&mut self, key: u8, error: AuthError) {
+		self.auth_rejections += 1;
+		self.counters.dropped += 1;
+		println!("Datalink {} rejected message with key {}: {:?}", self.id, key, error);
+	}
+
+	fn handle_message(&mut self, packet: Message, authenticated: bool) {
+		if !self.is_authorized(&packet, authenticated) {
+			return;
+		}
 
 		match packet {
 			Message::NetInfo(net_info) => self.handle_net_info(net_info),
@@ -310,22 +542,108 @@ impl Datalink {
 			Message::TrackId(track_id) => self.process_track_id(track_id),
 			Message::LeaveRequest(leave_network) => self.process_leave_network(leave_network),
 			Message::TrackInfo(track_info) => {
+				self.check_track_contact(track_info.track_id, track_info.contact_id);
+				self.report_sources.insert(track_info.track_id, track_info.source);
 				let track = self.get_track_mut(track_info.track_id);
 				track.contact_id = track_info.contact_id;
 				track.contact_type = track_info.contact_type;
 				track.is_allied = track_info.is_allied;
 			}
-			Message::TrackPosition(track_position) => self.get_track_mut(track_position.track_id).update_position(track_position.position),
-			Message::TrackVelocity(track_velocity) => self.get_track_mut(track_velocity.track_id).update_velocity(track_velocity.velocity),
+			Message::TrackPosition(track_position) => {
+				let now = self.clock.now();
+				let source = self.report_source(track_position.track_id);
+				let source_distance = self.source_distance(source, track_position.position);
+				self
+					.fusion
+					.report_position(track_position.track_id, source, track_position.position, source_distance, now);
+				self.fuse_track(track_position.track_id);
+			}
+			Message::TrackVelocity(track_velocity) => {
+				let source = self.report_source(track_velocity.track_id);
+				self.fusion.report_velocity(track_velocity.track_id, source, track_velocity.velocity);
+				self.fuse_track(track_velocity.track_id);
+			}
 			Message::IFFPosition(iff_pos) => self.handle_iff_position(iff_pos),
+			Message::TrackDrop(track_drop) => self.drop_source(track_drop.track_id, track_drop.source),
+			Message::SlotAssign(slot_assign) => self.handle_slot_assign(slot_assign),
+			Message::LinkStatus(link_status) => self.handle_link_status(link_status),
+			Message::Ack(ack) => {
+				if ack.destination == self.id {
+					self.reliable.handle_ack(&ack, self.clock.now());
+				}
+			}
+			Message::Fragment(fragment) => {
+				let word = fragment.serialize().value;
+				match self.reassembler.add(fragment, self.clock.now()) {
+					Ok(Some(framed)) => self.handle_framed(framed, false),
+					Ok(None) => {}
+					Err(error) => self.drop_bad_packet(word, error),
+				}
+			}
 			_ => self.core_message_queue.push(packet),
 		}
 	}
 
+	fn handle_framed(&mut self, message: FramedMessage, authenticated: bool) {
+		match message {
+			FramedMessage::TrackState(track_state) => self.handle_track_state(track_state),
+			FramedMessage::Reliable(reliable) => self.handle_reliable(reliable, authenticated),
+			FramedMessage::Single(single) => self.handle_message(single.message, authenticated),
+			FramedMessage::Authenticated(authenticated) => self.handle_authenticated(authenticated),
+			FramedMessage::TrackRetire(track_retire) => self.handle_track_retire(track_retire),
+		}
+	}
+
+	fn handle_authenticated(&mut self, authenticated: Authenticated) {
+		if let Err(error) = self.auth.verify(&authenticated, self.tick) {
+			self.reject_unauthenticated(FramedMessageKey::Authenticated as u8, error);
+			return;
+		}
+
+		match authenticated.open() {
+			Ok(inner) => self.handle_framed(inner, true),
+			Err(error) => self.drop_bad_signed_message(&authenticated, error),
+		}
+	}
+
+	fn handle_reliable(&mut self, reliable: Reliable, authenticated: bool) {
+		// A forged order doesn't get acknowledged either
+		if !self.is_authorized(&reliable.message, authenticated) {
+			return;
+		}
+
+		if reliable.destination == self.id {
+			self.send_message(Message::Ack(Ack::new(self.id, reliable.source, reliable.sequence)));
+		}
+
+		// Everyone listens in, a TrackId reply is useful to more than the one who asked
+		if self.reliable.receive(&reliable, self.clock.now()) {
+			self.handle_message(reliable.message, authenticated);
+		}
+	}
+
+	fn handle_track_state(&mut self, track_state: TrackState) {
+		self.check_track_contact(track_state.track_id, track_state.contact_id);
+		let now = self.clock.now();
+		let track = self.get_track_mut(track_state.track_id);
+		track.contact_id = track_state.contact_id;
+		track.contact_type = track_state.contact_type;
+		track.is_allied = track_state.is_allied;
+
+		let source_distance = self.source_distance(track_state.source, track_state.position);
+		self
+			.fusion
+			.report_position(track_state.track_id, track_state.source, track_state.position, source_distance, now);
+		self.fusion.report_velocity(track_state.track_id, track_state.source, track_state.velocity);
+		self.fuse_track(track_state.track_id);
+	}
+
 	fn handle_iff_position(&mut self, iff_pos: IFFPosition) {
 		let existing = self.friendly_positions.iter_mut().find(|f| f.dl_id == iff_pos.dl_id);
 		if let Some(existing) = existing {
 			existing.position = iff_pos.position;
+			existing.block = iff_pos.block;
+			existing.last_heard = self.clock.now();
 			existing.sphere.set_pos(iff_pos.position + Vector3::random());
 			existing.sphere.set_color(0.0, 1.0, 0.0);
 			existing.sphere.set_radius(15.0);
@@ -333,28 +651,267 @@ impl Datalink {
 			let pos = FriendlyPosition {
 				position: iff_pos.position,
 				dl_id: iff_pos.dl_id,
-				sphere: UpdatableSphere::new(),
+				block: iff_pos.block,
+				last_heard: self.clock.now(),
+				sphere: UpdatableSphere::new(self.hw.clone()),
 			};
 
 			self.friendly_positions.push(pos);
 		}
+
+		if self.is_host {
+			self.check_member_block(iff_pos.dl_id, iff_pos.block);
+		}
+	}
+
+	fn handle_slot_assign(&mut self, slot_assign: SlotAssign) {
+		if slot_assign.id != self.id || self.is_host {
+			return;
+		}
+
+		if slot_assign.block != self.our_block && self.awaiting_block_since.is_none() {
+			println!("Datalink {} moving from block {} to {}", self.id, self.our_block, slot_assign.block);
+		}
+		self.awaiting_block_since = None;
+		self.our_block = slot_assign.block;
+		self.extra_blocks = slot_assign.extra_blocks;
+		self.schedule_epoch = slot_assign.schedule_epoch;
+	}
+
+	fn send_link_status(&mut self) {
+		self.last_status_time = self.clock.now();
+		let status = LinkStatus::new(self.id, self.our_block, self.schedule_epoch, self.role, self.send_queue.len());
+		self.send_message(Message::LinkStatus(status));
+	}
+
+	fn handle_link_status(&mut self, link_status: LinkStatus) {
+		if !self.is_host {
+			return;
+		}
+
+		let demand = slot_allocator::demand(link_status.role, link_status.queue_depth);
+		self.member_demand.insert(link_status.id, demand);
+		self.member_roles.insert(link_status.id, link_status.role);
+
+		let scheduled = match self.primary_block_of(link_status.id) {
+			Some(scheduled) => scheduled,
+			None => return self.check_member_block(link_status.id, link_status.block),
+		};
+
+		// Extra slots only count as applied if they're from the current epoch
+		let has_extras = self.extra_slots.get(&link_status.id).is_some_and(|mask| *mask != 0);
+		if scheduled == link_status.block && (!has_extras || link_status.schedule_epoch == self.schedule_epoch) {
+			self.pending_assignments.remove(&link_status.id);
+		} else {
+			self.pending_assignments.entry(link_status.id).or_insert(f32::MIN);
+		}
+	}
+
+	fn primary_block_of(&self, id: u8) -> Option<u8> {
+		self.blocks.iter().find(|b| b.clients.contains(&id)).map(|b| b.index)
+	}
+
+	// The schedule as the host has it against the block a member says it's using. Only its LinkStatus says
+	// whether it's up to date, this just catches members we lost track of or that are sending in the wrong block
+	fn check_member_block(&mut self, id: u8, block: u8) {
+		match self.primary_block_of(id) {
+			// Evicted or lost in a host change, but clearly still around
+			None => {
+				println!("Datalink {} taking member {} back on", self.id, id);
+				self.place_member(id, block);
+			}
+			Some(scheduled) if scheduled != block => {
+				self.pending_assignments.entry(id).or_insert(f32::MIN);
+			}
+			Some(_) => {}
+		}
+	}
+
+	fn place_member(&mut self, id: u8, preferred_block: u8) {
+		self.retired_ids.retain(|(retired, _)| *retired != id);
+		self.member_since.insert(id, self.clock.now());
+
+		let block = match self.blocks.get_mut(preferred_block as usize) {
+			Some(block) if block.clients.len() < BLOCK_CAPACITY => block,
+			_ => {
+				let index = self.free_block();
+				self.pending_assignments.insert(id, f32::MIN);
+				&mut self.blocks[index as usize]
+			}
+		};
+		block.clients.push(id);
+	}
+
+	// First block with room, a new one on the end if they are all full
+	fn free_block(&mut self) -> u8 {
+		if let Some(block) = self.blocks.iter().find(|b| b.clients.len() < BLOCK_CAPACITY) {
+			return block.index;
+		}
+
+		let index = self.blocks.len() as u8;
+		self.blocks.push(TimeBlock::new(index));
+		index
+	}
+
+	fn scheduled_ids(&self) -> Vec<u8> {
+		let mut ids: Vec<u8> = self.blocks.iter().flat_map(|b| b.clients.iter().copied()).collect();
+		ids.sort();
+		ids.dedup();
+		ids
+	}
+
+	// Nobody has it, or had it recently. 0 is always the ship's
+	fn is_free_id(&self, id: u8) -> bool {
+		id != 0
+			&& id != INVALID
+			&& id != self.id
+			&& !self.blocks.iter().any(|b| b.clients.contains(&id))
+			&& !self.retired_ids.iter().any(|(retired, _)| *retired == id)
+			&& !self.friendly_positions.iter().any(|f| f.dl_id == id)
+	}
+
+	fn last_heard(&self, id: u8) -> f32 {
+		let heard = self.friendly_positions.iter().find(|f| f.dl_id == id).map_or(f32::MIN, |f| f.last_heard);
+		heard.max(*self.member_since.get(&id).unwrap_or(&f32::MIN))
+	}
+
+	// Host housekeeping on the schedule. Members that went quiet are dropped, and the frame is packed back down
+	// so everyone left gets more of the bandwidth
+	fn maintain_schedule(&mut self) {
+		let now = self.clock.now();
+		self.retired_ids.retain(|(_, retired)| now - *retired < ID_REUSE_DELAY);
+
+		let silent: Vec<u8> = self
+			.scheduled_ids()
+			.into_iter()
+			.filter(|id| *id != self.id && now - self.last_heard(*id) > EVICT_TIMEOUT)
+			.collect();
+		for id in silent {
+			println!("Datalink {} evicting silent member {}", self.id, id);
+			self.remove_member(id);
+		}
+
+		self.compact_blocks();
+
+		if now - self.last_allocation_time >= ALLOCATION_INTERVAL {
+			self.allocate_extra_slots();
+		}
+
+		let mut due: Vec<u8> = Vec::new();
+		for (id, last_sent) in self.pending_assignments.iter_mut() {
+			if now - *last_sent >= SLOT_ASSIGN_RETRY {
+				*last_sent = now;
+				due.push(*id);
+			}
+		}
+		for id in due {
+			if let Some(block) = self.primary_block_of(id) {
+				let extra_blocks = self.extra_slots.get(&id).copied().unwrap_or(0);
+				self.send_message(Message::SlotAssign(SlotAssign::new(id, block, extra_blocks, self.schedule_epoch)));
+			}
+		}
+	}
+
+	// Lends the room left in the frame to whoever has the most to send, weighted by role. Nobody loses their
+	// own slot, and the frame doesn't get any longer
+	fn allocate_extra_slots(&mut self) {
+		self.last_allocation_time = self.clock.now();
+
+		let blocks: Vec<Vec<u8>> = self.blocks.iter().map(|b| b.clients.clone()).collect();
+		let mut demands: Vec<(u8, f32)> = self
+			.scheduled_ids()
+			.into_iter()
+			.filter(|id| *id != self.id)
+			.map(|id| (id, *self.member_demand.get(&id).unwrap_or(&0.0)))
+			.collect();
+		let queue_depth = self.send_queue.len().min(u8::MAX as usize) as u8;
+		demands.push((self.id, slot_allocator::demand(self.role, queue_depth)));
+
+		let extra_slots = slot_allocator::allocate(&blocks, &demands);
+		if extra_slots == self.extra_slots {
+			return;
+		}
+
+		// Everyone holding extra slots, before or after, has to hear about the new epoch
+		self.schedule_epoch = (self.schedule_epoch + 1) & EPOCH_MASK;
+		for id in self.extra_slots.keys().chain(extra_slots.keys()) {
+			if *id != self.id {
+				self.pending_assignments.insert(*id, f32::MIN);
+			}
+		}
+
+		self.extra_blocks = extra_slots.get(&self.id).copied().unwrap_or(0);
+		self.extra_slots = extra_slots;
+	}
+
+	fn remove_member(&mut self, id: u8) {
+		for block in self.blocks.iter_mut() {
+			block.clients.retain(|c| *c != id);
+		}
+
+		self.friendly_positions.retain(|f| f.dl_id != id);
+		self.member_since.remove(&id);
+		self.pending_assignments.remove(&id);
+		self.extra_slots.remove(&id);
+		self.member_demand.remove(&id);
+		self.member_roles.remove(&id);
+		self.retired_ids.push((id, self.clock.now()));
+	}
+
+	// Moves one member from the last occupied block into the first gap, and drops empty blocks off the end.
+	// We stay put, our own blocks are only ever 0 and the one we had before taking over
+	fn compact_blocks(&mut self) {
+		let last = self.blocks.iter().rposition(|b| b.clients.iter().any(|c| *c != self.id));
+		let gap = self.blocks.iter().position(|b| b.clients.len() < BLOCK_CAPACITY);
+		if let (Some(last), Some(gap)) = (last, gap) {
+			if gap < last {
+				let id = *self.blocks[last].clients.iter().find(|c| **c != self.id).unwrap();
+				self.blocks[last].clients.retain(|c| *c != id);
+				self.blocks[gap].clients.push(id);
+				self.pending_assignments.insert(id, f32::MIN);
+			}
+		}
+
+		while self.blocks.len() > 2 && self.blocks.last().is_some_and(|b| b.clients.is_empty()) {
+			self.blocks.pop();
+		}
 	}
 
 	fn handle_net_info(&mut self, net_info: net_info::NetInfo) {
+		self.last_net_info_time = self.clock.now();
+		if self.is_host {
+			// Someone else took over too, whoever has the lower id keeps it
+			if self.live_members().iter().any(|(id, _)| *id < self.id) {
+				println!("Datalink {} stepping down as host", self.id);
+				self.is_host = false;
+			} else {
+				return;
+			}
+		}
+
 		self.total_blocks = net_info.num_blocks;
-		self.tick = net_info.current_tick + 1;
+		self.tick = (net_info.current_tick + 1) & TICK_MASK;
+
+		// The extra slots we were lent are out of date, stop using them until the host sends the new ones
+		if net_info.schedule_epoch != self.schedule_epoch {
+			self.extra_blocks = 0;
+		}
 		self.next_free_block = net_info.next_free_block;
 
 		match self.status {
 			DatalinkStatus::WaitingForId => {
-				if net_info.join_request_approve_id == self.our_join_request_id {
-					// Accepted into the network
+				let our_slot = 1u16 << self.our_join_slot;
+				if net_info.join_approvals & our_slot != 0 {
+					// Accepted into the network, ids are handed out in slot order
 					self.status = DatalinkStatus::Joined;
-					self.id = net_info.next_id;
+					self.id = net_info.next_id + (net_info.join_approvals & (our_slot - 1)).count_ones() as u8;
+					// The block we asked for may have been full, the host's SlotAssign says where we ended up
 					self.our_block = self.our_request_block;
+					self.awaiting_block_since = Some(self.clock.now());
 					println!("Joined network with id {}", self.id);
+					self.last_net_info_time = self.clock.now();
 
-					configure_control_system();
+					self.newly_joined = true;
 				} else {
 					// Denied
 					self.send_join_request();
@@ -367,36 +924,104 @@ impl Datalink {
 		}
 	}
 
+	// (dl_id, block) of everyone heard from recently, including us
+	fn live_members(&self) -> Vec<(u8, u8)> {
+		let now = self.clock.now();
+		let mut members: Vec<(u8, u8)> = self
+			.friendly_positions
+			.iter()
+			.filter(|f| now - f.last_heard < MEMBER_TIMEOUT && f.dl_id != self.id)
+			.map(|f| (f.dl_id, f.block))
+			.collect();
+		members.push((self.id, self.our_block));
+
+		members
+	}
+
+	// Host went quiet. Every client runs the same rule on what it has heard, the lowest live id takes over
+	fn elect_host(&mut self) {
+		let members = self.live_members();
+		let elected = members.iter().map(|(id, _)| *id).min().unwrap_or(self.id);
+		if elected != self.id {
+			return;
+		}
+
+		println!("Datalink {} taking over as host", self.id);
+		self.is_host = true;
+		self.awaiting_block_since = None;
+		self.join_requests.clear();
+
+		// Rebuild the schedule from who we have heard from, keeping every block index so nobody's slot moves
+		let block_count = members.iter().map(|(_, block)| *block + 1).max().unwrap_or(0).max(self.total_blocks).max(2);
+		self.blocks = (0..block_count).map(TimeBlock::new).collect();
+		self.blocks[0].clients.push(self.id);
+		for (id, block) in members.iter() {
+			self.blocks[*block as usize].clients.push(*id);
+		}
+		self.total_blocks = block_count;
+
+		// Anyone we knew of but haven't heard from lately might still come back, their ids sit out a while
+		let now = self.clock.now();
+		self.member_since = members.iter().map(|(id, _)| (*id, now)).collect();
+		self.pending_assignments.clear();
+		self.extra_slots.clear();
+		self.member_demand.clear();
+		self.extra_blocks = 0;
+		self.schedule_epoch = (self.schedule_epoch + 1) & EPOCH_MASK; // Whatever the old host lent out is void
+		self.retired_ids = self
+			.friendly_positions
+			.iter()
+			.filter(|f| !self.member_since.contains_key(&f.dl_id))
+			.map(|f| (f.dl_id, now))
+			.collect();
+		// Nobody knows which track ids the old host handed out last, so they all count as just handed out
+		self.track_id_assigned = self
+			.id_map
+			.values()
+			.chain(self.tracks.iter().map(|t| &t.track_id))
+			.map(|id| (*id, now))
+			.collect();
+		self.retired_track_ids.clear();
+		let highest_track_id = self.track_id_assigned.keys().max().copied().unwrap_or(0);
+		self.next_track_id = highest_track_id % (TRACK_ID_LIMIT - 1) + 1;
+	}
+
 	fn send_join_request(&mut self) {
-		let request_id = random::<u8>();
-		println!("Sending join request with id {}", request_id);
+		let slot = random::<u8>() % JOIN_SLOTS;
+		println!("Sending join request in slot {}", slot);
 
-		let join_request_packet = JoinRequest::new(request_id, self.next_free_block);
+		let join_request_packet = JoinRequest::new(slot, self.next_free_block);
 		self.transmit(join_request_packet.serialize().value);
 
-		self.our_join_request_id = request_id;
+		self.our_join_slot = slot;
 		self.our_request_block = self.next_free_block;
 
 		self.status = DatalinkStatus::WaitingForId;
 	}
 
-	fn get_block(&mut self, block_index: u8) -> Option<&mut TimeBlock> {
-		self.blocks.iter_mut().find(|block| block.index == block_index)
-	}
-
 	fn process_track_id(&mut self, packet: TrackId) {
 		if packet.track_id > 0 {
+			self.check_track_contact(packet.track_id, packet.contact_id);
 			self.id_map.insert(packet.contact_id, packet.track_id);
 		}
 
 		// Someone is requesting a track id
 		if self.is_host && packet.track_id == 0 {
-			let new_id = self.next_track_id;
-			self.next_track_id += 1;
-			self.id_map.insert(packet.contact_id, new_id);
+			// Contacts we already track keep their id, more than one vehicle can see the same thing
+			let new_id = match self.id_map.get(&packet.contact_id) {
+				Some(id) => *id,
+				None => get!(self.allocate_track_id(packet.contact_id)), // The requester keeps asking
+			};
 
-			let reply = TrackId::new(new_id, packet.contact_id);
-			self.message_queue.push(Message::TrackId(reply));
+			// Requests repeat until the reply arrives, the reply is already being retried
+			let already_replying = self
+				.reliable
+				.pending_to(packet.requester)
+				.any(|m| matches!(m, Message::TrackId(reply) if reply.contact_id == packet.contact_id));
+			if !already_replying {
+				let reply = TrackId::new(new_id, packet.contact_id, packet.requester);
+				self.send_reliable(packet.requester, Message::TrackId(reply));
+			}
 		}
 	}
 
@@ -405,37 +1030,51 @@ impl Datalink {
 			return;
 		}
 
-		if self.join_request_approve_id != INVALID {
-			println!(
-				"Failed to approve join request {} because {} is already waiting for approval",
-				join_request.request_id, self.join_request_approve_id
-			);
-			return;
+		self.join_requests.push((join_request.slot, join_request.block));
+	}
+
+	// Everyone asking since the last NetInfo gets in at once, unless two picked the same slot. They can't tell
+	// which of them was meant so both try again in a new one. Approved joiners get a run of ids starting at
+	// next_id, in slot order, and the block they asked for if it has room, sent to them in a SlotAssign.
+	// Returns the approved slots
+	fn approve_joins(&mut self) -> u16 {
+		let mut requests = std::mem::take(&mut self.join_requests);
+		requests.sort();
+		let mut unique: Vec<(u8, u8)> = requests
+			.iter()
+			.filter(|(slot, _)| requests.iter().filter(|(other, _)| other == slot).count() == 1)
+			.copied()
+			.collect();
+		if unique.is_empty() {
+			return 0;
 		}
 
-		let next_id = self.next_id + 1;
-		let request_block = self.get_block(join_request.block);
-		match request_block {
-			Some(block) => {
-				if block.clients.len() < 4 {
-					block.clients.push(next_id);
-					self.accept_join_request(join_request.request_id);
+		// Whoever doesn't fit in the longest run of free ids has to ask again
+		let first = loop {
+			let count = unique.len() as u8;
+			match (1..INVALID - count).find(|first| (*first..first + count).all(|id| self.is_free_id(id))) {
+				Some(first) => break first,
+				None if count > 1 => {
+					unique.pop();
+				}
+				None => {
+					println!("Failed to approve join requests, no ids left");
+					return 0;
 				}
 			}
-			None => {
-				let mut new_block = TimeBlock::new(join_request.block);
-				new_block.clients.push(next_id);
-				self.blocks.push(new_block);
-				self.accept_join_request(join_request.request_id);
-			}
+		};
+
+		let mut approvals = 0;
+		for (i, (slot, block)) in unique.into_iter().enumerate() {
+			let id = first + i as u8;
+			self.place_member(id, block);
+			self.pending_assignments.insert(id, f32::MIN); // Joiners stay quiet until they hear where they are
+			approvals |= 1 << slot;
+			println!("Accepted join request in slot {} as {}", slot, id);
 		}
-	}
-
-	fn accept_join_request(&mut self, request_id: u8) {
-		self.join_request_approve_id = request_id;
-		self.next_id += 1;
+		self.next_id = first;
 
-		println!("Accepted join request {}", request_id);
+		approvals
 	}
 
 	fn process_leave_network(&mut self, leave_network: LeaveNetwork) {
@@ -443,14 +1082,7 @@ impl Datalink {
 			return;
 		}
 
-		self.remove_client_from_block(leave_network.block, leave_network.id);
-	}
-
-	fn remove_client_from_block(&mut self, block: u8, id: u8) {
-		let block = get!(self.get_block(block));
-
-		let index = get!(block.clients.iter().position(|f| *f == id));
-		block.clients.remove(index);
+		self.remove_member(leave_network.id);
 	}
 
 	fn create_track(&mut self, track_id: u16) -> &mut DatalinkTrack {
@@ -472,22 +1104,187 @@ impl Datalink {
 		self.tracks.iter().any(|f| f.track_id == track_id)
 	}
 
-	fn get_track(&self, track_id: u16) -> Option<&DatalinkTrack> {
+	pub fn get_track(&self, track_id: u16) -> Option<&DatalinkTrack> {
 		self.tracks.iter().find(|f| f.track_id == track_id)
 	}
 
-	fn update_track_from_local_data(&mut self, contact_id_64: i64, rc_type: RadarTargetType, position: Vector3, velocity: Vector3) {
+	// Our own radar's view of a track goes into the fused picture like any other report
+	pub fn update_track_from_local_data(&mut self, contact_id_64: i64, rc_type: RadarTargetType, position: Vector3, velocity: Vector3, range: f32) {
 		let track_id = get!(self.net_id(contact_id_64));
+		let now = self.clock.now();
 
 		let track = self.get_track_mut(track_id);
 		track.contact_id = dl_crunch_id(contact_id_64);
 		track.contact_type = rc_type;
-		track.position = position;
-		track.velocity = velocity;
-		track.last_update_timestamp = now();
+
+		self.fusion.report_position(track_id, self.id, position, range, now);
+		self.fusion.report_velocity(track_id, self.id, velocity);
+		self.fuse_track(track_id);
+	}
+
+	fn report_source(&self, track_id: u16) -> u8 {
+		*self.report_sources.get(&track_id).unwrap_or(&INVALID)
+	}
+
+	fn source_distance(&self, source: u8, position: Vector3) -> f32 {
+		match self.friendly_positions.iter().find(|f| f.dl_id == source) {
+			Some(friendly) => (friendly.position - position).length(),
+			None => UNKNOWN_SOURCE_DISTANCE,
+		}
+	}
+
+	// Puts the combined estimate from every source reporting on it into the track
+	fn fuse_track(&mut self, track_id: u16) {
+		let fused = get!(self.fusion.fuse(track_id, self.clock.now()));
+
+		let track = self.get_track_mut(track_id);
+		track.position = fused.position;
+		track.velocity = fused.velocity;
+		track.last_update_timestamp = fused.timestamp;
+		track.sources = fused.sources;
+	}
+
+	fn drop_source(&mut self, track_id: u16, source: u8) {
+		if self.fusion.drop_source(track_id, source) {
+			self.fuse_track(track_id);
+		} else {
+			self.remove_track(track_id);
+		}
+	}
+
+	pub fn get_track_for_contact(&self, contact_id_64: i64) -> Option<&DatalinkTrack> {
+		let track_id = self.id_map.get(&dl_crunch_id(contact_id_64))?;
+		self.get_track(*track_id)
+	}
+
+	// Our radar lost the contact, tell everyone else to stop using it. The id mapping is kept until the host
+	// retires the id, so if anyone still sees it their next report brings it back under the same track id
+	pub fn drop_local_track(&mut self, contact_id_64: i64) {
+		let track_id = get!(self.id_map.get(&dl_crunch_id(contact_id_64)).copied());
+
+		self.drop_source(track_id, self.id);
+		self.send_queue.cancel_track(track_id);
+		self.send_message(Message::TrackDrop(TrackDrop::new(track_id, self.id)));
+	}
+
+	// Host. The next id along that isn't in use or sitting out, wrapping around at TRACK_ID_LIMIT. None when
+	// they are all taken, handing out one that is still in use would mix two contacts up
+	fn allocate_track_id(&mut self, contact_id: u32) -> Option<u16> {
+		let mut used: HashSet<u16> = self.id_map.values().copied().collect();
+		used.extend(self.tracks.iter().map(|t| t.track_id));
+		used.extend(self.retired_track_ids.iter().map(|(id, _)| *id));
+
+		let ids = TRACK_ID_LIMIT - 1; // 0 is a request
+		let id = match (0..ids).map(|i| (self.next_track_id - 1 + i) % ids + 1).find(|id| !used.contains(id)) {
+			Some(id) => id,
+			None => {
+				self.refused_track_ids += 1;
+				self.track_id_exhaustions += 1;
+				return None;
+			}
+		};
+
+		self.next_track_id = id % ids + 1;
+		self.id_map.insert(contact_id, id);
+		self.track_id_assigned.insert(id, self.clock.now());
+
+		Some(id)
 	}
 
-	fn get_ship_tracks(&self) -> Vec<DatalinkTrack> {
+	// Host. Ids whose track is gone are taken back and everyone is told to forget them. Ones nobody has
+	// reported on yet get TRACK_MAX_AGE to show up
+	fn maintain_track_ids(&mut self) {
+		let now = self.clock.now();
+		if now - self.last_track_sweep < TRACK_SWEEP_INTERVAL {
+			return;
+		}
+		self.last_track_sweep = now;
+
+		if self.refused_track_ids > 0 {
+			println!("Out of track ids, refused {} requests", self.refused_track_ids);
+			self.refused_track_ids = 0;
+		}
+
+		self.retired_track_ids.retain(|(_, time)| now - time < ID_REUSE_DELAY);
+
+		let mut stale: Vec<u16> = self
+			.id_map
+			.values()
+			.copied()
+			.filter(|id| !self.does_track_exist(*id) && self.track_id_assigned.get(id).is_none_or(|assigned| now - assigned > TRACK_MAX_AGE))
+			.collect();
+		stale.sort();
+		stale.dedup();
+		for id in stale {
+			self.retire_track_id(id);
+			self.retired_track_ids.push((id, now));
+		}
+
+		let mut recent: Vec<u16> = self
+			.retired_track_ids
+			.iter()
+			.filter(|(_, time)| now - time < RETIRE_REPEAT)
+			.map(|(id, _)| *id)
+			.collect();
+		recent.sort();
+		for retire in TrackRetire::from_ids(&recent) {
+			self.send_framed(FramedMessage::TrackRetire(retire));
+		}
+	}
+
+	// Forgets everything keyed on the id, from now on it means some other contact
+	fn retire_track_id(&mut self, track_id: u16) {
+		self.id_map.retain(|_, id| *id != track_id);
+		self.track_id_assigned.remove(&track_id);
+		self.report_sources.remove(&track_id);
+		self.send_queue.cancel_track(track_id);
+		self.remove_track(track_id);
+	}
+
+	fn handle_track_retire(&mut self, track_retire: TrackRetire) {
+		for id in track_retire.ids() {
+			self.retire_track_id(id);
+		}
+	}
+
+	// A different contact turned up under an id we already had. Either we missed its retirement or ids wrapped
+	// around, so what we had is thrown out instead of mixing the two up
+	fn check_track_contact(&mut self, track_id: u16, contact_id: u32) {
+		let mapped = self.id_map.iter().any(|(contact, id)| *id == track_id && *contact != contact_id);
+		let tracked = self.get_track(track_id).is_some_and(|t| t.contact_id != 0 && t.contact_id != contact_id);
+		if !mapped && !tracked {
+			return;
+		}
+
+		self.track_id_conflicts += 1;
+		println!("Track id {} now belongs to contact {}, dropping what we had under it", track_id, contact_id);
+		self.retire_track_id(track_id);
+	}
+
+	fn remove_track(&mut self, track_id: u16) {
+		self.tracks.retain(|f| f.track_id != track_id);
+		self.fusion.drop_track(track_id);
+	}
+
+	// Tracks nobody has reported on in a while, e.g. the reporter died before it could send a drop
+	fn age_out_tracks(&mut self) {
+		let now = self.clock.now();
+		let aged: Vec<u16> = self
+			.tracks
+			.iter()
+			.filter(|f| f.last_update_timestamp != f32::MAX && now - f.last_update_timestamp >= TRACK_MAX_AGE)
+			.map(|f| f.track_id)
+			.collect();
+		for track_id in aged {
+			self.remove_track(track_id);
+		}
+	}
+
+	pub fn get_tracks(&self) -> &[DatalinkTrack] {
+		&self.tracks
+	}
+
+	pub fn get_ship_tracks(&self) -> Vec<DatalinkTrack> {
 		self
 			.tracks
 			.iter()
@@ -496,25 +1293,22 @@ impl Datalink {
 			.collect()
 	}
 
-	fn disconnect(&mut self) {
+	pub fn disconnect(&mut self) {
 		self.disconnect_on_next_send = true;
 	}
 
-	fn net_id(&mut self, contact_id_64: i64) -> Option<u16> {
+	pub fn net_id(&mut self, contact_id_64: i64) -> Option<u16> {
 		let contact_id = dl_crunch_id(contact_id_64);
 		if let Some(id) = self.id_map.get(&contact_id) {
 			return Some(*id);
 		}
 
 		if self.is_host {
-			let new_id = self.next_track_id;
-			self.next_track_id += 1;
-			self.id_map.insert(contact_id, new_id);
-			return Some(new_id);
+			return self.allocate_track_id(contact_id);
 		}
 
 		let mut has_pending_request = false;
-		for message in &self.message_queue {
+		for message in self.send_queue.messages() {
 			if let Message::TrackId(track_id) = message {
 				if track_id.contact_id == contact_id {
 					has_pending_request = true;
@@ -524,94 +1318,154 @@ impl Datalink {
 		}
 
 		if !has_pending_request {
-			let track_id = TrackId::new(0, contact_id);
-			self.message_queue.push(Message::TrackId(track_id));
+			let track_id = TrackId::new(0, contact_id, self.id);
+			self.send_queue.push(Message::TrackId(track_id));
 		}
 
 		None
 	}
-}
 
-thread_local! {
-	static DL: RefCell<Datalink> = RefCell::new(Datalink::new());
-}
+	pub fn id(&self) -> u8 {
+		self.id
+	}
 
-pub fn dl_declare_host() {
-	DL.with(|fc| {
-		let mut fc = fc.borrow_mut();
-		fc.setup_as_host();
-	});
-}
+	pub fn block(&self) -> u8 {
+		self.our_block
+	}
 
-pub fn update_dl() {
-	DL.with(|fc| {
-		let mut fc = fc.borrow_mut();
-		fc.update();
-	});
-}
+	pub fn is_host(&self) -> bool {
+		self.is_host
+	}
 
-pub fn get_tick() -> u32 {
-	DL.with(|fc| {
-		let fc = fc.borrow();
-		fc.tick
-	})
-}
+	// Host only, members whose last LinkStatus gave this role. Lowest id first
+	pub fn members_with_role(&self, role: LinkRole) -> Vec<u8> {
+		let mut members: Vec<u8> = self.member_roles.iter().filter(|(_, r)| **r == role).map(|(id, _)| *id).collect();
+		members.sort();
+		members
+	}
 
-pub fn send_message(message: Message) {
-	DL.with(|fc| {
-		let mut fc = fc.borrow_mut();
-		fc.send_message(message);
-	});
-}
+	pub fn channel(&self) -> u8 {
+		self.channel
+	}
 
-pub fn get_core_message_queue() -> Vec<Message> {
-	DL.with(|fc| {
-		let mut fc = fc.borrow_mut();
-		fc.get_core_message_queue()
-	})
-}
+	// Received words that couldn't be decoded and were dropped
+	pub fn decode_errors(&self) -> u32 {
+		self.decode_errors
+	}
 
-pub fn get_dl_track(track_id: u16) -> Option<DatalinkTrack> {
-	DL.with(|fc| fc.borrow().get_track(track_id).copied())
-}
+	// Orders refused for a missing or bad MAC, or for being a replay
+	pub fn auth_rejections(&self) -> u32 {
+		self.auth_rejections
+	}
 
-pub fn get_ship_tracks() -> Vec<DatalinkTrack> {
-	DL.with(|fc| fc.borrow().get_ship_tracks())
-}
+	// Framed messages we dropped for needing more than MAX_FRAGMENTS
+	pub fn oversized_messages(&self) -> u32 {
+		self.oversized_messages
+	}
 
-pub fn update_track_from_local_data(contact_id_64: i64, rc_type: RadarTargetType, position: Vector3, velocity: Vector3) {
-	DL.with(|fc| fc.borrow_mut().update_track_from_local_data(contact_id_64, rc_type, position, velocity));
-}
+	// Our slots are all in use and the queue keeps growing, time to send less
+	pub fn is_saturated(&self) -> bool {
+		self.counters.is_saturated(self.send_queue.len())
+	}
 
-pub fn datalink_disconnect() {
-	DL.with(|fc| {
-		fc.borrow_mut().disconnect();
-	});
-}
+	pub fn stats(&self) -> DatalinkStats {
+		let now = self.clock.now();
 
-pub fn dl_net_id(contact_id: i64) -> Option<u16> {
-	DL.with(|fc| fc.borrow_mut().net_id(contact_id))
-}
+		let mut queue_depth: Vec<(MessageKey, usize)> = Vec::new();
+		for message in self.send_queue.messages() {
+			let key = message.message_type();
+			match queue_depth.iter_mut().find(|(k, _)| *k == key) {
+				Some((_, count)) => *count += 1,
+				None => queue_depth.push((key, 1)),
+			}
+		}
 
-pub fn dl_crunch_id(contact_id: i64) -> u32 {
-	(contact_id >> 32) as u32
+		let members = self
+			.friendly_positions
+			.iter()
+			.filter(|f| now - f.last_heard < MEMBER_TIMEOUT && f.dl_id != self.id)
+			.map(|f| DatalinkMember {
+				id: f.dl_id,
+				block: f.block,
+				position: f.position,
+				silent_for: now - f.last_heard,
+				extra_slots: self.extra_slots.get(&f.dl_id).map_or(0, |mask| mask.count_ones()),
+			})
+			.collect();
+
+		DatalinkStats {
+			status: self.status,
+			id: self.id,
+			is_host: self.is_host,
+			members,
+			total_blocks: self.total_blocks,
+			slots_per_frame: 1 + self.extra_blocks.count_ones(),
+			schedule_epoch: self.schedule_epoch,
+
+			queue_depth,
+			queue_by_class: CLASSES.into_iter().map(|c| (c, self.send_queue.len_of(c))).collect(),
+			reliable_outstanding: self.reliable.outstanding(),
+
+			packets_sent: self.counters.sent,
+			packets_received: self.counters.received,
+			packets_dropped: self.counters.dropped,
+			decode_errors: self.decode_errors,
+			auth_rejections: self.auth_rejections,
+			oversized_messages: self.oversized_messages,
+
+			messages_pushed_per_second: self.counters.pushed_last_second(),
+			slot_utilization: self.counters.slot_utilization(),
+			channel_utilization: self.counters.channel_utilization(),
+			saturated: self.is_saturated(),
+
+			time_since_net_info: now - self.last_net_info_time,
+
+			track_ids_in_use: self.id_map.values().collect::<HashSet<_>>().len(),
+			track_id_exhaustions: self.track_id_exhaustions,
+			track_id_conflicts: self.track_id_conflicts,
+		}
+	}
+
+	// Set once when a client is accepted into the network, the control system is configured off of it
+	pub fn take_newly_joined(&mut self) -> bool {
+		let joined = self.newly_joined;
+		self.newly_joined = false;
+		joined
+	}
+
+	pub fn is_position_friendly(&self, position: Vector3) -> bool {
+		self.friendly_positions.iter().any(|f| (f.position - position).length() < IFF_FRIEND_DISTANCE)
+	}
+
+	pub fn get_ship_pos_from_iff(&self) -> Option<Vector3> {
+		let pos = self.friendly_positions.iter().find(|f| f.dl_id == 0);
+		pos.map(|f| f.position)
+	}
 }
 
-pub fn own_dl_id() -> u8 {
-	DL.with(|fc| fc.borrow().id)
+// Set with the FleetChannel env var (1-15). Without it, or with anything outside that, each side of the map gets
+// its own, the same way the ship picks its side, so two fleets running this code don't end up in each other's
+// network. Channel 0 is never used, a word of all zeros is the likeliest garbage
+fn fleet_channel(hw: &dyn Hardware) -> u8 {
+	if let Some(channel) = hw.vehicle_env("FleetChannel").and_then(|c| c.parse::<u8>().ok()) {
+		if (1..=15).contains(&channel) {
+			return channel;
+		}
+		println!("FleetChannel {} is not 1-15, using the default", channel);
+	}
+
+	if hw.vehicle_get_position().2 > 0.0 {
+		1
+	} else {
+		2
+	}
 }
 
-pub fn is_position_friendly(position: Vector3) -> bool {
-	DL.with(|fc| {
-		let fc = fc.borrow();
-		fc.friendly_positions.iter().any(|f| (f.position - position).length() < IFF_FRIEND_DISTANCE)
-	})
+// Orders a forged message could do real damage with, signed when a DatalinkKey is set
+fn is_protected(message: &Message) -> bool {
+	matches!(message, Message::AssignAttackTarget(_) | Message::InterceptTaskAssign(_))
 }
 
-pub fn get_ship_pos_from_iff() -> Option<Vector3> {
-	DL.with(|fc| {
-		let fc = fc.borrow();
-		let pos = fc.friendly_positions.iter().find(|f| f.dl_id == 0);
-		pos.map(|f| f.position)
-	})
+pub fn dl_crunch_id(contact_id: i64) -> u32 {
+	(contact_id >> 32) as u32
 }
@@ -0,0 +1,24 @@
+use std::fmt;
+
+// Why a received radio word was thrown away. Anything on the channel can end up in our inbox, including
+// corrupted words and other fleets' traffic, so none of these are allowed to crash the vehicle
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+	UnknownKey(u8),
+	UnknownFramedKey(u8),
+	Truncated,               // Ran out of bits before the message was complete
+	InvalidFragment(u8, u8), // (index, count), index past the end of its message
+	ChecksumMismatch,        // Reassembled from fragments of more than one message
+}
+
+impl fmt::Display for DecodeError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			DecodeError::UnknownKey(key) => write!(f, "unknown message key {}", key),
+			DecodeError::UnknownFramedKey(key) => write!(f, "unknown framed message key {}", key),
+			DecodeError::Truncated => write!(f, "message truncated"),
+			DecodeError::InvalidFragment(index, count) => write!(f, "fragment {} of {}", index, count),
+			DecodeError::ChecksumMismatch => write!(f, "checksum mismatch"),
+		}
+	}
+}
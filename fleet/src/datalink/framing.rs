@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use enum_mac::EnumKeys;
+
+use super::{
+	bit_buffer::BitBuffer,
+	decode_error::DecodeError,
+	messages::{
+		authenticated::Authenticated,
+		fragment::{Fragment, FRAGMENT_PAYLOAD_BITS, MAX_FRAGMENTS},
+		message::Message,
+		reliable::Reliable,
+		single::Single,
+		track_retire::TrackRetire,
+		track_state::TrackState,
+	},
+};
+
+const REASSEMBLY_TIMEOUT: f32 = 2.0; // Partial messages older than this lost a fragment
+const SEQUENCE_COUNT: u8 = 16;
+const CHECKSUM_BITS: usize = 8; // At the end of the last fragment, catches fragments of two messages put together
+const CHECKSUM_POLYNOMIAL: u8 = 0x07;
+
+// Messages too big for one radio word. They get split into Fragments and put back together on the other end
+pub trait FramedDatalinkMessage: Sized {
+	fn serialize(&self, buffer: &mut BitBuffer);
+	fn parse(buffer: &mut BitBuffer) -> Result<Self, DecodeError>;
+
+	fn message_type(&self) -> FramedMessageKey;
+}
+
+#[derive(EnumKeys, Clone, Debug)]
+pub enum FramedMessage {
+	TrackState(TrackState),
+	Reliable(Reliable),
+	Authenticated(Authenticated),
+	Single(Single),
+	TrackRetire(TrackRetire),
+}
+
+impl FramedMessage {
+	pub fn parse(buffer: &mut BitBuffer) -> Result<FramedMessage, DecodeError> {
+		let message_type = FramedMessageKey::try_from(buffer.read(4) as u8).map_err(DecodeError::UnknownFramedKey)?;
+
+		let message = match message_type {
+			FramedMessageKey::TrackState => FramedMessage::TrackState(TrackState::parse(buffer)?),
+			FramedMessageKey::Reliable => FramedMessage::Reliable(Reliable::parse(buffer)?),
+			FramedMessageKey::Authenticated => FramedMessage::Authenticated(Authenticated::parse(buffer)?),
+			FramedMessageKey::Single => FramedMessage::Single(Single::parse(buffer)?),
+			FramedMessageKey::TrackRetire => FramedMessage::TrackRetire(TrackRetire::parse(buffer)?),
+		};
+
+		if buffer.overflowed() {
+			return Err(DecodeError::Truncated);
+		}
+
+		Ok(message)
+	}
+
+	pub fn serialize(&self, buffer: &mut BitBuffer) {
+		match self {
+			FramedMessage::TrackState(track_state) => track_state.serialize(buffer),
+			FramedMessage::Reliable(reliable) => reliable.serialize(buffer),
+			FramedMessage::Authenticated(authenticated) => authenticated.serialize(buffer),
+			FramedMessage::Single(single) => single.serialize(buffer),
+			FramedMessage::TrackRetire(track_retire) => track_retire.serialize(buffer),
+		}
+	}
+
+	// The one word message inside, if there is one
+	pub fn inner_message(&self) -> Option<&Message> {
+		match self {
+			FramedMessage::Reliable(reliable) => Some(&reliable.message),
+			FramedMessage::Single(single) => Some(&single.message),
+			_ => None,
+		}
+	}
+
+	pub fn fragment(&self, source: u8, sequence: u8) -> Result<Vec<Fragment>, Oversized> {
+		let mut buffer = BitBuffer::new();
+		self.serialize(&mut buffer);
+
+		let count = (buffer.len() + CHECKSUM_BITS).div_ceil(FRAGMENT_PAYLOAD_BITS);
+		if count > MAX_FRAGMENTS {
+			return Err(Oversized(buffer.len()));
+		}
+
+		// Pad out the last fragment, leaving room for the checksum
+		buffer.write(0, count * FRAGMENT_PAYLOAD_BITS - CHECKSUM_BITS - buffer.len());
+		let checksum = checksum(&mut buffer.clone(), buffer.len());
+		buffer.write(checksum as u64, CHECKSUM_BITS);
+
+		Ok((0..count)
+			.map(|i| Fragment::new(source, sequence % SEQUENCE_COUNT, i as u8, count as u8, buffer.read(FRAGMENT_PAYLOAD_BITS)))
+			.collect())
+	}
+}
+
+// A framed message that needs more than MAX_FRAGMENTS, with its size in bits
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Oversized(pub usize);
+
+// CRC-8 over the next len bits
+fn checksum(buffer: &mut BitBuffer, len: usize) -> u8 {
+	(0..len).fold(0, |crc, _| {
+		let top = (crc >> 7) ^ buffer.read(1) as u8;
+		let crc = crc << 1;
+		if top == 1 {
+			crc ^ CHECKSUM_POLYNOMIAL
+		} else {
+			crc
+		}
+	})
+}
+
+struct PartialMessage {
+	fragments: Vec<Option<u64>>,
+	started: f32,
+}
+
+// Collects fragments per (sender, sequence) until a message is complete
+#[derive(Default)]
+pub struct Reassembler {
+	partial: HashMap<(u8, u8), PartialMessage>,
+}
+
+impl Reassembler {
+	pub fn new() -> Reassembler {
+		Reassembler { partial: HashMap::new() }
+	}
+
+	pub fn add(&mut self, fragment: Fragment, now: f32) -> Result<Option<FramedMessage>, DecodeError> {
+		self.partial.retain(|_, p| now - p.started < REASSEMBLY_TIMEOUT);
+
+		if fragment.index >= fragment.count {
+			return Err(DecodeError::InvalidFragment(fragment.index, fragment.count));
+		}
+
+		let key = (fragment.source, fragment.sequence);
+		let partial = self.partial.entry(key).or_insert_with(|| PartialMessage {
+			fragments: vec![None; fragment.count as usize],
+			started: now,
+		});
+
+		// Sequence got reused for a different message before the old one finished
+		if partial.fragments.len() != fragment.count as usize || partial.fragments[fragment.index as usize].is_some() {
+			*partial = PartialMessage {
+				fragments: vec![None; fragment.count as usize],
+				started: now,
+			};
+		}
+
+		partial.fragments[fragment.index as usize] = Some(fragment.payload);
+		if partial.fragments.iter().any(|f| f.is_none()) {
+			return Ok(None);
+		}
+
+		let partial = self.partial.remove(&key).unwrap();
+		let mut buffer = BitBuffer::new();
+		for payload in partial.fragments.into_iter().flatten() {
+			buffer.write(payload, FRAGMENT_PAYLOAD_BITS);
+		}
+
+		let mut check = buffer.clone();
+		let expected = checksum(&mut check, buffer.len() - CHECKSUM_BITS);
+		if check.read(CHECKSUM_BITS) as u8 != expected {
+			return Err(DecodeError::ChecksumMismatch);
+		}
+
+		FramedMessage::parse(&mut buffer).map(Some)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use protologic_core::radar::RadarTargetType;
+
+	use crate::{datalink::messages::authenticated::Authenticated, math::vector3::Vector3};
+
+	use super::*;
+
+	const SOURCE: u8 = 4;
+	const SEQUENCE: u8 = 9;
+
+	fn track_state(track_id: u16) -> FramedMessage {
+		let position = Vector3::new(1000.0, 0.0, -2000.0);
+		FramedMessage::TrackState(TrackState::new(
+			track_id,
+			77,
+			RadarTargetType::Missile,
+			false,
+			SOURCE,
+			position,
+			Vector3::zero(),
+		))
+	}
+
+	fn track_id(message: Option<FramedMessage>) -> Option<u16> {
+		match message {
+			Some(FramedMessage::TrackState(track_state)) => Some(track_state.track_id),
+			_ => None,
+		}
+	}
+
+	#[test]
+	fn fragments_are_put_back_together() {
+		let fragments = track_state(12).fragment(SOURCE, SEQUENCE).unwrap();
+		assert!(fragments.len() > 1);
+
+		let mut reassembler = Reassembler::new();
+		let (last, rest) = fragments.split_last().unwrap();
+		for fragment in rest {
+			assert!(reassembler.add(*fragment, 0.0).unwrap().is_none());
+		}
+		assert_eq!(track_id(reassembler.add(*last, 0.0).unwrap()), Some(12));
+	}
+
+	#[test]
+	fn fragments_of_two_messages_are_not_spliced_together() {
+		// The rest of the first message is lost, then its sequence comes around again and the second's first fragment is lost
+		let first = track_state(12).fragment(SOURCE, SEQUENCE).unwrap();
+		let second = track_state(13).fragment(SOURCE, SEQUENCE + SEQUENCE_COUNT).unwrap();
+
+		let mut reassembler = Reassembler::new();
+		assert!(reassembler.add(first[0], 0.0).unwrap().is_none());
+		let (last, middle) = second[1..].split_last().unwrap();
+		for fragment in middle {
+			assert!(reassembler.add(*fragment, 0.5).unwrap().is_none());
+		}
+		assert_eq!(reassembler.add(*last, 0.5).unwrap_err(), DecodeError::ChecksumMismatch);
+	}
+
+	#[test]
+	fn fragment_for_a_filled_index_starts_over() {
+		let first = track_state(12).fragment(SOURCE, SEQUENCE).unwrap();
+		let second = track_state(13).fragment(SOURCE, SEQUENCE).unwrap();
+
+		let mut reassembler = Reassembler::new();
+		assert!(reassembler.add(first[0], 0.0).unwrap().is_none());
+		assert!(reassembler.add(first[1], 0.0).unwrap().is_none());
+
+		// The second message's copy of index 1 throws out everything of the first
+		for fragment in second.iter().skip(1) {
+			assert!(reassembler.add(*fragment, 0.5).unwrap().is_none());
+		}
+		assert_eq!(track_id(reassembler.add(second[0], 0.5).unwrap()), Some(13));
+	}
+
+	#[test]
+	fn oversized_message_is_refused() {
+		let mut payload = BitBuffer::new();
+		payload.write(0, 64);
+		payload.write(0, 64);
+		payload.write(0, 64);
+		payload.write(0, 63);
+		let message = FramedMessage::Authenticated(Authenticated::new(0, 0, payload));
+
+		assert_eq!(message.fragment(SOURCE, SEQUENCE).unwrap_err(), Oversized(315));
+	}
+}
@@ -0,0 +1,18 @@
+use enum_mac::DatalinkMessage;
+
+// Confirms a reliable message arrived, see datalink::reliable_channel
+#[derive(DatalinkMessage, Clone, Debug)]
+pub struct Ack {
+	#[bits(8)]
+	pub source: u8, // Who is acknowledging
+	#[bits(8)]
+	pub destination: u8, // Sender of the original message
+	#[bits(8)]
+	pub sequence: u8,
+}
+
+impl Ack {
+	pub fn new(source: u8, destination: u8, sequence: u8) -> Ack {
+		Ack { source, destination, sequence }
+	}
+}
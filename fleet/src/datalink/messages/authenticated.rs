@@ -0,0 +1,68 @@
+use crate::datalink::{
+	bit_buffer::BitBuffer,
+	decode_error::DecodeError,
+	framing::{FramedDatalinkMessage, FramedMessage, FramedMessageKey},
+};
+
+// A framed message with a MAC over it and the tick it was signed on, see datalink::authenticator. The inner
+// message is kept as raw bits so the MAC is checked against exactly what was sent
+#[derive(Clone, Debug)]
+pub struct Authenticated {
+	pub tick: u16,
+	pub mac: u32,
+	pub payload: BitBuffer,
+}
+
+impl Authenticated {
+	pub fn payload_of(message: &FramedMessage) -> BitBuffer {
+		let mut payload = BitBuffer::new();
+		message.serialize(&mut payload);
+		payload
+	}
+
+	pub fn new(tick: u16, mac: u32, payload: BitBuffer) -> Authenticated {
+		Authenticated { tick, mac, payload }
+	}
+
+	// Only once the MAC has been checked
+	pub fn open(&self) -> Result<FramedMessage, DecodeError> {
+		let mut payload = BitBuffer::from_words(self.payload.words.clone(), self.payload.len());
+		FramedMessage::parse(&mut payload)
+	}
+}
+
+impl FramedDatalinkMessage for Authenticated {
+	fn serialize(&self, buffer: &mut BitBuffer) {
+		buffer.write(self.message_type() as u64, 4); // 4
+		buffer.write(self.tick as u64, 16); // 20
+		buffer.write(self.mac as u64, 32); // 52
+		buffer.write(self.payload.len() as u64, 8); // 60
+
+		let mut payload = BitBuffer::from_words(self.payload.words.clone(), self.payload.len());
+		let mut remaining = payload.len();
+		while remaining > 0 {
+			let len = remaining.min(64);
+			buffer.write(payload.read(len), len);
+			remaining -= len;
+		}
+	}
+
+	fn parse(buffer: &mut BitBuffer) -> Result<Self, DecodeError> {
+		let tick = buffer.read(16) as u16;
+		let mac = buffer.read(32) as u32;
+		let mut remaining = buffer.read(8) as usize;
+
+		let mut payload = BitBuffer::new();
+		while remaining > 0 {
+			let len = remaining.min(64);
+			payload.write(buffer.read(len), len);
+			remaining -= len;
+		}
+
+		Ok(Authenticated::new(tick, mac, payload))
+	}
+
+	fn message_type(&self) -> FramedMessageKey {
+		FramedMessageKey::Authenticated
+	}
+}
@@ -0,0 +1,33 @@
+use enum_mac::DatalinkMessage;
+
+pub const FRAGMENT_PAYLOAD_BITS: usize = 38;
+pub const MAX_FRAGMENTS: usize = 8;
+
+// One radio word worth of a framed message, see datalink::framing
+#[derive(DatalinkMessage, Clone, Copy, Debug)]
+pub struct Fragment {
+	#[bits(8)]
+	pub source: u8, // dl_id of the sender, sequences are per sender
+	#[bits(4)]
+	pub sequence: u8, // Which message of the sender's this belongs to
+	#[bits(3)]
+	pub index: u8,
+	#[bits(3, encode = count_to_bits, decode = bits_to_count)]
+	pub count: u8, // There is always at least one, so 1-8 fits in 3 bits
+	#[bits(38)]
+	pub payload: u64, // FRAGMENT_PAYLOAD_BITS
+}
+
+impl Fragment {
+	pub fn new(source: u8, sequence: u8, index: u8, count: u8, payload: u64) -> Fragment {
+		Fragment { source, sequence, index, count, payload }
+	}
+}
+
+fn count_to_bits(count: u8) -> u8 {
+	count - 1
+}
+
+fn bits_to_count(bits: u8) -> u8 {
+	bits + 1
+}
@@ -0,0 +1,31 @@
+use enum_mac::DatalinkMessage;
+
+use crate::datalink::slot_allocator::{bits_to_role, role_to_bits, LinkRole};
+
+// Sent by every member about once a second. The host weighs extra slots by role and queue depth, and checks
+// the member is on the schedule it was last given
+#[derive(DatalinkMessage, Clone, Debug)]
+pub struct LinkStatus {
+	#[bits(8)]
+	pub id: u8,
+	#[bits(4)]
+	pub block: u8,
+	#[bits(4)]
+	pub schedule_epoch: u8, // Of the last SlotAssign applied
+	#[bits(2, encode = role_to_bits, decode = bits_to_role)]
+	pub role: LinkRole,
+	#[bits(8)]
+	pub queue_depth: u8, // Words waiting, saturates
+}
+
+impl LinkStatus {
+	pub fn new(id: u8, block: u8, schedule_epoch: u8, role: LinkRole, queue_depth: usize) -> LinkStatus {
+		LinkStatus {
+			id,
+			block,
+			schedule_epoch,
+			role,
+			queue_depth: queue_depth.min(u8::MAX as usize) as u8,
+		}
+	}
+}
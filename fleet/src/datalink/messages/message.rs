@@ -1,11 +1,11 @@
 use enum_mac::EnumKeys;
 
-use crate::datalink::u64_view::U64View;
+use crate::datalink::{decode_error::DecodeError, u64_view::U64View};
 
 use super::{
-	assign_attack_target::AssignAttackTarget, iff_pos::IFFPosition, intercept_task_assign::InterceptTaskAssign, join_request::JoinRequest,
-	leave_network::LeaveNetwork, net_info::NetInfo, ready_attack_time::ReadyAttackTime, track_id::TrackId, track_info::TrackInfo, track_position::TrackPosition,
-	track_velocity::TrackVelocity,
+	ack::Ack, assign_attack_target::AssignAttackTarget, fragment::Fragment, iff_pos::IFFPosition, intercept_task_assign::InterceptTaskAssign,
+	join_request::JoinRequest, leave_network::LeaveNetwork, link_status::LinkStatus, net_info::NetInfo, ready_attack_time::ReadyAttackTime,
+	slot_assign::SlotAssign, track_drop::TrackDrop, track_id::TrackId, track_info::TrackInfo, track_position::TrackPosition, track_velocity::TrackVelocity,
 };
 
 pub trait DatalinkMessage {
@@ -31,15 +31,20 @@ pub enum Message {
 
 	IFFPosition(IFFPosition),
 	InterceptTaskAssign(InterceptTaskAssign),
+	TrackDrop(TrackDrop),
+	Fragment(Fragment),
+	Ack(Ack),
+	SlotAssign(SlotAssign),
+	LinkStatus(LinkStatus),
 }
 
 impl Message {
-	pub fn parse(message: u64) -> Message {
+	pub fn parse(message: u64) -> Result<Message, DecodeError> {
 		let mut view = U64View::new(message);
 
-		let message_type: MessageKey = (view.read(4) as u8).into();
+		let message_type = MessageKey::try_from(view.read(4) as u8).map_err(DecodeError::UnknownKey)?;
 
-		match message_type {
+		Ok(match message_type {
 			MessageKey::NetInfo => Message::NetInfo(NetInfo::parse(view)),
 			MessageKey::JoinRequest => Message::JoinRequest(JoinRequest::parse(view)),
 			MessageKey::LeaveRequest => Message::LeaveRequest(LeaveNetwork::parse(view)),
@@ -51,11 +56,16 @@ impl Message {
 			MessageKey::AssignAttackTarget => Message::AssignAttackTarget(AssignAttackTarget::parse(view)),
 			MessageKey::IFFPosition => Message::IFFPosition(IFFPosition::parse(view)),
 			MessageKey::InterceptTaskAssign => Message::InterceptTaskAssign(InterceptTaskAssign::parse(view)),
-		}
+			MessageKey::TrackDrop => Message::TrackDrop(TrackDrop::parse(view)),
+			MessageKey::Fragment => Message::Fragment(Fragment::parse(view)),
+			MessageKey::Ack => Message::Ack(Ack::parse(view)),
+			MessageKey::SlotAssign => Message::SlotAssign(SlotAssign::parse(view)),
+			MessageKey::LinkStatus => Message::LinkStatus(LinkStatus::parse(view)),
+		})
 	}
 
 	pub fn serialize(&self) -> U64View {
-		return match self {
+		let view = match self {
 			Message::NetInfo(net_info) => net_info.serialize(),
 			Message::JoinRequest(join_request) => join_request.serialize(),
 			Message::LeaveRequest(leave_request) => leave_request.serialize(),
@@ -67,20 +77,126 @@ impl Message {
 			Message::AssignAttackTarget(assign_attack_target) => assign_attack_target.serialize(),
 			Message::IFFPosition(iff_position) => iff_position.serialize(),
 			Message::InterceptTaskAssign(intercept_task_assign) => intercept_task_assign.serialize(),
+			Message::TrackDrop(track_drop) => track_drop.serialize(),
+			Message::Fragment(fragment) => fragment.serialize(),
+			Message::Ack(ack) => ack.serialize(),
+			Message::SlotAssign(slot_assign) => slot_assign.serialize(),
+			Message::LinkStatus(link_status) => link_status.serialize(),
 		};
+
+		// Anything past the 64th bit is silently lost, a message that big is a bug in its layout
+		debug_assert!(!view.overflowed(), "{:?} doesn't fit in one radio word", self.message_type());
+
+		view
+	}
+
+	pub fn message_type(&self) -> MessageKey {
+		match self {
+			Message::NetInfo(net_info) => net_info.message_type(),
+			Message::JoinRequest(join_request) => join_request.message_type(),
+			Message::LeaveRequest(leave_request) => leave_request.message_type(),
+			Message::TrackId(track_id) => track_id.message_type(),
+			Message::TrackPosition(track_position) => track_position.message_type(),
+			Message::TrackVelocity(track_velocity) => track_velocity.message_type(),
+			Message::ReadyAttackTime(ready_attack_time) => ready_attack_time.message_type(),
+			Message::TrackInfo(track_info) => track_info.message_type(),
+			Message::AssignAttackTarget(assign_attack_target) => assign_attack_target.message_type(),
+			Message::IFFPosition(iff_position) => iff_position.message_type(),
+			Message::InterceptTaskAssign(intercept_task_assign) => intercept_task_assign.message_type(),
+			Message::TrackDrop(track_drop) => track_drop.message_type(),
+			Message::Fragment(fragment) => fragment.message_type(),
+			Message::Ack(ack) => ack.message_type(),
+			Message::SlotAssign(slot_assign) => slot_assign.message_type(),
+			Message::LinkStatus(link_status) => link_status.message_type(),
+		}
 	}
 }
 
-pub fn squash_f32(inp_value: f32, bits: usize, ratio: i32, offset: f32) -> u64 {
-	let value = ((inp_value + offset) * (ratio as f32)).round() as i32;
-	let mask = (1 << bits) - 1;
+// Covers -offset to (2^bits - 1) / ratio - offset in steps of 1 / ratio. Anything outside is clamped to the nearest
+// end rather than wrapping round to the other
+pub fn squash_f32(inp_value: f32, bits: usize, ratio: f32, offset: f32) -> u64 {
+	let max = ((1u64 << bits) - 1) as f32;
+	let value = ((inp_value + offset) * ratio).round().clamp(0.0, max);
 
-	value as u64 & mask as u64
+	value as u64
 }
 
-pub fn unsquash_f32(inp_value: u64, bits: usize, ratio: i32, offset: f32) -> f32 {
-	let mask = (1 << bits) - 1;
-	let value = inp_value & mask as u64;
+pub fn unsquash_f32(inp_value: u64, bits: usize, ratio: f32, offset: f32) -> f32 {
+	let mask = (1u64 << bits) - 1;
+	let value = inp_value & mask;
+
+	value as f32 / ratio - offset
+}
+
+#[cfg(test)]
+mod tests {
+	use protologic_core::radar::RadarTargetType;
+
+	use crate::math::vector3::Vector3;
+
+	use super::*;
+
+	// Through the radio word and back, the way Datalink sends and receives it
+	fn round_trip(message: Message) -> Message {
+		Message::parse(message.serialize().value).unwrap()
+	}
+
+	#[test]
+	fn bits_fields_round_trip() {
+		let Message::TrackInfo(info) = round_trip(Message::TrackInfo(TrackInfo::new(4095, 0xDEADBEEF, RadarTargetType::Missile, true, 200))) else {
+			panic!("Came back as a different message");
+		};
+		assert_eq!(info.track_id, 4095);
+		assert_eq!(info.contact_id, 0xDEADBEEF);
+		assert!(matches!(info.contact_type, RadarTargetType::Missile));
+		assert!(info.is_allied);
+		assert_eq!(info.source, 200);
+	}
+
+	#[test]
+	fn key_attribute_picks_the_message_key() {
+		let leave = LeaveNetwork::new(9, 17);
+		assert!(matches!(leave.message_type(), MessageKey::LeaveRequest));
+
+		let Message::LeaveRequest(leave) = round_trip(Message::LeaveRequest(leave)) else {
+			panic!("Came back as a different message");
+		};
+		assert_eq!((leave.block, leave.id), (9, 17));
+	}
 
-	(value as i32 as f32) / (ratio as f32) - offset
+	#[test]
+	fn squash_fields_round_trip_to_their_resolution() {
+		let position = Vector3::new(-9999.0, 123.4, 10400.0);
+		let Message::IFFPosition(iff) = round_trip(Message::IFFPosition(IFFPosition::new(position, 3, 7))) else {
+			panic!("Came back as a different message");
+		};
+		assert!(
+			(iff.position - position).length() < 1.25,
+			"Position is {}m off",
+			(iff.position - position).length()
+		);
+		assert_eq!((iff.dl_id, iff.block), (3, 7));
+
+		let velocity = Vector3::new(-740.0, 0.0, 745.0);
+		let Message::TrackVelocity(track) = round_trip(Message::TrackVelocity(TrackVelocity::new(12, velocity))) else {
+			panic!("Came back as a different message");
+		};
+		assert!(
+			(track.velocity - velocity).length() < 0.1,
+			"Velocity is {}m/s off",
+			(track.velocity - velocity).length()
+		);
+		assert_eq!(track.track_id, 12);
+	}
+
+	#[test]
+	fn squash_clamps_instead_of_wrapping() {
+		let position = Vector3::new(-50000.0, 50000.0, 0.0);
+		let Message::TrackPosition(track) = round_trip(Message::TrackPosition(TrackPosition::new(1, position))) else {
+			panic!("Came back as a different message");
+		};
+		assert!((track.position.x + 10000.0).abs() < 1.0, "Far negative came back as {}", track.position.x);
+		assert!(track.position.y > 10000.0, "Far positive came back as {}", track.position.y);
+		assert!(track.position.z.abs() < 1.0);
+	}
 }
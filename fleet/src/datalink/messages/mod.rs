@@ -1,12 +1,22 @@
+pub mod ack;
 pub mod assign_attack_target;
+pub mod authenticated;
+pub mod fragment;
 pub mod iff_pos;
 pub mod intercept_task_assign;
 pub mod join_request;
 pub mod leave_network;
+pub mod link_status;
 pub mod message;
 pub mod net_info;
 pub mod ready_attack_time;
+pub mod reliable;
+pub mod single;
+pub mod slot_assign;
+pub mod track_drop;
 pub mod track_id;
 pub mod track_info;
 pub mod track_position;
+pub mod track_retire;
+pub mod track_state;
 pub mod track_velocity;
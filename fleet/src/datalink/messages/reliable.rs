@@ -0,0 +1,45 @@
+use crate::datalink::{
+	bit_buffer::BitBuffer,
+	decode_error::DecodeError,
+	framing::{FramedDatalinkMessage, FramedMessageKey},
+};
+
+use super::message::Message;
+
+// A normal message wrapped with who it is for and a sequence number, the destination answers with an Ack
+#[derive(Clone, Debug)]
+pub struct Reliable {
+	pub source: u8,
+	pub destination: u8,
+	pub sequence: u8,
+	pub message: Message,
+}
+
+impl Reliable {
+	pub fn new(source: u8, destination: u8, sequence: u8, message: Message) -> Reliable {
+		Reliable { source, destination, sequence, message }
+	}
+}
+
+impl FramedDatalinkMessage for Reliable {
+	fn serialize(&self, buffer: &mut BitBuffer) {
+		buffer.write(self.message_type() as u64, 4); // 4
+		buffer.write(self.source as u64, 8); // 12
+		buffer.write(self.destination as u64, 8); // 20
+		buffer.write(self.sequence as u64, 8); // 28
+		buffer.write(self.message.serialize().value, 64); // 92
+	}
+
+	fn parse(buffer: &mut BitBuffer) -> Result<Self, DecodeError> {
+		let source = buffer.read(8) as u8;
+		let destination = buffer.read(8) as u8;
+		let sequence = buffer.read(8) as u8;
+		let message = Message::parse(buffer.read(64))?;
+
+		Ok(Reliable::new(source, destination, sequence, message))
+	}
+
+	fn message_type(&self) -> FramedMessageKey {
+		FramedMessageKey::Reliable
+	}
+}
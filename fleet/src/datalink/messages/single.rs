@@ -0,0 +1,34 @@
+use crate::datalink::{
+	bit_buffer::BitBuffer,
+	decode_error::DecodeError,
+	framing::{FramedDatalinkMessage, FramedMessageKey},
+};
+
+use super::message::Message;
+
+// A normal one word message sent through framing, so it can be wrapped like the bigger ones (signed, for one)
+#[derive(Clone, Debug)]
+pub struct Single {
+	pub message: Message,
+}
+
+impl Single {
+	pub fn new(message: Message) -> Single {
+		Single { message }
+	}
+}
+
+impl FramedDatalinkMessage for Single {
+	fn serialize(&self, buffer: &mut BitBuffer) {
+		buffer.write(self.message_type() as u64, 4); // 4
+		buffer.write(self.message.serialize().value, 64); // 68
+	}
+
+	fn parse(buffer: &mut BitBuffer) -> Result<Self, DecodeError> {
+		Ok(Single::new(Message::parse(buffer.read(64))?))
+	}
+
+	fn message_type(&self) -> FramedMessageKey {
+		FramedMessageKey::Single
+	}
+}
@@ -0,0 +1,21 @@
+use enum_mac::DatalinkMessage;
+
+// Host telling a member where it sends. `block` is its own slot, moved when the frame is packed down, and
+// `extra_blocks` a mask of spare slots lent to it on top
+#[derive(DatalinkMessage, Clone, Debug)]
+pub struct SlotAssign {
+	#[bits(8)]
+	pub id: u8,
+	#[bits(4)]
+	pub block: u8,
+	#[bits(15)]
+	pub extra_blocks: u16,
+	#[bits(4)]
+	pub schedule_epoch: u8,
+}
+
+impl SlotAssign {
+	pub fn new(id: u8, block: u8, extra_blocks: u16, schedule_epoch: u8) -> SlotAssign {
+		SlotAssign { id, block, extra_blocks, schedule_epoch }
+	}
+}
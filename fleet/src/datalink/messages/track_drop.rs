@@ -0,0 +1,16 @@
+use enum_mac::DatalinkMessage;
+
+// Sent when a vehicle's radar gives up on a track, so everyone else stops using the datalink copy
+#[derive(DatalinkMessage, Clone, Debug)]
+pub struct TrackDrop {
+	#[bits(12)]
+	pub track_id: u16,
+	#[bits(8)]
+	pub source: u8, // Others may still be tracking it
+}
+
+impl TrackDrop {
+	pub fn new(track_id: u16, source: u8) -> TrackDrop {
+		TrackDrop { track_id, source }
+	}
+}
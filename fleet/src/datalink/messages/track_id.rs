@@ -1,38 +1,17 @@
-use crate::datalink::u64_view::U64View;
+use enum_mac::DatalinkMessage;
 
-use super::message::{DatalinkMessage, MessageKey};
-
-#[derive(Clone, Debug)]
+#[derive(DatalinkMessage, Clone, Debug)]
 pub struct TrackId {
+	#[bits(16)]
 	pub track_id: u16,
+	#[bits(32)]
 	pub contact_id: u32,
+	#[bits(8)]
+	pub requester: u8, // dl_id of whoever asked, the reply is sent reliably to them
 }
 
 impl TrackId {
-	pub fn new(track_id: u16, contact_id: u32) -> TrackId {
-		TrackId { track_id, contact_id }
-	}
-}
-
-impl DatalinkMessage for TrackId {
-	fn serialize(&self) -> U64View {
-		let mut view = U64View::zero();
-
-		view.write(self.message_type() as u64, 4); // 4
-		view.write(self.track_id as u64, 16); // 20
-		view.write(self.contact_id as u64, 32); // 52
-
-		view
-	}
-
-	fn parse(mut view: U64View) -> Self {
-		let track_id = view.read(16) as u16;
-		let contact_id = view.read(32) as u32;
-
-		TrackId::new(track_id, contact_id)
-	}
-
-	fn message_type(&self) -> MessageKey {
-		MessageKey::TrackId
+	pub fn new(track_id: u16, contact_id: u32, requester: u8) -> TrackId {
+		TrackId { track_id, contact_id, requester }
 	}
 }
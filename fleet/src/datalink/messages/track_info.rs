@@ -1,70 +1,53 @@
+use enum_mac::DatalinkMessage;
 use protologic_core::radar::RadarTargetType;
 
-use crate::datalink::u64_view::U64View;
-
-use super::message::{DatalinkMessage, MessageKey};
-
-#[derive(Clone, Debug)]
+#[derive(DatalinkMessage, Clone, Debug)]
 pub struct TrackInfo {
+	#[bits(12)]
 	pub track_id: u16,
+	#[bits(32)]
 	pub contact_id: u32,
+	#[bits(3, encode = radar_to_i32, decode = i32_to_radar)]
 	pub contact_type: RadarTargetType,
+	#[bits(1)]
 	pub is_allied: bool,
+	#[bits(8)]
+	pub source: u8, // dl_id of the reporting vehicle, position and velocity that follow are from it
 }
 
 impl TrackInfo {
-	pub fn new(track_id: u16, contact_id: u32, contact_type: RadarTargetType, is_allied: bool) -> TrackInfo {
-		TrackInfo { track_id, contact_id, contact_type, is_allied }
+	pub fn new(track_id: u16, contact_id: u32, contact_type: RadarTargetType, is_allied: bool, source: u8) -> TrackInfo {
+		TrackInfo {
+			track_id,
+			contact_id,
+			contact_type,
+			is_allied,
+			source,
+		}
 	}
 }
 
-fn radar_to_i32(r: RadarTargetType) -> i32 {
+// Packed into 3 bits, -1 masks to 7 and comes back as Invalid
+pub fn radar_to_i32(r: RadarTargetType) -> i32 {
 	match r {
 		RadarTargetType::SpaceBattleShip => 0,
 		RadarTargetType::SpaceHulk => 1,
 		RadarTargetType::Missile => 2,
-		RadarTargetType::Asteroid => 5,
-		RadarTargetType::FlakShell => 7,
-		RadarTargetType::APShell => 8,
+		RadarTargetType::Asteroid => 3,
+		RadarTargetType::FlakShell => 4,
+		RadarTargetType::APShell => 5,
 		_ => -1,
 	}
 }
 
-fn i32_to_radar(r: i32) -> RadarTargetType {
+pub fn i32_to_radar(r: i32) -> RadarTargetType {
 	match r {
 		0 => RadarTargetType::SpaceBattleShip,
 		1 => RadarTargetType::SpaceHulk,
 		2 => RadarTargetType::Missile,
-		5 => RadarTargetType::Asteroid,
-		7 => RadarTargetType::FlakShell,
-		8 => RadarTargetType::APShell,
+		3 => RadarTargetType::Asteroid,
+		4 => RadarTargetType::FlakShell,
+		5 => RadarTargetType::APShell,
 		_ => RadarTargetType::Invalid,
 	}
 }
-
-impl DatalinkMessage for TrackInfo {
-	fn serialize(&self) -> U64View {
-		let mut view = U64View::zero();
-
-		view.write(self.message_type() as u64, 4); // 4
-		view.write(self.track_id as u64, 12); // 16
-		view.write(self.contact_id as u64, 32); // 48
-		view.write(radar_to_i32(self.contact_type) as u64, 4); // 52
-		view.write(if self.is_allied { 1 } else { 0 }, 1);
-
-		view
-	}
-
-	fn parse(mut view: U64View) -> Self {
-		let track_id = view.read(12) as u16;
-		let contact_id = view.read(32) as u32;
-		let contact_type = i32_to_radar(view.read(4) as i32);
-		let is_allied = view.read(1) == 1;
-
-		TrackInfo::new(track_id, contact_id, contact_type, is_allied)
-	}
-
-	fn message_type(&self) -> MessageKey {
-		MessageKey::TrackInfo
-	}
-}
@@ -0,0 +1,61 @@
+use crate::datalink::{
+	bit_buffer::BitBuffer,
+	decode_error::DecodeError,
+	framing::{FramedDatalinkMessage, FramedMessageKey},
+};
+
+pub const MAX_RANGES: usize = 7;
+
+// Track ids the host took back, everyone forgets them so they can be handed out again. Ids are freed in
+// bulk as tracks go stale, so they go as (first, count) runs
+#[derive(Clone, Debug)]
+pub struct TrackRetire {
+	pub ranges: Vec<(u16, u16)>,
+}
+
+impl TrackRetire {
+	pub fn new(ranges: Vec<(u16, u16)>) -> TrackRetire {
+		assert!(ranges.len() <= MAX_RANGES, "TrackRetire has room for {} ranges", MAX_RANGES);
+		TrackRetire { ranges }
+	}
+
+	// Sorted ids into as few messages as they fit in
+	pub fn from_ids(ids: &[u16]) -> Vec<TrackRetire> {
+		let mut ranges: Vec<(u16, u16)> = Vec::new();
+		for id in ids {
+			match ranges.last_mut() {
+				Some((first, count)) if *first + *count == *id => *count += 1,
+				_ => ranges.push((*id, 1)),
+			}
+		}
+
+		ranges.chunks(MAX_RANGES).map(|chunk| TrackRetire::new(chunk.to_vec())).collect()
+	}
+
+	pub fn ids(&self) -> impl Iterator<Item = u16> + '_ {
+		self.ranges.iter().flat_map(|(first, count)| *first..*first + *count)
+	}
+}
+
+impl FramedDatalinkMessage for TrackRetire {
+	fn serialize(&self, buffer: &mut BitBuffer) {
+		buffer.write(self.message_type() as u64, 4); // 4
+		buffer.write(self.ranges.len() as u64, 3); // 7
+		for (first, count) in self.ranges.iter() {
+			buffer.write(*first as u64, 12);
+			buffer.write(*count as u64, 12);
+		}
+		// 175 at most
+	}
+
+	fn parse(buffer: &mut BitBuffer) -> Result<Self, DecodeError> {
+		let count = buffer.read(3) as usize;
+		let ranges = (0..count).map(|_| (buffer.read(12) as u16, buffer.read(12) as u16)).collect();
+
+		Ok(TrackRetire { ranges })
+	}
+
+	fn message_type(&self) -> FramedMessageKey {
+		FramedMessageKey::TrackRetire
+	}
+}
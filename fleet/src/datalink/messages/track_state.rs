@@ -0,0 +1,97 @@
+use protologic_core::radar::RadarTargetType;
+
+use crate::{
+	datalink::{
+		bit_buffer::BitBuffer,
+		decode_error::DecodeError,
+		framing::{FramedDatalinkMessage, FramedMessageKey},
+	},
+	math::vector3::Vector3,
+};
+
+use super::{
+	message::{squash_f32, unsquash_f32},
+	track_info::{i32_to_radar, radar_to_i32},
+};
+
+// Everything about a track in one go. Framed, so it spans several radio words but arrives all at once,
+// with finer position resolution and more range than TrackPosition/TrackVelocity
+#[derive(Clone, Debug)]
+pub struct TrackState {
+	pub track_id: u16,
+	pub contact_id: u32,
+	pub contact_type: RadarTargetType,
+	pub is_allied: bool,
+	pub source: u8,
+
+	pub position: Vector3,
+	pub velocity: Vector3,
+}
+
+// 0.25m over +-131km
+const POSITION_BITS: usize = 20;
+const POSITION_RATIO: f32 = 4.0;
+const POSITION_OFFSET: f32 = 131000.0;
+
+// 0.125m/s over +-2km/s
+const VELOCITY_BITS: usize = 15;
+const VELOCITY_RATIO: f32 = 8.0;
+const VELOCITY_OFFSET: f32 = 2000.0;
+
+impl TrackState {
+	pub fn new(track_id: u16, contact_id: u32, contact_type: RadarTargetType, is_allied: bool, source: u8, position: Vector3, velocity: Vector3) -> TrackState {
+		TrackState {
+			track_id,
+			contact_id,
+			contact_type,
+			is_allied,
+			source,
+			position,
+			velocity,
+		}
+	}
+}
+
+fn write_vector(buffer: &mut BitBuffer, v: Vector3, bits: usize, ratio: f32, offset: f32) {
+	buffer.write(squash_f32(v.x, bits, ratio, offset), bits);
+	buffer.write(squash_f32(v.y, bits, ratio, offset), bits);
+	buffer.write(squash_f32(v.z, bits, ratio, offset), bits);
+}
+
+fn read_vector(buffer: &mut BitBuffer, bits: usize, ratio: f32, offset: f32) -> Vector3 {
+	let x = unsquash_f32(buffer.read(bits), bits, ratio, offset);
+	let y = unsquash_f32(buffer.read(bits), bits, ratio, offset);
+	let z = unsquash_f32(buffer.read(bits), bits, ratio, offset);
+
+	Vector3::new(x, y, z)
+}
+
+impl FramedDatalinkMessage for TrackState {
+	fn serialize(&self, buffer: &mut BitBuffer) {
+		buffer.write(self.message_type() as u64, 4); // 4
+		buffer.write(self.track_id as u64, 12); // 16
+		buffer.write(self.contact_id as u64, 32); // 48
+		buffer.write(radar_to_i32(self.contact_type) as u64, 4); // 52
+		buffer.write(if self.is_allied { 1 } else { 0 }, 1); // 53
+		buffer.write(self.source as u64, 8); // 61
+		write_vector(buffer, self.position, POSITION_BITS, POSITION_RATIO, POSITION_OFFSET); // 121
+		write_vector(buffer, self.velocity, VELOCITY_BITS, VELOCITY_RATIO, VELOCITY_OFFSET);
+		// 166
+	}
+
+	fn parse(buffer: &mut BitBuffer) -> Result<Self, DecodeError> {
+		let track_id = buffer.read(12) as u16;
+		let contact_id = buffer.read(32) as u32;
+		let contact_type = i32_to_radar(buffer.read(4) as i32);
+		let is_allied = buffer.read(1) == 1;
+		let source = buffer.read(8) as u8;
+		let position = read_vector(buffer, POSITION_BITS, POSITION_RATIO, POSITION_OFFSET);
+		let velocity = read_vector(buffer, VELOCITY_BITS, VELOCITY_RATIO, VELOCITY_OFFSET);
+
+		Ok(TrackState::new(track_id, contact_id, contact_type, is_allied, source, position, velocity))
+	}
+
+	fn message_type(&self) -> FramedMessageKey {
+		FramedMessageKey::TrackState
+	}
+}
@@ -1,3 +1,13 @@
+pub mod authenticator;
+pub mod bit_buffer;
+#[allow(clippy::module_inception)]
 pub mod datalink;
+pub mod decode_error;
+pub mod framing;
 pub mod messages;
+pub mod reliable_channel;
+pub mod send_queue;
+pub mod slot_allocator;
+pub mod stats;
+pub mod track_fusion;
 pub mod u64_view;
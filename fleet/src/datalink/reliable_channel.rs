@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+use super::messages::{ack::Ack, message::Message, reliable::Reliable};
+
+const RETRANSMIT_TIMEOUT: f32 = 1.0; // No Ack by then and the message goes out again
+const MAX_ATTEMPTS: u8 = 4;
+const DUPLICATE_WINDOW: f32 = 10.0; // Longer than a sender keeps retrying for
+const STATUS_LIFETIME: f32 = 10.0; // How long a delivered or failed message's outcome is kept for whoever sent it
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeliveryStatus {
+	Pending,
+	Delivered,
+	Failed,
+}
+
+// Handle for following up on one reliable send. Sequence numbers wrap after 256 sends, these don't
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Delivery(u32);
+
+struct Outstanding {
+	delivery: Delivery,
+	sequence: u8,
+	destination: u8,
+	message: Message,
+	last_sent: f32,
+	attempts: u8,
+}
+
+// Sequence numbers, retransmission and duplicate suppression for messages that have to arrive. Only the
+// bookkeeping lives here, the Datalink does the sending
+#[derive(Default)]
+pub struct ReliableChannel {
+	next_sequence: u8,
+	next_delivery: u32,
+	outstanding: Vec<Outstanding>,
+	outcomes: HashMap<Delivery, (DeliveryStatus, f32)>, // Delivered or failed, and when
+
+	// (source, sequence) of everything delivered recently, and when
+	received: HashMap<(u8, u8), f32>,
+}
+
+impl ReliableChannel {
+	pub fn new() -> ReliableChannel {
+		ReliableChannel {
+			next_sequence: 0,
+			next_delivery: 0,
+			outstanding: Vec::new(),
+			outcomes: HashMap::new(),
+			received: HashMap::new(),
+		}
+	}
+
+	// Returns the message to transmit, and the handle the sender follows up on delivery with
+	pub fn send(&mut self, source: u8, destination: u8, message: Message, now: f32) -> (Reliable, Delivery) {
+		let sequence = self.next_sequence;
+		self.next_sequence = self.next_sequence.wrapping_add(1);
+		let delivery = Delivery(self.next_delivery);
+		self.next_delivery = self.next_delivery.wrapping_add(1);
+
+		// Still unacknowledged 256 sends later, its Ack could no longer be told apart from the new one's
+		if let Some(index) = self.outstanding.iter().position(|o| o.sequence == sequence) {
+			let stale = self.outstanding.remove(index);
+			self.outcomes.insert(stale.delivery, (DeliveryStatus::Failed, now));
+		}
+		self.outstanding.push(Outstanding {
+			delivery,
+			sequence,
+			destination,
+			message: message.clone(),
+			last_sent: now,
+			attempts: 1,
+		});
+
+		(Reliable::new(source, destination, sequence, message), delivery)
+	}
+
+	pub fn handle_ack(&mut self, ack: &Ack, now: f32) {
+		let index = match self.outstanding.iter().position(|o| o.sequence == ack.sequence && o.destination == ack.source) {
+			Some(index) => index,
+			None => return,
+		};
+
+		let delivered = self.outstanding.remove(index);
+		self.outcomes.insert(delivered.delivery, (DeliveryStatus::Delivered, now));
+	}
+
+	// Whether the message is new and should be acted on. Duplicates are retransmissions whose Ack got lost,
+	// they still get acknowledged again
+	pub fn receive(&mut self, reliable: &Reliable, now: f32) -> bool {
+		self.received.retain(|_, time| now - *time < DUPLICATE_WINDOW);
+		self.received.insert((reliable.source, reliable.sequence), now).is_none()
+	}
+
+	// Messages whose Ack is overdue and need sending again. Ones out of attempts are given up on
+	pub fn due_retransmits(&mut self, source: u8, now: f32) -> Vec<Reliable> {
+		self.outcomes.retain(|_, (_, time)| now - *time < STATUS_LIFETIME);
+
+		let mut due = Vec::new();
+		for outstanding in self.outstanding.iter_mut() {
+			if now - outstanding.last_sent < RETRANSMIT_TIMEOUT {
+				continue;
+			}
+
+			if outstanding.attempts >= MAX_ATTEMPTS {
+				println!(
+					"Reliable message {} to {} failed after {} attempts",
+					outstanding.sequence, outstanding.destination, outstanding.attempts
+				);
+				self.outcomes.insert(outstanding.delivery, (DeliveryStatus::Failed, now));
+				continue;
+			}
+
+			outstanding.attempts += 1;
+			outstanding.last_sent = now;
+			due.push(Reliable::new(
+				source,
+				outstanding.destination,
+				outstanding.sequence,
+				outstanding.message.clone(),
+			));
+		}
+
+		let outcomes = &self.outcomes;
+		self.outstanding.retain(|o| !outcomes.contains_key(&o.delivery));
+
+		due
+	}
+
+	// Outcomes are only kept for STATUS_LIFETIME, one asked about later than that counts as failed
+	pub fn status(&self, delivery: Delivery) -> DeliveryStatus {
+		if self.outstanding.iter().any(|o| o.delivery == delivery) {
+			return DeliveryStatus::Pending;
+		}
+
+		self.outcomes.get(&delivery).map_or(DeliveryStatus::Failed, |(status, _)| *status)
+	}
+
+	// Messages to the destination still waiting on an Ack
+	pub fn pending_to(&self, destination: u8) -> impl Iterator<Item = &Message> {
+		self.outstanding.iter().filter(move |o| o.destination == destination).map(|o| &o.message)
+	}
+
+	pub fn outstanding(&self) -> usize {
+		self.outstanding.len()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::datalink::messages::track_drop::TrackDrop;
+
+	use super::*;
+
+	const SOURCE: u8 = 0;
+	const DESTINATION: u8 = 3;
+
+	fn send(channel: &mut ReliableChannel, now: f32) -> (u8, Delivery) {
+		let (reliable, delivery) = channel.send(SOURCE, DESTINATION, Message::TrackDrop(TrackDrop::new(1, SOURCE)), now);
+		(reliable.sequence, delivery)
+	}
+
+	fn ack(channel: &mut ReliableChannel, sequence: u8, now: f32) {
+		channel.handle_ack(&Ack::new(DESTINATION, SOURCE, sequence), now);
+	}
+
+	#[test]
+	fn outcomes_survive_the_sequence_wrapping() {
+		let mut channel = ReliableChannel::new();
+		let (sequence, first) = send(&mut channel, 0.0);
+		ack(&mut channel, sequence, 0.0);
+
+		let later: Vec<(u8, Delivery)> = (0..256).map(|_| send(&mut channel, 0.0)).collect();
+		assert_eq!(later[255].0, sequence);
+		assert_eq!(channel.status(first), DeliveryStatus::Delivered);
+		assert_eq!(channel.status(later[255].1), DeliveryStatus::Pending);
+
+		// An Ack for the reused sequence is for the new message, not the old one
+		ack(&mut channel, sequence, 0.0);
+		assert_eq!(channel.status(later[255].1), DeliveryStatus::Delivered);
+		assert_eq!(channel.status(later[0].1), DeliveryStatus::Pending);
+	}
+
+	#[test]
+	fn unacknowledged_message_fails_when_its_sequence_is_reused() {
+		let mut channel = ReliableChannel::new();
+		let (_, first) = send(&mut channel, 0.0);
+		for _ in 0..256 {
+			send(&mut channel, 0.0);
+		}
+
+		assert_eq!(channel.status(first), DeliveryStatus::Failed);
+		assert_eq!(channel.outstanding(), 256);
+	}
+
+	#[test]
+	fn outcomes_are_pruned_after_a_while() {
+		let mut channel = ReliableChannel::new();
+		let (sequence, delivered) = send(&mut channel, 0.0);
+		ack(&mut channel, sequence, 0.0);
+		let (_, failed) = send(&mut channel, 0.0);
+		for attempt in 1..=MAX_ATTEMPTS as u32 {
+			channel.due_retransmits(SOURCE, attempt as f32 * RETRANSMIT_TIMEOUT);
+		}
+		assert_eq!(channel.status(failed), DeliveryStatus::Failed);
+		assert_eq!(channel.outcomes.len(), 2);
+
+		channel.due_retransmits(SOURCE, STATUS_LIFETIME * 2.0);
+		assert!(channel.outcomes.is_empty());
+		assert_eq!(channel.status(delivered), DeliveryStatus::Failed);
+	}
+}
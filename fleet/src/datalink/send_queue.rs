@@ -0,0 +1,212 @@
+use super::{framing::FramedMessage, messages::message::Message};
+
+// In priority order, a lower class always goes first while it has budget left
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum MessageClass {
+	Order,        // Network management, TrackId requests and replies, attack and intercept orders
+	Coordination, // Salvo timing
+	Track,        // Track reports and drops
+	Routine,      // IFF
+}
+
+pub const CLASSES: [MessageClass; 4] = [MessageClass::Order, MessageClass::Coordination, MessageClass::Track, MessageClass::Routine];
+
+impl MessageClass {
+	pub fn of(message: &Message) -> MessageClass {
+		match message {
+			Message::TrackId(_) | Message::AssignAttackTarget(_) | Message::InterceptTaskAssign(_) | Message::Ack(_) => MessageClass::Order,
+			Message::NetInfo(_) | Message::JoinRequest(_) | Message::LeaveRequest(_) | Message::SlotAssign(_) | Message::LinkStatus(_) => MessageClass::Order,
+			Message::ReadyAttackTime(_) => MessageClass::Coordination,
+			Message::TrackInfo(_) | Message::TrackPosition(_) | Message::TrackVelocity(_) | Message::TrackDrop(_) | Message::Fragment(_) => MessageClass::Track,
+			Message::IFFPosition(_) => MessageClass::Routine,
+		}
+	}
+
+	pub fn of_framed(message: &FramedMessage) -> MessageClass {
+		match message {
+			FramedMessage::TrackState(_) | FramedMessage::TrackRetire(_) => MessageClass::Track,
+			FramedMessage::Reliable(reliable) => MessageClass::of(&reliable.message),
+			FramedMessage::Single(single) => MessageClass::of(&single.message),
+			FramedMessage::Authenticated(authenticated) => authenticated.open().map_or(MessageClass::Order, |m| MessageClass::of_framed(&m)),
+		}
+	}
+
+	// Share of our slots the class may use while anything else is waiting
+	fn budget(self) -> f32 {
+		match self {
+			MessageClass::Order => 0.7, // Most of the link, but a flood of orders can't shut out IFF and tracks
+			MessageClass::Coordination => 0.2,
+			MessageClass::Track => 0.6,
+			MessageClass::Routine => 0.3,
+		}
+	}
+
+	fn index(self) -> usize {
+		self as usize
+	}
+}
+
+const BUDGET_WINDOW: f32 = 10.0; // Slots worth of unused budget a class can save up
+
+// Messages that replace an older queued one with the same key instead of waiting behind it
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CoalesceKey {
+	TrackInfo(u16),
+	TrackPosition(u16),
+	TrackVelocity(u16),
+	TrackState(u16),
+	Iff,
+	ReadyAttackTime,
+	SlotAssign(u8),
+	LinkStatus,
+}
+
+impl CoalesceKey {
+	pub fn of(message: &Message) -> Option<CoalesceKey> {
+		match message {
+			Message::TrackInfo(info) => Some(CoalesceKey::TrackInfo(info.track_id)),
+			Message::TrackPosition(position) => Some(CoalesceKey::TrackPosition(position.track_id)),
+			Message::TrackVelocity(velocity) => Some(CoalesceKey::TrackVelocity(velocity.track_id)),
+			Message::IFFPosition(_) => Some(CoalesceKey::Iff),
+			Message::ReadyAttackTime(_) => Some(CoalesceKey::ReadyAttackTime),
+			Message::SlotAssign(assign) => Some(CoalesceKey::SlotAssign(assign.id)),
+			Message::LinkStatus(_) => Some(CoalesceKey::LinkStatus),
+			_ => None,
+		}
+	}
+
+	pub fn of_framed(message: &FramedMessage) -> Option<CoalesceKey> {
+		match message {
+			FramedMessage::TrackState(state) => Some(CoalesceKey::TrackState(state.track_id)),
+			FramedMessage::Reliable(_) | FramedMessage::Authenticated(_) | FramedMessage::TrackRetire(_) => None,
+			FramedMessage::Single(single) => CoalesceKey::of(&single.message),
+		}
+	}
+
+	fn track_id(self) -> Option<u16> {
+		match self {
+			CoalesceKey::TrackInfo(id) | CoalesceKey::TrackPosition(id) | CoalesceKey::TrackVelocity(id) | CoalesceKey::TrackState(id) => Some(id),
+			_ => None,
+		}
+	}
+}
+
+struct QueuedMessage {
+	message: Message,
+	class: MessageClass,
+	key: Option<CoalesceKey>,
+	// Cleared once part of the same framed message has gone out, the rest has to follow or it is wasted
+	replaceable: bool,
+}
+
+pub struct SendQueue {
+	queue: Vec<QueuedMessage>,
+	credit: [f32; CLASSES.len()],
+}
+
+impl Default for SendQueue {
+	fn default() -> Self {
+		SendQueue::new()
+	}
+}
+
+impl SendQueue {
+	pub fn new() -> SendQueue {
+		SendQueue { queue: Vec::new(), credit: [0.0; CLASSES.len()] }
+	}
+
+	pub fn push(&mut self, message: Message) {
+		let class = MessageClass::of(&message);
+		let key = CoalesceKey::of(&message);
+		self.push_group(vec![message], class, key);
+	}
+
+	// Several messages that are queued, replaced and sent as a unit, the fragments of a framed message
+	pub fn push_group(&mut self, messages: Vec<Message>, class: MessageClass, key: Option<CoalesceKey>) {
+		let entries = messages.into_iter().map(|message| QueuedMessage { message, class, key, replaceable: true });
+
+		// Superseded updates are swapped out in place, so an update that keeps getting refreshed doesn't keep
+		// going to the back of the line
+		let existing = key.and_then(|key| self.queue.iter().position(|q| q.replaceable && q.key == Some(key)));
+		match existing {
+			Some(index) => {
+				self.queue.retain(|q| !(q.replaceable && q.key == key));
+				let tail = self.queue.split_off(index);
+				self.queue.extend(entries);
+				self.queue.extend(tail);
+			}
+			None => self.queue.extend(entries),
+		}
+	}
+
+	// Nothing we still have queued about the track is worth sending anymore
+	pub fn cancel_track(&mut self, track_id: u16) {
+		self.queue.retain(|q| !q.replaceable || q.key.and_then(|k| k.track_id()) != Some(track_id));
+	}
+
+	// Called once per slot we own. Picks the highest priority class that is within its budget, and if every
+	// class with something waiting is over budget the slot still gets used
+	pub fn pop(&mut self) -> Option<Message> {
+		for class in CLASSES {
+			let credit = &mut self.credit[class.index()];
+			*credit = (*credit + class.budget()).min(class.budget() * BUDGET_WINDOW);
+		}
+
+		let within_budget = CLASSES
+			.into_iter()
+			.filter(|c| self.credit[c.index()] >= 1.0)
+			.find_map(|c| self.queue.iter().position(|q| q.class == c));
+		let index = within_budget.or_else(|| CLASSES.into_iter().find_map(|c| self.queue.iter().position(|q| q.class == c)))?;
+
+		// A slot nobody within budget wanted costs nothing
+		let sent = self.queue.remove(index);
+		if within_budget.is_some() {
+			self.credit[sent.class.index()] -= 1.0;
+		}
+
+		if let Message::Fragment(fragment) = &sent.message {
+			for q in self.queue.iter_mut().filter(|q| q.key == sent.key) {
+				if matches!(&q.message, Message::Fragment(f) if f.sequence == fragment.sequence) {
+					q.replaceable = false;
+				}
+			}
+		}
+
+		Some(sent.message)
+	}
+
+	pub fn len(&self) -> usize {
+		self.queue.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.queue.is_empty()
+	}
+
+	pub fn len_of(&self, class: MessageClass) -> usize {
+		self.queue.iter().filter(|q| q.class == class).count()
+	}
+
+	pub fn messages(&self) -> impl Iterator<Item = &Message> {
+		self.queue.iter().map(|q| &q.message)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::datalink::messages::{ack::Ack, track_drop::TrackDrop};
+
+	use super::*;
+
+	#[test]
+	fn orders_leave_room_for_tracks() {
+		let mut queue = SendQueue::new();
+		for sequence in 0..50 {
+			queue.push(Message::Ack(Ack::new(1, 0, sequence)));
+		}
+		queue.push(Message::TrackDrop(TrackDrop::new(7, 0)));
+
+		let sent = (0..10).find(|_| matches!(queue.pop(), Some(Message::TrackDrop(_))));
+		assert!(sent.is_some(), "Track report was stuck behind orders");
+	}
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use crate::hardware::traits::Hardware;
+
+pub const BLOCK_CAPACITY: usize = 4; // Members sending in the same tick
+
+// What a member is for, the ship carries most of the fleet's tracks and gets most of the bandwidth
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LinkRole {
+	Ship,
+	Strike,
+	Interceptor,
+	Other,
+}
+
+impl LinkRole {
+	pub fn of(hw: &dyn Hardware) -> LinkRole {
+		match (hw.vehicle_env("Type").as_deref(), hw.vehicle_env("WarheadType").as_deref()) {
+			(Some("Ship"), _) => LinkRole::Ship,
+			(_, Some("Nuclear")) => LinkRole::Strike,
+			(_, Some("Flak")) => LinkRole::Interceptor,
+			_ => LinkRole::Other,
+		}
+	}
+
+	fn weight(self) -> f32 {
+		match self {
+			LinkRole::Ship => 4.0,
+			LinkRole::Strike => 2.0,
+			LinkRole::Interceptor | LinkRole::Other => 1.0,
+		}
+	}
+}
+
+pub fn role_to_bits(role: LinkRole) -> u8 {
+	role as u8
+}
+
+pub fn bits_to_role(bits: u8) -> LinkRole {
+	match bits {
+		0 => LinkRole::Ship,
+		1 => LinkRole::Strike,
+		2 => LinkRole::Interceptor,
+		_ => LinkRole::Other,
+	}
+}
+
+// How much a member wants more slots, nothing queued is no claim at all. Queue depth only counts by its order
+// of magnitude, so the schedule isn't redone over every message
+pub fn demand(role: LinkRole, queue_depth: u8) -> f32 {
+	role.weight() * (u8::BITS - queue_depth.leading_zeros()) as f32
+}
+
+// Hands out the room left in each block after everyone's own slot, as extra slots proportional to demand
+// (Sainte-Laguë, so one big claim doesn't take everything). `blocks` is who has their own slot in each block,
+// the result is a mask of extra blocks per member
+pub fn allocate(blocks: &[Vec<u8>], demands: &[(u8, f32)]) -> HashMap<u8, u16> {
+	let mut spare: Vec<usize> = blocks.iter().map(|b| BLOCK_CAPACITY.saturating_sub(b.len())).collect();
+	let mut extras: HashMap<u8, u16> = HashMap::new();
+	let mut given: HashMap<u8, u32> = HashMap::new();
+	let mut claims: Vec<(u8, f32)> = demands.iter().filter(|(_, demand)| *demand > 0.0).copied().collect();
+
+	while !claims.is_empty() {
+		let priority = |(id, demand): &(u8, f32)| demand / (2 * given.get(id).copied().unwrap_or(0) + 1) as f32;
+		let (index, (id, _)) = claims
+			.iter()
+			.enumerate()
+			.max_by(|(_, a), (_, b)| priority(a).total_cmp(&priority(b)).then(b.0.cmp(&a.0)))
+			.unwrap();
+		let id = *id;
+
+		// The emptiest block it isn't already sending in
+		let mask = extras.get(&id).copied().unwrap_or(0);
+		let block = (0..blocks.len())
+			.filter(|b| spare[*b] > 0 && !blocks[*b].contains(&id) && mask & (1 << b) == 0)
+			.max_by_key(|b| (spare[*b], usize::MAX - b));
+		match block {
+			Some(block) => {
+				spare[block] -= 1;
+				extras.insert(id, mask | 1 << block);
+				*given.entry(id).or_insert(0) += 1;
+			}
+			None => {
+				claims.remove(index);
+			}
+		}
+	}
+
+	extras
+}
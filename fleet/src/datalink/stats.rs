@@ -0,0 +1,128 @@
+use crate::math::vector3::Vector3;
+
+use super::{datalink::DatalinkStatus, messages::message::MessageKey, send_queue::MessageClass};
+
+const SATURATED_UTILIZATION: f32 = 0.9; // Share of our slots used before the link counts as full
+const SATURATED_BACKLOG: usize = 10; // And still this many words waiting
+
+#[derive(Clone, Copy, Default)]
+struct SecondCounts {
+	pushed: u32,      // Messages handed to the datalink to send
+	owned_slots: u32, // Our turns to transmit
+	used_slots: u32,  // Turns we had something for
+	ticks: u32,
+	busy_ticks: u32, // Ticks anything was heard on our channel
+}
+
+// Running counts behind DatalinkStats. Totals since startup, and rates over the last full second
+#[derive(Default)]
+pub struct LinkCounters {
+	pub sent: u32,
+	pub received: u32,
+	pub dropped: u32,
+
+	current: SecondCounts,
+	last: SecondCounts,
+	window_start: f32,
+}
+
+impl LinkCounters {
+	pub fn new() -> LinkCounters {
+		LinkCounters::default()
+	}
+
+	pub fn pushed(&mut self) {
+		self.current.pushed += 1;
+	}
+
+	pub fn tick(&mut self, received: usize) {
+		self.received += received as u32;
+		self.current.ticks += 1;
+		if received > 0 {
+			self.current.busy_ticks += 1;
+		}
+	}
+
+	pub fn slot(&mut self, used: bool) {
+		self.current.owned_slots += 1;
+		if used {
+			self.current.used_slots += 1;
+		}
+	}
+
+	pub fn roll(&mut self, now: f32) {
+		if now - self.window_start > 1.0 {
+			self.last = self.current;
+			self.current = SecondCounts::default();
+			self.window_start = now;
+		}
+	}
+
+	pub fn pushed_last_second(&self) -> u32 {
+		self.last.pushed
+	}
+
+	pub fn slot_utilization(&self) -> f32 {
+		ratio(self.last.used_slots, self.last.owned_slots)
+	}
+
+	pub fn channel_utilization(&self) -> f32 {
+		ratio(self.last.busy_ticks, self.last.ticks)
+	}
+
+	// Every slot we get is taken and messages are still piling up
+	pub fn is_saturated(&self, queued: usize) -> bool {
+		self.slot_utilization() >= SATURATED_UTILIZATION && queued >= SATURATED_BACKLOG
+	}
+}
+
+fn ratio(part: u32, whole: u32) -> f32 {
+	if whole == 0 {
+		0.0
+	} else {
+		part as f32 / whole as f32
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DatalinkMember {
+	pub id: u8,
+	pub block: u8,
+	pub position: Vector3,
+	pub silent_for: f32,  // Seconds since its last IFF
+	pub extra_slots: u32, // Spare slots the host lent it, only the host knows
+}
+
+// How the link is doing, for control systems to back off with when it's full. See Datalink::stats
+#[derive(Clone, Debug)]
+pub struct DatalinkStats {
+	pub status: DatalinkStatus,
+	pub id: u8,
+	pub is_host: bool,
+	pub members: Vec<DatalinkMember>, // Everyone heard from recently, not including us
+	pub total_blocks: u8,             // Frame length
+	pub slots_per_frame: u32,         // Ours, our own block and any extra ones
+	pub schedule_epoch: u8,
+
+	pub queue_depth: Vec<(MessageKey, usize)>, // Words waiting, framed messages count as their fragments
+	pub queue_by_class: Vec<(MessageClass, usize)>,
+	pub reliable_outstanding: usize,
+
+	pub packets_sent: u32,
+	pub packets_received: u32,
+	pub packets_dropped: u32, // Received but thrown out, both bad decodes and refused orders
+	pub decode_errors: u32,
+	pub auth_rejections: u32,
+	pub oversized_messages: u32, // Ours, too big to fragment and never sent
+
+	pub messages_pushed_per_second: u32,
+	pub slot_utilization: f32,    // Share of our slots used over the last second
+	pub channel_utilization: f32, // Share of ticks over the last second anything was heard
+	pub saturated: bool,
+
+	pub time_since_net_info: f32,
+
+	pub track_ids_in_use: usize,
+	pub track_id_exhaustions: u32, // Host only, ids asked for while every one was taken
+	pub track_id_conflicts: u32,   // Reports that put a different contact under an id we already had
+}
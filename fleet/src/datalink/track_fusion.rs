@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::math::vector3::Vector3;
+
+// Error model for a single report. Radar error grows with range, and an old report drifts by however much the
+// target could have maneuvered since
+const RANGE_ERROR: f32 = 0.005; // m of error per m of range
+const AGE_DRIFT: f32 = 30.0; // m of error per second of age
+const MIN_ERROR: f32 = 1.0;
+pub const REPORT_MAX_AGE: f32 = 15.0;
+
+// What one vehicle last said about a track
+#[derive(Clone, Copy, Debug)]
+pub struct TrackReport {
+	pub source: u8,
+	pub position: Vector3,
+	pub velocity: Vector3,
+	pub timestamp: f32,
+	pub source_distance: f32,
+}
+
+impl TrackReport {
+	fn variance(&self, now: f32) -> f32 {
+		let error = MIN_ERROR + self.source_distance * RANGE_ERROR + (now - self.timestamp).max(0.0) * AGE_DRIFT;
+		error * error
+	}
+
+	fn position_at(&self, time: f32) -> Vector3 {
+		self.position + self.velocity * (time - self.timestamp)
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FusedEstimate {
+	pub position: Vector3,
+	pub velocity: Vector3,
+	pub timestamp: f32,
+	pub sources: usize,
+}
+
+// Keeps the latest report from every source for each track and blends them into one picture, each report
+// weighted by the inverse of its expected error
+#[derive(Default)]
+pub struct TrackFusion {
+	reports: HashMap<u16, Vec<TrackReport>>,
+}
+
+impl TrackFusion {
+	pub fn new() -> TrackFusion {
+		TrackFusion { reports: HashMap::new() }
+	}
+
+	fn report_mut(&mut self, track_id: u16, source: u8) -> &mut TrackReport {
+		let reports = self.reports.entry(track_id).or_default();
+		let index = match reports.iter().position(|r| r.source == source) {
+			Some(index) => index,
+			None => {
+				reports.push(TrackReport {
+					source,
+					position: Vector3::zero(),
+					velocity: Vector3::zero(),
+					timestamp: f32::MIN,
+					source_distance: f32::MAX,
+				});
+				reports.len() - 1
+			}
+		};
+
+		&mut reports[index]
+	}
+
+	pub fn report_position(&mut self, track_id: u16, source: u8, position: Vector3, source_distance: f32, now: f32) {
+		let report = self.report_mut(track_id, source);
+		report.position = position;
+		report.source_distance = source_distance;
+		report.timestamp = now;
+	}
+
+	pub fn report_velocity(&mut self, track_id: u16, source: u8, velocity: Vector3) {
+		self.report_mut(track_id, source).velocity = velocity;
+	}
+
+	// Returns whether anyone is still reporting the track
+	pub fn drop_source(&mut self, track_id: u16, source: u8) -> bool {
+		let reports = match self.reports.get_mut(&track_id) {
+			Some(reports) => reports,
+			None => return false,
+		};
+
+		reports.retain(|r| r.source != source);
+		if reports.is_empty() {
+			self.reports.remove(&track_id);
+			return false;
+		}
+
+		true
+	}
+
+	pub fn drop_track(&mut self, track_id: u16) {
+		self.reports.remove(&track_id);
+	}
+
+	pub fn reports(&self, track_id: u16) -> &[TrackReport] {
+		self.reports.get(&track_id).map(|r| r.as_slice()).unwrap_or(&[])
+	}
+
+	pub fn fuse(&mut self, track_id: u16, now: f32) -> Option<FusedEstimate> {
+		let reports = self.reports.get_mut(&track_id)?;
+		reports.retain(|r| now - r.timestamp < REPORT_MAX_AGE);
+
+		// Everything is brought to the time of the newest report
+		let timestamp = reports.iter().map(|r| r.timestamp).fold(f32::MIN, f32::max);
+		let mut total_weight = 0.0;
+		let mut position = Vector3::zero();
+		let mut velocity = Vector3::zero();
+		for report in reports.iter() {
+			let weight = 1.0 / report.variance(now);
+			position += report.position_at(timestamp) * weight;
+			velocity += report.velocity * weight;
+			total_weight += weight;
+		}
+
+		if total_weight == 0.0 {
+			return None;
+		}
+
+		Some(FusedEstimate {
+			position: position / total_weight,
+			velocity: velocity / total_weight,
+			timestamp,
+			sources: reports.len(),
+		})
+	}
+}
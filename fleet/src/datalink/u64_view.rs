@@ -3,6 +3,7 @@ pub struct U64View {
 
 	write_index: usize,
 	read_index: usize,
+	overflowed: bool,
 }
 
 impl U64View {
@@ -11,16 +12,43 @@ impl U64View {
 	}
 
 	pub fn new(value: u64) -> U64View {
-		U64View { value, write_index: 0, read_index: 0 }
+		U64View {
+			value,
+			write_index: 0,
+			read_index: 0,
+			overflowed: false,
+		}
+	}
+
+	// Bits past the end of the word are dropped on write and read as zero
+	fn mask(start: usize, len: usize) -> u64 {
+		if start >= 64 {
+			return 0;
+		}
+
+		let len = len.min(64 - start);
+		if len == 64 {
+			u64::MAX
+		} else {
+			(1 << len) - 1
+		}
 	}
 
 	pub fn get(&self, start: usize, len: usize) -> u64 {
-		let mask = (1 << len) - 1;
+		let mask = U64View::mask(start, len);
+		if mask == 0 {
+			return 0;
+		}
+
 		(self.value >> start) & mask
 	}
 
 	pub fn set(&mut self, start: usize, len: usize, value: u64) {
-		let mask = (1 << len) - 1;
+		let mask = U64View::mask(start, len);
+		if mask == 0 {
+			return;
+		}
+
 		self.value &= !(mask << start);
 		self.value |= (value & mask) << start;
 	}
@@ -28,19 +56,19 @@ impl U64View {
 	pub fn write(&mut self, value: u64, len: usize) {
 		self.set(self.write_index, len, value);
 		self.write_index += len;
-		if self.write_index > 64 {
-			panic!("U64View write overflowed");
-		}
+		self.overflowed |= self.write_index > 64;
 	}
 
 	pub fn read(&mut self, len: usize) -> u64 {
 		let value = self.get(self.read_index, len);
 		self.read_index += len;
-
-		if self.read_index > 64 {
-			panic!("U64View read overflowed");
-		}
+		self.overflowed |= self.read_index > 64;
 
 		value
 	}
+
+	// Whether anything was read or written past the 64th bit
+	pub fn overflowed(&self) -> bool {
+		self.overflowed
+	}
 }
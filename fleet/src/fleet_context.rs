@@ -0,0 +1,89 @@
+use std::rc::Rc;
+
+use crate::{
+	clock::Clock,
+	controllers::{flight_controller::FlightController, radar_controller::RadarController},
+	datalink::{datalink::Datalink, messages::message::Message},
+	get,
+	hardware::traits::Hardware,
+	missile_control_system::MissileControlSystem,
+	ship_control_system::ShipControlSystem,
+};
+
+pub enum ControlSystem {
+	Ship(ShipControlSystem),
+	Missile(MissileControlSystem),
+}
+
+// Everything one vehicle runs on. Subsystems get handed the parts they need instead of reaching for globals,
+// so any number of vehicles can live in the same process
+pub struct FleetContext {
+	pub hw: Rc<dyn Hardware>,
+	pub clock: Rc<dyn Clock>,
+	pub datalink: Datalink,
+	pub radar: RadarController,
+	pub flight: FlightController,
+
+	// Taken out while it runs so it can borrow the rest of the context
+	control: Option<ControlSystem>,
+}
+
+impl FleetContext {
+	pub fn new(hw: Rc<dyn Hardware>, clock: Rc<dyn Clock>) -> FleetContext {
+		FleetContext {
+			datalink: Datalink::new(hw.clone(), clock.clone()),
+			radar: RadarController::new(hw.clone(), clock.clone()),
+			flight: FlightController::new(hw.clone()),
+			control: None,
+			hw,
+			clock,
+		}
+	}
+
+	pub fn configure_control_system(&mut self) {
+		let v_type_key = self.hw.vehicle_env("Type").unwrap();
+		let mut control = match v_type_key.as_str() {
+			"Ship" => {
+				println!("Setting up vehicle as ship");
+				ControlSystem::Ship(ShipControlSystem::new(self.hw.clone(), self.clock.clone()))
+			}
+			"Missile" => {
+				println!("Setting up vehicle as missile");
+				self.flight.setup_for_missile();
+				ControlSystem::Missile(MissileControlSystem::new(self.hw.clone()))
+			}
+			_ => panic!("Unknown vehicle type"),
+		};
+
+		match &mut control {
+			ControlSystem::Ship(scs) => scs.init(self),
+			ControlSystem::Missile(mcs) => mcs.init(),
+		}
+
+		self.control = Some(control);
+	}
+
+	pub fn update_control_system(&mut self) {
+		let mut control = get!(self.control.take());
+		match &mut control {
+			ControlSystem::Ship(scs) => scs.update(self),
+			ControlSystem::Missile(mcs) => mcs.update(self),
+		}
+
+		self.control = Some(control);
+	}
+
+	pub fn control_system_handle_dl_message(&mut self, message: Message) {
+		let mut control = get!(self.control.take());
+		match &mut control {
+			ControlSystem::Ship(scs) => scs.handle_dl_message(message),
+			ControlSystem::Missile(mcs) => mcs.handle_dl_message(message, self),
+		}
+
+		self.control = Some(control);
+	}
+
+	pub fn control_system(&self) -> Option<&ControlSystem> {
+		self.control.as_ref()
+	}
+}
@@ -0,0 +1,346 @@
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, MutexGuard},
+};
+
+use protologic_core::{
+	guns::AmmoType,
+	missile_launcher::{MissileEngineType, MissileWarheadType},
+	radar::RadarGetContactInfo,
+};
+
+use crate::math::{quaternion::Quaternion, vector3::Vector3};
+
+use super::traits::{DebugShape, Hardware};
+
+pub const MOCK_GUN_COUNT: usize = 4;
+pub const MOCK_LAUNCHER_CELLS: usize = 19;
+const MOCK_MAGAZINE_SIZE: i32 = 10;
+
+#[derive(Clone, Copy, Debug)]
+pub struct MockGun {
+	pub bearing: f32,
+	pub elevation: f32,
+	pub fuse: f32,
+
+	pub ammo: AmmoType,
+	pub magazine_remaining: i32,
+	pub refire_time: f32,
+	pub reload_time: f32,
+
+	pub shots_fired: u32,
+}
+
+impl MockGun {
+	fn new() -> MockGun {
+		MockGun {
+			bearing: 0.0,
+			elevation: 0.0,
+			fuse: 0.0,
+
+			ammo: AmmoType::Flak,
+			magazine_remaining: MOCK_MAGAZINE_SIZE,
+			refire_time: 0.0,
+			reload_time: 0.0,
+
+			shots_fired: 0,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct MockLauncherCell {
+	pub engine: MissileEngineType,
+	pub warhead: MissileWarheadType,
+	pub fuel_load: f32,
+	pub reload_time: f32,
+}
+
+impl MockLauncherCell {
+	fn new() -> MockLauncherCell {
+		MockLauncherCell {
+			engine: MissileEngineType::HighThrust,
+			warhead: MissileWarheadType::Nuclear,
+			fuel_load: 1.0,
+			reload_time: 0.0,
+		}
+	}
+}
+
+// Plain state behind the mock. Whoever drives the mock (a test, the simulator) writes the sensor side
+// and reads back the commands the fleet logic issued
+pub struct MockState {
+	pub position: Vector3,
+	pub velocity: Vector3,
+	pub orientation: Quaternion,
+	pub angular_velocity: Vector3,
+
+	pub throttle: f32,
+	pub torque: Vector3,
+	pub fuel: f32,
+
+	pub radar_angle: f32,
+	pub radar_bearing: f32,
+	pub radar_elevation: f32,
+	pub radar_triggered: bool,
+	pub radar_contacts: Vec<RadarGetContactInfo>,
+
+	pub radio_filter: u64,
+	pub radio_mask: u64,
+	pub radio_inbox: Vec<u64>,
+	pub radio_outbox: Vec<(u64, f32)>,
+
+	pub guns: Vec<MockGun>,
+	pub shell_speed: f32,
+
+	pub launcher_cells: Vec<MockLauncherCell>,
+	pub launches: Vec<i32>,
+
+	pub armed: bool,
+	pub destructed: bool,
+
+	pub env: HashMap<String, String>,
+}
+
+impl Default for MockState {
+	fn default() -> Self {
+		MockState::new()
+	}
+}
+
+impl MockState {
+	pub fn new() -> MockState {
+		MockState {
+			position: Vector3::zero(),
+			velocity: Vector3::zero(),
+			orientation: Quaternion::zero(),
+			angular_velocity: Vector3::zero(),
+
+			throttle: 0.0,
+			torque: Vector3::zero(),
+			fuel: 1.0,
+
+			radar_angle: 0.0,
+			radar_bearing: 0.0,
+			radar_elevation: 0.0,
+			radar_triggered: false,
+			radar_contacts: Vec::new(),
+
+			radio_filter: 0,
+			radio_mask: 0,
+			radio_inbox: Vec::new(),
+			radio_outbox: Vec::new(),
+
+			guns: vec![MockGun::new(); MOCK_GUN_COUNT],
+			shell_speed: 500.0,
+
+			launcher_cells: vec![MockLauncherCell::new(); MOCK_LAUNCHER_CELLS],
+			launches: Vec::new(),
+
+			armed: false,
+			destructed: false,
+
+			env: HashMap::new(),
+		}
+	}
+}
+
+// Hardware backend with no simulator behind it. State is shared so it can be handed to a vehicle running on
+// another thread while the owner keeps a handle to it
+#[derive(Clone)]
+pub struct MockHardware {
+	state: Arc<Mutex<MockState>>,
+}
+
+impl Default for MockHardware {
+	fn default() -> Self {
+		MockHardware::new()
+	}
+}
+
+impl MockHardware {
+	pub fn new() -> MockHardware {
+		MockHardware { state: Arc::new(Mutex::new(MockState::new())) }
+	}
+
+	pub fn state(&self) -> MutexGuard<'_, MockState> {
+		self.state.lock().unwrap()
+	}
+}
+
+impl Hardware for MockHardware {
+	fn vehicle_get_position(&self) -> (f32, f32, f32) {
+		let p = self.state().position;
+		(p.x, p.y, p.z)
+	}
+
+	fn vehicle_get_velocity(&self) -> (f32, f32, f32) {
+		let v = self.state().velocity;
+		(v.x, v.y, v.z)
+	}
+
+	fn vehicle_get_orientation(&self) -> (f32, f32, f32, f32) {
+		let q = self.state().orientation;
+		(q.x, q.y, q.z, q.w)
+	}
+
+	fn vehicle_get_angular_velocity(&self) -> (f32, f32, f32) {
+		let av = self.state().angular_velocity;
+		(av.x, av.y, av.z)
+	}
+
+	fn engine_set_throttle(&self, throttle: f32) {
+		self.state().throttle = throttle.clamp(0.0, 1.0);
+	}
+
+	fn engine_get_fuel_amount(&self) -> f32 {
+		self.state().fuel
+	}
+
+	fn wheel_set_torque(&self, x: f32, y: f32, z: f32) {
+		self.state().torque = Vector3::new(x, y, z).clamp(-1.0, 1.0);
+	}
+
+	fn radar_set_angle(&self, angle: f32) {
+		self.state().radar_angle = angle;
+	}
+
+	fn radar_set_bearing(&self, bearing: f32) {
+		self.state().radar_bearing = bearing;
+	}
+
+	fn radar_set_elevation(&self, elevation: f32) {
+		self.state().radar_elevation = elevation;
+	}
+
+	fn radar_trigger(&self) {
+		self.state().radar_triggered = true;
+	}
+
+	fn radar_get_contacts(&self, output: &mut Vec<RadarGetContactInfo>) {
+		output.clear();
+		output.extend(self.state().radar_contacts.iter().cloned());
+	}
+
+	fn radio_receive_filter(&self, filter: u64, mask: u64) {
+		let mut state = self.state();
+		state.radio_filter = filter;
+		state.radio_mask = mask;
+	}
+
+	fn radio_receive(&self, output: &mut Vec<u64>) {
+		let mut state = self.state();
+		let (filter, mask) = (state.radio_filter, state.radio_mask);
+
+		output.clear();
+		output.extend(state.radio_inbox.drain(..).filter(|m| m & mask == filter));
+	}
+
+	fn radio_transmit(&self, message: u64, range: f32) {
+		self.state().radio_outbox.push((message, range));
+	}
+
+	fn gun_set_bearing(&self, index: i32, bearing: f32) {
+		self.state().guns[index as usize].bearing = bearing;
+	}
+
+	fn gun_set_elevation(&self, index: i32, elevation: f32) {
+		self.state().guns[index as usize].elevation = elevation;
+	}
+
+	fn gun_set_fuse(&self, index: i32, fuse: f32) {
+		self.state().guns[index as usize].fuse = fuse;
+	}
+
+	fn gun_trigger(&self, index: i32) {
+		let gun = &mut self.state().guns[index as usize];
+		if gun.refire_time > 0.0 || gun.reload_time > 0.0 || gun.magazine_remaining == 0 {
+			return;
+		}
+
+		gun.magazine_remaining -= 1;
+		gun.shots_fired += 1;
+	}
+
+	fn gun_reload(&self, index: i32, ammo: AmmoType) {
+		let gun = &mut self.state().guns[index as usize];
+		gun.ammo = ammo;
+		gun.magazine_remaining = MOCK_MAGAZINE_SIZE;
+	}
+
+	fn gun_get_bearing(&self, index: i32) -> f32 {
+		self.state().guns[index as usize].bearing
+	}
+
+	fn gun_get_elevation(&self, index: i32) -> f32 {
+		self.state().guns[index as usize].elevation
+	}
+
+	fn gun_get_refiretime(&self, index: i32) -> f32 {
+		self.state().guns[index as usize].refire_time
+	}
+
+	fn gun_get_magazine_remaining(&self, index: i32) -> i32 {
+		self.state().guns[index as usize].magazine_remaining
+	}
+
+	fn gun_get_magazine_type(&self, index: i32) -> AmmoType {
+		self.state().guns[index as usize].ammo
+	}
+
+	fn gun_get_magazine_reloadtime(&self, index: i32) -> f32 {
+		self.state().guns[index as usize].reload_time
+	}
+
+	fn turret_shell_speed(&self) -> f32 {
+		self.state().shell_speed
+	}
+
+	fn missilelauncher_configure(&self, index: i32, engine: MissileEngineType, warhead: MissileWarheadType, fuel_load: f32) {
+		let cell = &mut self.state().launcher_cells[index as usize];
+		cell.engine = engine;
+		cell.warhead = warhead;
+		cell.fuel_load = fuel_load;
+	}
+
+	fn missilelauncher_trigger(&self, index: i32) {
+		let mut state = self.state();
+		if state.launcher_cells[index as usize].reload_time > 0.0 {
+			return;
+		}
+
+		state.launches.push(index);
+	}
+
+	fn missilelauncher_get_enginetype(&self, index: i32) -> MissileEngineType {
+		self.state().launcher_cells[index as usize].engine
+	}
+
+	fn missilelauncher_get_warheadtype(&self, index: i32) -> MissileWarheadType {
+		self.state().launcher_cells[index as usize].warhead
+	}
+
+	fn missilelauncher_get_reloadtime(&self, index: i32) -> f32 {
+		self.state().launcher_cells[index as usize].reload_time
+	}
+
+	fn warhead_arm(&self) {
+		self.state().armed = true;
+	}
+
+	fn self_destruct(&self) {
+		self.state().destructed = true;
+	}
+
+	fn debug_sphere_create(&self, _x: f32, _y: f32, _z: f32, _radius: f32, _r: f32, _g: f32, _b: f32) -> DebugShape {
+		DebugShape::none()
+	}
+
+	fn debug_line_create(&self, _x1: f32, _y1: f32, _z1: f32, _x2: f32, _y2: f32, _z2: f32, _r: f32, _g: f32, _b: f32) -> DebugShape {
+		DebugShape::none()
+	}
+
+	fn vehicle_env(&self, key: &str) -> Option<String> {
+		self.state().env.get(key).cloned()
+	}
+}
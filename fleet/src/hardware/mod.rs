@@ -0,0 +1,3 @@
+pub mod mock_hardware;
+pub mod protologic_hardware;
+pub mod traits;
@@ -0,0 +1,168 @@
+use std::env;
+
+use protologic_core::{
+	constants,
+	debugging::{debug_line_create, debug_sphere_create},
+	guns::*,
+	maneuvering::{engine_get_fuel_amount, engine_set_throttle, wheel_set_torque},
+	missile_launcher::*,
+	physics::{vehicle_get_angular_velocity, vehicle_get_orientation, vehicle_get_position, vehicle_get_velocity},
+	radar::*,
+	radio::{radio_receive, radio_receive_filter, radio_transmit},
+	warhead::{self_destruct, warhead_arm},
+};
+
+use super::traits::{DebugShape, Hardware};
+
+// The real thing, forwards straight to the protologic host functions
+pub struct ProtologicHardware;
+
+impl Hardware for ProtologicHardware {
+	fn vehicle_get_position(&self) -> (f32, f32, f32) {
+		vehicle_get_position()
+	}
+
+	fn vehicle_get_velocity(&self) -> (f32, f32, f32) {
+		vehicle_get_velocity()
+	}
+
+	fn vehicle_get_orientation(&self) -> (f32, f32, f32, f32) {
+		vehicle_get_orientation()
+	}
+
+	fn vehicle_get_angular_velocity(&self) -> (f32, f32, f32) {
+		vehicle_get_angular_velocity()
+	}
+
+	fn engine_set_throttle(&self, throttle: f32) {
+		engine_set_throttle(throttle);
+	}
+
+	fn engine_get_fuel_amount(&self) -> f32 {
+		engine_get_fuel_amount()
+	}
+
+	fn wheel_set_torque(&self, x: f32, y: f32, z: f32) {
+		wheel_set_torque(x, y, z);
+	}
+
+	fn radar_set_angle(&self, angle: f32) {
+		radar_set_angle(angle);
+	}
+
+	fn radar_set_bearing(&self, bearing: f32) {
+		radar_set_bearing(bearing);
+	}
+
+	fn radar_set_elevation(&self, elevation: f32) {
+		radar_set_elevation(elevation);
+	}
+
+	fn radar_trigger(&self) {
+		radar_trigger();
+	}
+
+	fn radar_get_contacts(&self, output: &mut Vec<RadarGetContactInfo>) {
+		radar_get_contacts(output);
+	}
+
+	fn radio_receive_filter(&self, filter: u64, mask: u64) {
+		radio_receive_filter(filter, mask);
+	}
+
+	fn radio_receive(&self, output: &mut Vec<u64>) {
+		radio_receive(output);
+	}
+
+	fn radio_transmit(&self, message: u64, range: f32) {
+		radio_transmit(message, range);
+	}
+
+	fn gun_set_bearing(&self, index: i32, bearing: f32) {
+		gun_set_bearing(index, bearing);
+	}
+
+	fn gun_set_elevation(&self, index: i32, elevation: f32) {
+		gun_set_elevation(index, elevation);
+	}
+
+	fn gun_set_fuse(&self, index: i32, fuse: f32) {
+		gun_set_fuse(index, fuse);
+	}
+
+	fn gun_trigger(&self, index: i32) {
+		gun_trigger(index);
+	}
+
+	fn gun_reload(&self, index: i32, ammo: AmmoType) {
+		gun_reload(index, ammo);
+	}
+
+	fn gun_get_bearing(&self, index: i32) -> f32 {
+		gun_get_bearing(index)
+	}
+
+	fn gun_get_elevation(&self, index: i32) -> f32 {
+		gun_get_elevation(index)
+	}
+
+	fn gun_get_refiretime(&self, index: i32) -> f32 {
+		gun_get_refiretime(index)
+	}
+
+	fn gun_get_magazine_remaining(&self, index: i32) -> i32 {
+		gun_get_magazine_remaining(index)
+	}
+
+	fn gun_get_magazine_type(&self, index: i32) -> AmmoType {
+		gun_get_magazine_type(index)
+	}
+
+	fn gun_get_magazine_reloadtime(&self, index: i32) -> f32 {
+		gun_get_magazine_reloadtime(index)
+	}
+
+	fn turret_shell_speed(&self) -> f32 {
+		constants::turret_shell_speed()
+	}
+
+	fn missilelauncher_configure(&self, index: i32, engine: MissileEngineType, warhead: MissileWarheadType, fuel_load: f32) {
+		missilelauncher_configure(index, engine, warhead, fuel_load);
+	}
+
+	fn missilelauncher_trigger(&self, index: i32) {
+		missilelauncher_trigger(index);
+	}
+
+	fn missilelauncher_get_enginetype(&self, index: i32) -> MissileEngineType {
+		missilelauncher_get_enginetype(index)
+	}
+
+	fn missilelauncher_get_warheadtype(&self, index: i32) -> MissileWarheadType {
+		missilelauncher_get_warheadtype(index)
+	}
+
+	fn missilelauncher_get_reloadtime(&self, index: i32) -> f32 {
+		missilelauncher_get_reloadtime(index)
+	}
+
+	fn warhead_arm(&self) {
+		warhead_arm();
+	}
+
+	fn self_destruct(&self) {
+		self_destruct();
+	}
+
+	fn debug_sphere_create(&self, x: f32, y: f32, z: f32, radius: f32, r: f32, g: f32, b: f32) -> DebugShape {
+		DebugShape::from_handle(debug_sphere_create(x, y, z, radius, r, g, b))
+	}
+
+	fn debug_line_create(&self, x1: f32, y1: f32, z1: f32, x2: f32, y2: f32, z2: f32, r: f32, g: f32, b: f32) -> DebugShape {
+		DebugShape::from_handle(debug_line_create(x1, y1, z1, x2, y2, z2, r, g, b))
+	}
+
+	fn vehicle_env(&self, key: &str) -> Option<String> {
+		env::var(key).ok()
+	}
+}
@@ -0,0 +1,82 @@
+use protologic_core::{
+	debugging::DebugShapeHandle,
+	guns::AmmoType,
+	missile_launcher::{MissileEngineType, MissileWarheadType},
+	radar::RadarGetContactInfo,
+};
+
+// Keeps a debug shape alive until dropped, backends without a renderer just hand out empty shapes
+pub struct DebugShape {
+	_handle: Option<DebugShapeHandle>,
+}
+
+impl DebugShape {
+	pub fn none() -> DebugShape {
+		DebugShape { _handle: None }
+	}
+
+	pub fn from_handle(handle: DebugShapeHandle) -> DebugShape {
+		DebugShape { _handle: Some(handle) }
+	}
+}
+
+// Everything the fleet logic needs from the vehicle it is running on. Mirrors the protologic_core API so the
+// real simulator is just one backend, and a mock one can stand in for it on a normal native build
+pub trait Hardware {
+	// Physics
+	fn vehicle_get_position(&self) -> (f32, f32, f32);
+	fn vehicle_get_velocity(&self) -> (f32, f32, f32);
+	fn vehicle_get_orientation(&self) -> (f32, f32, f32, f32);
+	fn vehicle_get_angular_velocity(&self) -> (f32, f32, f32);
+
+	// Maneuvering
+	fn engine_set_throttle(&self, throttle: f32);
+	fn engine_get_fuel_amount(&self) -> f32;
+	fn wheel_set_torque(&self, x: f32, y: f32, z: f32);
+
+	// Radar
+	fn radar_set_angle(&self, angle: f32);
+	fn radar_set_bearing(&self, bearing: f32);
+	fn radar_set_elevation(&self, elevation: f32);
+	fn radar_trigger(&self);
+	fn radar_get_contacts(&self, output: &mut Vec<RadarGetContactInfo>);
+
+	// Radio
+	fn radio_receive_filter(&self, filter: u64, mask: u64);
+	fn radio_receive(&self, output: &mut Vec<u64>);
+	fn radio_transmit(&self, message: u64, range: f32);
+
+	// Guns
+	fn gun_set_bearing(&self, index: i32, bearing: f32);
+	fn gun_set_elevation(&self, index: i32, elevation: f32);
+	fn gun_set_fuse(&self, index: i32, fuse: f32);
+	fn gun_trigger(&self, index: i32);
+	fn gun_reload(&self, index: i32, ammo: AmmoType);
+	fn gun_get_bearing(&self, index: i32) -> f32;
+	fn gun_get_elevation(&self, index: i32) -> f32;
+	fn gun_get_refiretime(&self, index: i32) -> f32;
+	fn gun_get_magazine_remaining(&self, index: i32) -> i32;
+	fn gun_get_magazine_type(&self, index: i32) -> AmmoType;
+	fn gun_get_magazine_reloadtime(&self, index: i32) -> f32;
+	fn turret_shell_speed(&self) -> f32;
+
+	// Missile launcher
+	fn missilelauncher_configure(&self, index: i32, engine: MissileEngineType, warhead: MissileWarheadType, fuel_load: f32);
+	fn missilelauncher_trigger(&self, index: i32);
+	fn missilelauncher_get_enginetype(&self, index: i32) -> MissileEngineType;
+	fn missilelauncher_get_warheadtype(&self, index: i32) -> MissileWarheadType;
+	fn missilelauncher_get_reloadtime(&self, index: i32) -> f32;
+
+	// Warhead
+	fn warhead_arm(&self);
+	fn self_destruct(&self);
+
+	// Debug shapes
+	#[allow(clippy::too_many_arguments)]
+	fn debug_sphere_create(&self, x: f32, y: f32, z: f32, radius: f32, r: f32, g: f32, b: f32) -> DebugShape;
+	#[allow(clippy::too_many_arguments)]
+	fn debug_line_create(&self, x1: f32, y1: f32, z1: f32, x2: f32, y2: f32, z2: f32, r: f32, g: f32, b: f32) -> DebugShape;
+
+	// Launch configuration (vehicle "Type", missile "WarheadType", ...)
+	fn vehicle_env(&self, key: &str) -> Option<String>;
+}
@@ -1,19 +1,25 @@
 use core::Core;
-use std::env;
+use std::{env, rc::Rc};
 
+use clock::TickClock;
 use controllers::flight_controller::VehicleType;
-use math::utils::now;
+use hardware::protologic_hardware::ProtologicHardware;
 use protologic_core::{physics::vehicle_get_position, wait::*};
 
 extern crate protologic_core;
+pub mod clock;
 pub mod controllers;
 pub mod core;
 pub mod datalink;
+pub mod fleet_context;
+pub mod hardware;
 pub mod math;
 pub mod missile_control_system;
 pub mod radar_scan_pattern;
 pub mod ship_control_system;
+pub mod threat_evaluation;
 pub mod updatable_debug;
+pub mod weapon_assignment;
 
 #[no_mangle]
 pub extern "C" fn tick() {
@@ -40,15 +46,12 @@ pub extern "C" fn tick() {
 		}
 	}
 
-	let mut core = Core::new(v_type);
-	let mut prev_time = now();
+	let mut core = Core::new(v_type, Rc::new(ProtologicHardware), Rc::new(TickClock::new()));
 
 	println!("Pos: {:?}", vehicle_get_position());
 
 	loop {
-		let dt = now() - prev_time;
-		prev_time = now();
-		core.tick(dt);
+		core.tick();
 		wait_tick();
 	}
 }
@@ -0,0 +1,158 @@
+// Minimum total cost assignment of every row to a different column, the Hungarian algorithm with
+// potentials. Needs at least as many columns as rows. Returns the column picked for each row
+pub fn min_cost_assignment(costs: &[Vec<f32>]) -> Vec<usize> {
+	let rows = costs.len();
+	if rows == 0 {
+		return Vec::new();
+	}
+	let columns = costs[0].len();
+	assert!(columns >= rows, "{} rows can't each get one of {} columns", rows, columns);
+
+	// 1-indexed, row/column 0 is the virtual start of each augmenting path
+	let mut u = vec![0.0f64; rows + 1];
+	let mut v = vec![0.0f64; columns + 1];
+	let mut row_of = vec![0usize; columns + 1]; // Row assigned to each column, 0 for none
+	let mut way = vec![0usize; columns + 1];
+
+	for row in 1..=rows {
+		row_of[0] = row;
+		let mut column = 0;
+		let mut min_to = vec![f64::INFINITY; columns + 1];
+		let mut used = vec![false; columns + 1];
+
+		loop {
+			used[column] = true;
+			let current = row_of[column];
+			let mut delta = f64::INFINITY;
+			let mut next = 0;
+			for j in 1..=columns {
+				if used[j] {
+					continue;
+				}
+
+				let reduced = costs[current - 1][j - 1] as f64 - u[current] - v[j];
+				if reduced < min_to[j] {
+					min_to[j] = reduced;
+					way[j] = column;
+				}
+				if min_to[j] < delta {
+					delta = min_to[j];
+					next = j;
+				}
+			}
+
+			for j in 0..=columns {
+				if used[j] {
+					u[row_of[j]] += delta;
+					v[j] -= delta;
+				} else {
+					min_to[j] -= delta;
+				}
+			}
+
+			column = next;
+			if row_of[column] == 0 {
+				break;
+			}
+		}
+
+		// Flip the augmenting path
+		while column != 0 {
+			let previous = way[column];
+			row_of[column] = row_of[previous];
+			column = previous;
+		}
+	}
+
+	let mut assignment = vec![0; rows];
+	for j in 1..=columns {
+		if row_of[j] != 0 {
+			assignment[row_of[j] - 1] = j - 1;
+		}
+	}
+
+	assignment
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn total(costs: &[Vec<f32>], assignment: &[usize]) -> f32 {
+		assignment.iter().enumerate().map(|(row, column)| costs[row][*column]).sum()
+	}
+
+	// Cheapest total over every way of giving each row its own column
+	fn brute_force(costs: &[Vec<f32>], row: usize, used: &mut Vec<bool>) -> f32 {
+		if row == costs.len() {
+			return 0.0;
+		}
+
+		let mut best = f32::INFINITY;
+		for column in 0..used.len() {
+			if !used[column] {
+				used[column] = true;
+				best = best.min(costs[row][column] + brute_force(costs, row + 1, used));
+				used[column] = false;
+			}
+		}
+		best
+	}
+
+	fn assert_distinct(assignment: &[usize]) {
+		let mut columns = assignment.to_vec();
+		columns.sort();
+		columns.dedup();
+		assert_eq!(columns.len(), assignment.len(), "{:?} gives a column to more than one row", assignment);
+	}
+
+	#[test]
+	fn square_matrix_gets_the_cheapest_assignment() {
+		let costs = vec![vec![4.0, 1.0, 3.0], vec![2.0, 0.0, 5.0], vec![3.0, 2.0, 2.0]];
+		assert_eq!(min_cost_assignment(&costs), vec![1, 0, 2]);
+	}
+
+	#[test]
+	fn rectangular_matrices_match_brute_force() {
+		for seed in 1..20u32 {
+			let mut state = seed;
+			let mut next = || {
+				state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+				(state >> 16) as f32 % 100.0
+			};
+			let rows = 1 + seed as usize % 4;
+			let columns = rows + seed as usize % 3;
+			let costs: Vec<Vec<f32>> = (0..rows).map(|_| (0..columns).map(|_| next()).collect()).collect();
+
+			let assignment = min_cost_assignment(&costs);
+			assert_eq!(assignment.len(), rows);
+			assert_distinct(&assignment);
+			assert_eq!(total(&costs, &assignment), brute_force(&costs, 0, &mut vec![false; columns]), "{:?}", costs);
+		}
+	}
+
+	#[test]
+	fn forbidden_pairs_are_avoided_when_there_is_a_way_round() {
+		const FORBIDDEN: f32 = 1.0e6;
+		// Row 0 is cheapest on column 0, but row 1 can't have anything else
+		let costs = vec![vec![1.0, 5.0, FORBIDDEN], vec![2.0, FORBIDDEN, FORBIDDEN]];
+		assert_eq!(min_cost_assignment(&costs), vec![1, 0]);
+
+		// No way round, the forbidden pair is still picked and it's up to the caller to see it
+		let costs = vec![vec![1.0, FORBIDDEN], vec![2.0, FORBIDDEN]];
+		let assignment = min_cost_assignment(&costs);
+		assert_distinct(&assignment);
+		assert_eq!(total(&costs, &assignment), 1.0 + FORBIDDEN);
+	}
+
+	#[test]
+	fn empty_matrix_gets_nothing() {
+		assert!(min_cost_assignment(&[]).is_empty());
+	}
+
+	#[test]
+	#[should_panic]
+	fn more_rows_than_columns_is_rejected() {
+		min_cost_assignment(&[vec![1.0], vec![2.0]]);
+	}
+}
@@ -10,7 +10,43 @@ pub fn first_order_intercept(
 	let target_relative_velocity = target_velocity - shooter_velocity;
 	let t = first_order_intercept_time(shot_speed, target_position - shooter_position, target_relative_velocity);
 
-	return target_position + target_relative_velocity * t;
+	target_position + target_relative_velocity * t
+}
+
+const ACCEL_INTERCEPT_ITERATIONS: usize = 4;
+
+// Same as first_order_intercept, but leads a target that is accelerating. Starts from the constant velocity
+// solution and refines the flight time against the curved path a few times
+pub fn first_order_intercept_accel(
+	shooter_position: Vector3,
+	shooter_velocity: Vector3,
+	shot_speed: f32,
+	target_position: Vector3,
+	target_velocity: Vector3,
+	target_acceleration: Vector3,
+) -> Vector3 {
+	let target_relative_velocity = target_velocity - shooter_velocity;
+	let target_relative_position = target_position - shooter_position;
+	if shot_speed <= 0.0 {
+		return target_position;
+	}
+
+	let mut t = first_order_intercept_time(shot_speed, target_relative_position, target_relative_velocity);
+	if t == 0.0 {
+		// No intercept at constant velocity, aim straight at it like first_order_intercept does. Unless it isn't
+		// moving yet, then the acceleration alone is worth leading
+		if target_relative_velocity.length_sq() >= 0.001 {
+			return target_position;
+		}
+		t = target_relative_position.length() / shot_speed;
+	}
+
+	for _ in 0..ACCEL_INTERCEPT_ITERATIONS {
+		let offset = target_relative_velocity * t + target_acceleration * (0.5 * t * t);
+		t = (target_relative_position + offset).length() / shot_speed;
+	}
+
+	target_position + target_relative_velocity * t + target_acceleration * (0.5 * t * t)
 }
 
 pub fn first_order_intercept_time(shot_speed: f32, target_relative_position: Vector3, target_relative_velocity: Vector3) -> f32 {
@@ -34,13 +70,83 @@ pub fn first_order_intercept_time(shot_speed: f32, target_relative_position: Vec
 		let t2 = (-b - detriment.sqrt()) / (2.0 * a);
 
 		if t1 > 0.0 {
-			return if t2 > 0.0 { f32::min(t1, t2) } else { t1 };
+			if t2 > 0.0 {
+				f32::min(t1, t2)
+			} else {
+				t1
+			}
 		} else {
-			return t2.max(0.0);
+			t2.max(0.0)
 		}
 	} else if detriment < 0.0 {
-		return 0.0;
+		0.0
 	} else {
-		return (-b / (2.0 * a)).max(0.0);
+		(-b / (2.0 * a)).max(0.0)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stationary_target_is_aimed_at_directly() {
+		let target = Vector3::new(300.0, 400.0, 0.0);
+		let aim = first_order_intercept(Vector3::zero(), Vector3::zero(), 1000.0, target, Vector3::zero());
+		assert!((aim - target).length() < 0.01);
+	}
+
+	#[test]
+	fn shot_and_target_arrive_together() {
+		let shooter = Vector3::new(0.0, 0.0, 100.0);
+		let shooter_velocity = Vector3::new(10.0, 0.0, 0.0);
+		let target = Vector3::new(2000.0, 500.0, -300.0);
+		let target_velocity = Vector3::new(-50.0, 120.0, 30.0);
+		let shot_speed = 800.0;
+
+		let t = first_order_intercept_time(shot_speed, target - shooter, target_velocity - shooter_velocity);
+		let aim = first_order_intercept(shooter, shooter_velocity, shot_speed, target, target_velocity);
+
+		assert!(t > 0.0);
+		assert!(
+			((aim - shooter).length() - shot_speed * t).abs() < 0.5,
+			"Shot travels {}m in {}s",
+			(aim - shooter).length(),
+			t
+		);
+		assert!((aim - (target + (target_velocity - shooter_velocity) * t)).length() < 0.5);
+	}
+
+	#[test]
+	fn target_outrunning_the_shot_has_no_intercept() {
+		let t = first_order_intercept_time(100.0, Vector3::new(1000.0, 0.0, 0.0), Vector3::new(500.0, 0.0, 0.0));
+		assert_eq!(t, 0.0);
+	}
+
+	#[test]
+	fn accelerating_target_is_led_along_its_curve() {
+		let target = Vector3::new(1500.0, 0.0, 0.0);
+		let target_velocity = Vector3::new(0.0, 100.0, 0.0);
+		let target_acceleration = Vector3::new(0.0, 20.0, 0.0);
+		let shot_speed = 1000.0;
+
+		let aim = first_order_intercept_accel(Vector3::zero(), Vector3::zero(), shot_speed, target, target_velocity, target_acceleration);
+
+		let t = aim.length() / shot_speed;
+		let there = target + target_velocity * t + target_acceleration * (0.5 * t * t);
+		assert!((aim - there).length() < 1.0, "Aim point is {}m off the target's path", (aim - there).length());
+	}
+
+	#[test]
+	fn accelerating_target_without_an_intercept_is_aimed_at_directly() {
+		let target = Vector3::new(1000.0, 0.0, 0.0);
+		let target_velocity = Vector3::new(500.0, 0.0, 0.0);
+		let target_acceleration = Vector3::new(0.0, 20.0, 0.0);
+
+		let outrun = first_order_intercept_accel(Vector3::zero(), Vector3::zero(), 100.0, target, target_velocity, target_acceleration);
+		assert!((outrun - target).length() < 0.01);
+
+		let no_shot = first_order_intercept_accel(Vector3::zero(), Vector3::zero(), 0.0, target, target_velocity, target_acceleration);
+		assert!((no_shot - target).length() < 0.01);
 	}
 }
@@ -0,0 +1,264 @@
+use super::vector3::Vector3;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MotionModel {
+	ConstantVelocity,
+	ConstantAcceleration,
+}
+
+// One axis of the track, state is [position, velocity, acceleration]. The axes are filtered independently,
+// radar noise isn't correlated between them closely enough to be worth a full 9x9 covariance
+#[derive(Clone, Copy, Debug)]
+struct AxisFilter {
+	x: [f32; 3],
+	p: [[f32; 3]; 3],
+}
+
+impl AxisFilter {
+	fn new(position: f32, position_variance: f32, velocity_variance: f32, acceleration_variance: f32) -> AxisFilter {
+		AxisFilter {
+			x: [position, 0.0, 0.0],
+			p: [[position_variance, 0.0, 0.0], [0.0, velocity_variance, 0.0], [0.0, 0.0, acceleration_variance]],
+		}
+	}
+
+	#[allow(clippy::needless_range_loop)]
+	fn predict(&mut self, model: MotionModel, dt: f32, q: f32) {
+		let dt2 = dt * dt;
+		let dt3 = dt2 * dt;
+
+		// x = F x, P = F P F' + Q
+		let (f, noise) = match model {
+			MotionModel::ConstantVelocity => (
+				[[1.0, dt, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 0.0]],
+				// White noise acceleration
+				[[dt3 / 3.0, dt2 / 2.0, 0.0], [dt2 / 2.0, dt, 0.0], [0.0, 0.0, 0.0]],
+			),
+			MotionModel::ConstantAcceleration => (
+				[[1.0, dt, dt2 / 2.0], [0.0, 1.0, dt], [0.0, 0.0, 1.0]],
+				// White noise jerk
+				[
+					[dt3 * dt2 / 20.0, dt3 * dt / 8.0, dt3 / 6.0],
+					[dt3 * dt / 8.0, dt3 / 3.0, dt2 / 2.0],
+					[dt3 / 6.0, dt2 / 2.0, dt],
+				],
+			),
+		};
+
+		let mut x = [0.0; 3];
+		for i in 0..3 {
+			for j in 0..3 {
+				x[i] += f[i][j] * self.x[j];
+			}
+		}
+
+		let mut fp = [[0.0; 3]; 3];
+		for i in 0..3 {
+			for j in 0..3 {
+				for k in 0..3 {
+					fp[i][j] += f[i][k] * self.p[k][j];
+				}
+			}
+		}
+
+		let mut p = [[0.0; 3]; 3];
+		for i in 0..3 {
+			for j in 0..3 {
+				for k in 0..3 {
+					p[i][j] += fp[i][k] * f[j][k];
+				}
+				p[i][j] += noise[i][j] * q;
+			}
+		}
+
+		self.x = x;
+		self.p = p;
+	}
+
+	// Only position is measured, so H = [1, 0, 0]
+	#[allow(clippy::needless_range_loop)]
+	fn update(&mut self, measurement: f32, r: f32) {
+		let s = self.p[0][0] + r;
+		let k = [self.p[0][0] / s, self.p[1][0] / s, self.p[2][0] / s];
+		let residual = measurement - self.x[0];
+
+		for i in 0..3 {
+			self.x[i] += k[i] * residual;
+		}
+
+		let row = self.p[0];
+		for i in 0..3 {
+			for j in 0..3 {
+				self.p[i][j] -= k[i] * row[j];
+			}
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct KalmanFilter {
+	pub model: MotionModel,
+
+	// Variance of a single position measurement (m^2)
+	measurement_variance: f32,
+	// Spectral density of the unmodelled motion, acceleration for CV and jerk for CA
+	process_noise: f32,
+
+	axes: [AxisFilter; 3],
+}
+
+impl KalmanFilter {
+	pub fn new(model: MotionModel, position: Vector3, measurement_variance: f32, process_noise: f32, initial_velocity_variance: f32) -> KalmanFilter {
+		let acceleration_variance = if model == MotionModel::ConstantAcceleration {
+			initial_velocity_variance
+		} else {
+			0.0
+		};
+		let axis = |p: f32| AxisFilter::new(p, measurement_variance, initial_velocity_variance, acceleration_variance);
+
+		KalmanFilter {
+			model,
+			measurement_variance,
+			process_noise,
+			axes: [axis(position.x), axis(position.y), axis(position.z)],
+		}
+	}
+
+	pub fn predict(&mut self, dt: f32) {
+		if dt <= 0.0 {
+			return;
+		}
+
+		for axis in self.axes.iter_mut() {
+			axis.predict(self.model, dt, self.process_noise);
+		}
+	}
+
+	pub fn update(&mut self, position: Vector3) {
+		let r = self.measurement_variance;
+		self.axes[0].update(position.x, r);
+		self.axes[1].update(position.y, r);
+		self.axes[2].update(position.z, r);
+	}
+
+	// Copy of the filter run forward, for looking ahead without touching the track
+	pub fn predicted(&self, dt: f32) -> KalmanFilter {
+		let mut filter = *self;
+		filter.predict(dt);
+		filter
+	}
+
+	fn state(&self, index: usize) -> Vector3 {
+		Vector3::new(self.axes[0].x[index], self.axes[1].x[index], self.axes[2].x[index])
+	}
+
+	fn variance(&self, index: usize) -> Vector3 {
+		Vector3::new(self.axes[0].p[index][index], self.axes[1].p[index][index], self.axes[2].p[index][index])
+	}
+
+	pub fn position(&self) -> Vector3 {
+		self.state(0)
+	}
+
+	pub fn velocity(&self) -> Vector3 {
+		self.state(1)
+	}
+
+	pub fn acceleration(&self) -> Vector3 {
+		self.state(2)
+	}
+
+	// Per axis variances (diagonal of the covariance)
+	pub fn position_variance(&self) -> Vector3 {
+		self.variance(0)
+	}
+
+	pub fn velocity_variance(&self) -> Vector3 {
+		self.variance(1)
+	}
+
+	pub fn acceleration_variance(&self) -> Vector3 {
+		self.variance(2)
+	}
+
+	// Variance of the difference between a new measurement and the predicted position, per axis
+	pub fn innovation_variance(&self) -> Vector3 {
+		let p = self.position_variance();
+		let r = self.measurement_variance;
+		Vector3::new(p.x + r, p.y + r, p.z + r)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const DT: f32 = 0.1;
+
+	// Repeatable stand-in for radar noise, a few metres either way
+	fn noise(step: usize) -> Vector3 {
+		let t = step as f32;
+		Vector3::new((t * 1.7).sin() * 5.0, (t * 2.3).cos() * 5.0, (t * 3.1).sin() * 5.0)
+	}
+
+	#[test]
+	fn constant_velocity_converges_on_a_constant_velocity_target() {
+		let start = Vector3::new(1000.0, -2000.0, 500.0);
+		let velocity = Vector3::new(100.0, -50.0, 20.0);
+		let mut filter = KalmanFilter::new(MotionModel::ConstantVelocity, start, 25.0, 1.0, 10000.0);
+
+		for step in 1..=300 {
+			filter.predict(DT);
+			filter.update(start + velocity * (step as f32 * DT) + noise(step));
+		}
+
+		let truth = start + velocity * (300.0 * DT);
+		assert!(
+			(filter.position() - truth).length() < 5.0,
+			"Position is {}m off",
+			(filter.position() - truth).length()
+		);
+		assert!(
+			(filter.velocity() - velocity).length() < 1.0,
+			"Velocity is {}m/s off",
+			(filter.velocity() - velocity).length()
+		);
+	}
+
+	#[test]
+	fn update_shrinks_and_predict_grows_the_covariance() {
+		let mut filter = KalmanFilter::new(MotionModel::ConstantVelocity, Vector3::zero(), 25.0, 1.0, 10000.0);
+		filter.predict(DT);
+		filter.update(Vector3::zero());
+
+		let before = filter.position_variance().x;
+		filter.predict(1.0);
+		let predicted = filter.position_variance().x;
+		filter.update(Vector3::zero());
+		let updated = filter.position_variance().x;
+
+		assert!(predicted > before, "Predicting took the variance from {} to {}", before, predicted);
+		assert!(updated < predicted, "Updating took the variance from {} to {}", predicted, updated);
+		assert!(filter.velocity_variance().x > 0.0);
+	}
+
+	#[test]
+	fn constant_acceleration_tracks_a_constant_acceleration() {
+		let start = Vector3::new(0.0, 3000.0, -1000.0);
+		let velocity = Vector3::new(50.0, 0.0, -20.0);
+		let acceleration = Vector3::new(5.0, -3.0, 10.0);
+		let truth = |t: f32| start + velocity * t + acceleration * (0.5 * t * t);
+		let mut filter = KalmanFilter::new(MotionModel::ConstantAcceleration, start, 25.0, 0.1, 10000.0);
+
+		for step in 1..=300 {
+			filter.predict(DT);
+			filter.update(truth(step as f32 * DT) + noise(step));
+		}
+
+		let t = 300.0 * DT;
+		let error = (filter.acceleration() - acceleration).length();
+		assert!(error < 0.5, "Acceleration is {}m/s^2 off", error);
+		assert!((filter.velocity() - (velocity + acceleration * t)).length() < 2.0);
+		assert!((filter.position() - truth(t)).length() < 5.0);
+	}
+}
@@ -1,4 +1,6 @@
+pub mod assignment;
 pub mod first_order_intercept;
+pub mod kalman;
 pub mod pid;
 pub mod quaternion;
 pub mod utils;
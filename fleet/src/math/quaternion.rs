@@ -50,13 +50,13 @@ impl Quaternion {
 			}
 		}
 
-		return Quaternion {
+		Quaternion {
 			x: v_from.y * v_to.z - v_from.z * v_to.y,
 			y: v_from.z * v_to.x - v_from.x * v_to.z,
 			z: v_from.x * v_to.y - v_from.y * v_to.x,
 			w: r,
 		}
-		.normalized();
+		.normalized()
 	}
 
 	pub fn from_axis_angle(axis_angle: &AxisAngle) -> Quaternion {
@@ -80,7 +80,7 @@ impl Quaternion {
 
 	pub fn angle_to(&self, other: &Quaternion) -> f32 {
 		let dot = self.dot(other);
-		return f32::acos(f32::min(f32::abs(dot), 1.0)) * 2.0;
+		f32::acos(f32::min(f32::abs(dot), 1.0)) * 2.0
 	}
 
 	pub fn invert(&self) -> Quaternion {
@@ -119,7 +119,7 @@ impl Quaternion {
 		let angle = 2.0 * quat.w.acos();
 		let den = (1.0 - quat.w * quat.w).sqrt();
 
-		return if den > f32::EPSILON {
+		if den > f32::EPSILON {
 			AxisAngle {
 				axis: Vector3 {
 					x: quat.x / den,
@@ -130,13 +130,13 @@ impl Quaternion {
 			}
 		} else {
 			AxisAngle { axis: Vector3 { x: 1.0, y: 0.0, z: 0.0 }, angle }
-		};
+		}
 	}
 }
 
 impl Clone for Quaternion {
 	fn clone(&self) -> Quaternion {
-		Quaternion { x: self.x, y: self.y, z: self.z, w: self.w }
+		*self
 	}
 }
 
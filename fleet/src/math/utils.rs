@@ -1,6 +1,4 @@
-use std::time::SystemTime;
-
-use protologic_core::debugging::{debug_line_create, DebugShapeHandle};
+use crate::hardware::traits::{DebugShape, Hardware};
 
 use super::vector3::Vector3;
 
@@ -10,19 +8,16 @@ pub fn deg(radians: f32) -> f32 {
 pub fn rad(degrees: f32) -> f32 {
 	degrees * std::f32::consts::PI / 180.0
 }
-pub fn now() -> f32 {
-	SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs_f32()
-}
 pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
 	a + (b - a) * t
 }
 
-pub fn debug_line_white(a: Vector3, b: Vector3) -> DebugShapeHandle {
-	debug_line(a, b, 1.0, 1.0, 1.0)
+pub fn debug_line_white(hw: &dyn Hardware, a: Vector3, b: Vector3) -> DebugShape {
+	debug_line(hw, a, b, 1.0, 1.0, 1.0)
 }
 
-pub fn debug_line(a: Vector3, b: Vector3, cr: f32, cg: f32, cb: f32) -> DebugShapeHandle {
-	debug_line_create(a.x, a.y, a.z, b.x, b.y, b.z, cr, cg, cb)
+pub fn debug_line(hw: &dyn Hardware, a: Vector3, b: Vector3, cr: f32, cg: f32, cb: f32) -> DebugShape {
+	hw.debug_line_create(a.x, a.y, a.z, b.x, b.y, b.z, cr, cg, cb)
 }
 
 #[macro_export]
@@ -116,7 +116,7 @@ impl Vector3 {
 
 impl Clone for Vector3 {
 	fn clone(&self) -> Vector3 {
-		Vector3 { x: self.x, y: self.y, z: self.z }
+		*self
 	}
 }
 
@@ -1,22 +1,18 @@
-use std::{cell::RefCell, env};
+use std::rc::Rc;
 
-use protologic_core::{
-	missile_launcher::MissileWarheadType,
-	physics::vehicle_get_position,
-	warhead::{self_destruct, warhead_arm},
-};
+use protologic_core::missile_launcher::MissileWarheadType;
 
 use crate::{
-	controllers::{
-		flight_controller::*,
-		radar_controller::{set_radar_mode, RadarMode},
-	},
+	clock::TICKS_PER_SECOND,
+	controllers::{flight_controller::*, radar_controller::RadarMode},
 	datalink::{
-		datalink::{datalink_disconnect, get_dl_track, get_ship_pos_from_iff, get_ship_tracks, get_tick, own_dl_id, send_message, DatalinkTrack},
+		datalink::DatalinkTrack,
 		messages::{assign_attack_target::AssignAttackTarget, intercept_task_assign::InterceptTaskAssign, message::Message, ready_attack_time::ReadyAttackTime},
 	},
+	fleet_context::FleetContext,
 	get, get_err,
-	math::{utils::now, vector3::Vector3},
+	hardware::traits::Hardware,
+	math::vector3::Vector3,
 	updatable_debug::UpdatableDebugLine,
 };
 
@@ -37,6 +33,8 @@ const RING_RANGES: [f32; 15] = [
 ];
 
 pub struct MissileControlSystem {
+	hw: Rc<dyn Hardware>,
+
 	pub target_id: u16,
 	attack_time: u32,
 
@@ -52,12 +50,10 @@ pub struct MissileControlSystem {
 	warhead_type: MissileWarheadType,
 	intercept_ring: u8,
 	intercept_target_line: UpdatableDebugLine,
-
-	has_started: bool,
 }
 
 impl MissileControlSystem {
-	fn new() -> MissileControlSystem {
+	pub fn new(hw: Rc<dyn Hardware>) -> MissileControlSystem {
 		MissileControlSystem {
 			target_id: u16::MAX,
 			attack_time: 0,
@@ -70,15 +66,15 @@ impl MissileControlSystem {
 			allow_retarget: false,
 			fallback_target: Vector3::zero(),
 			warhead_type: MissileWarheadType::Nuclear,
-			intercept_target_line: UpdatableDebugLine::new(),
+			intercept_target_line: UpdatableDebugLine::new(hw.clone()),
 
 			intercept_ring: 0,
-			has_started: false,
+			hw,
 		}
 	}
 
-	fn init(&mut self) {
-		let warhead_type = env::vars().find(|(k, _)| k == "WarheadType").unwrap().1;
+	pub fn init(&mut self) {
+		let warhead_type = self.hw.vehicle_env("WarheadType").unwrap();
 		let warhead = match warhead_type.as_str() {
 			"Nuclear" => MissileWarheadType::Nuclear,
 			"Flak" => MissileWarheadType::Flak,
@@ -88,62 +84,71 @@ impl MissileControlSystem {
 		};
 
 		self.warhead_type = warhead;
-
-		self.has_started = true;
 	}
 
-	fn after_dl_init(&mut self) {
+	fn after_dl_init(&mut self, ctx: &mut FleetContext) {
 		match self.warhead_type {
 			MissileWarheadType::Nuclear => {
 				self.wait_point = self.produce_wait_point();
-				if vehicle_get_position().2 > 0.0 {
+				if self.hw.vehicle_get_position().2 > 0.0 {
 					self.fallback_target = Vector3::new(0.0, 0.0, -5000.0);
 				} else {
 					self.fallback_target = Vector3::new(0.0, 0.0, 5000.0);
 				}
-				self.setup_first_strike_mission();
+				self.setup_first_strike_mission(ctx);
 				self.phase = MissilePhase::WaitingForAttackTime;
 			}
 			MissileWarheadType::Flak => {
 				// Let ship know we're flak missile
-				let message = InterceptTaskAssign::new(0, 0, own_dl_id(), 0);
-				send_message(Message::InterceptTaskAssign(message));
+				let message = InterceptTaskAssign::new(0, 0, ctx.datalink.id(), 0);
+				ctx.datalink.send_message(Message::InterceptTaskAssign(message));
 
-				let cur_pos: Vector3 = vehicle_get_position().into();
+				let cur_pos: Vector3 = self.hw.vehicle_get_position().into();
 				self.wait_point = Vector3::random_direction() * 100.0;
-				flight_set_target_point(cur_pos);
-				flight_set_target_point_velocity(Vector3::zero());
-				set_flight_mode(GuidanceMode::StopAtPoint);
+				ctx.flight.target_point = cur_pos;
+				ctx.flight.target_point_velocity = Vector3::zero();
+				ctx.flight.guidance_mode = GuidanceMode::StopAtPoint;
 				self.phase = MissilePhase::InterceptWait;
 			}
 			_ => {}
 		}
 	}
 
-	fn setup_first_strike_mission(&mut self) {
+	fn setup_first_strike_mission(&mut self, ctx: &mut FleetContext) {
 		self.wait_point = self.produce_wait_point_around(self.fallback_target);
 		self.allow_retarget = true;
 
-		flight_set_target_point(self.wait_point);
-		flight_set_target_point_velocity(Vector3::zero());
-		set_flight_mode(GuidanceMode::StopAtPoint);
+		ctx.flight.target_point = self.wait_point;
+		ctx.flight.target_point_velocity = Vector3::zero();
+		ctx.flight.guidance_mode = GuidanceMode::StopAtPoint;
 
 		// Declare time ready to attack
-		let seconds_to_point = (self.wait_point - vehicle_get_position().into()).length() / (get_max_flight_target_speed() * 1.0);
-		self.attack_time = get_tick() + (seconds_to_point * 100.0).round() as u32;
+		let seconds_to_point = (self.wait_point - self.hw.vehicle_get_position().into()).length() / (ctx.flight.max_target_speed * 1.0);
+		self.attack_time = ctx.datalink.tick + (seconds_to_point * TICKS_PER_SECOND as f32).round() as u32;
 
 		println!("Attack time is set to {}", self.attack_time);
 		// Send rat to datalink
 		let rat = ReadyAttackTime::new(self.attack_time);
-		send_message(Message::ReadyAttackTime(rat));
+		ctx.datalink.send_message(Message::ReadyAttackTime(rat));
 	}
 
-	fn setup_attack_mission(&mut self, aat: AssignAttackTarget) {
-		if self.phase != MissilePhase::WaitingForTarget || self.target_id == aat.target_id {
+	fn setup_attack_mission(&mut self, aat: AssignAttackTarget, ctx: &mut FleetContext) {
+		if self.target_id == aat.target_id {
 			return;
 		}
 
-		let opt_target = get_dl_track(aat.target_id);
+		// A first strike already on its way only swaps what it goes for. The order can get here before the track
+		// does, until then it keeps going for whatever resolve_target_params falls back on
+		if self.phase != MissilePhase::WaitingForTarget {
+			let on_strike = matches!(self.phase, MissilePhase::WaitingForAttackTime | MissilePhase::Attack);
+			if self.allow_retarget && on_strike {
+				println!("Strike retargeted to {}", aat.target_id);
+				self.target_id = aat.target_id;
+			}
+			return;
+		}
+
+		let opt_target = ctx.datalink.get_track(aat.target_id).copied();
 		if opt_target.is_none() || opt_target.unwrap().position.length_sq() == 0.0 {
 			return;
 		}
@@ -159,17 +164,17 @@ impl MissileControlSystem {
 		self.phase = MissilePhase::WaitingForAttackTime;
 		self.wait_point = self.produce_wait_point_around(target.position);
 
-		flight_set_target_point(self.wait_point);
-		flight_set_target_point_velocity(Vector3::zero());
-		set_flight_mode(GuidanceMode::StopAtPoint);
+		ctx.flight.target_point = self.wait_point;
+		ctx.flight.target_point_velocity = Vector3::zero();
+		ctx.flight.guidance_mode = GuidanceMode::StopAtPoint;
 
 		// Declare time ready to attack
-		let seconds_to_point = (self.wait_point - vehicle_get_position().into()).length() / (get_max_flight_target_speed() * 0.75);
-		self.attack_time = get_tick() + (seconds_to_point * 100.0).round() as u32;
+		let seconds_to_point = (self.wait_point - self.hw.vehicle_get_position().into()).length() / (ctx.flight.max_target_speed * 0.75);
+		self.attack_time = ctx.datalink.tick + (seconds_to_point * TICKS_PER_SECOND as f32).round() as u32;
 
 		// Send rat to datalink
 		let rat = ReadyAttackTime::new(self.attack_time);
-		send_message(Message::ReadyAttackTime(rat));
+		ctx.datalink.send_message(Message::ReadyAttackTime(rat));
 	}
 
 	fn update_ready_attack_time(&mut self, rat: ReadyAttackTime) {
@@ -183,88 +188,88 @@ impl MissileControlSystem {
 			wp = pos + Vector3::random_direction() * 4000.0;
 		}
 
-		return wp;
+		wp
 	}
 
 	fn produce_wait_point(&self) -> Vector3 {
-		return Vector3::random_direction() * 3000.0;
+		Vector3::random_direction() * 3000.0
 	}
 
-	fn update(&mut self) {
-		if !self.has_started {
-			return;
-		}
-
+	pub fn update(&mut self, ctx: &mut FleetContext) {
 		// println!("Current phase: {:?}", self.phase);
 		match self.phase {
-			MissilePhase::None => self.after_dl_init(),
+			MissilePhase::None => self.after_dl_init(ctx),
 			MissilePhase::WaitingForTarget => self.time_loitering += 1,
 			MissilePhase::WaitingForAttackTime => {
-				if self.attack_time > 0 && get_tick() > self.attack_time {
-					println!("Switching to attack phase. Current tick {}, RAT: {}", get_tick(), self.attack_time);
+				if self.attack_time > 0 && ctx.datalink.tick > self.attack_time {
+					println!("Switching to attack phase. Current tick {}, RAT: {}", ctx.datalink.tick, self.attack_time);
 					self.phase = MissilePhase::Attack;
 				}
 			}
 			MissilePhase::Attack => {
-				let (target_position, target_velocity) = self.resolve_target_params();
+				let (target_position, target_velocity) = self.resolve_target_params(ctx);
 
 				if target_position.length_sq() == 0.0 {
 					println!("After target selection logic, target position is still zero!");
-					set_flight_mode(GuidanceMode::Drift);
+					ctx.flight.guidance_mode = GuidanceMode::Drift;
 					return;
 				}
 
-				set_flight_mode(GuidanceMode::Impact);
+				ctx.flight.guidance_mode = GuidanceMode::Impact;
 
-				let current_target_point = current_flight_target_point();
-				if (current_target_point - vehicle_get_position().into()).length_sq() > 1.0 {
-					flight_set_target_point(target_position);
-					flight_set_target_point_velocity(target_velocity);
+				let current_target_point = ctx.flight.target_point;
+				if (current_target_point - self.hw.vehicle_get_position().into()).length_sq() > 1.0 {
+					ctx.flight.target_point = target_position;
+					ctx.flight.target_point_velocity = target_velocity;
 				}
 
-				self.run_warhead_logic(target_position);
+				self.run_warhead_logic(target_position, ctx);
 			}
 			MissilePhase::InterceptWait => {
 				// Update hold point based off where ship is
-				let ship_pos = get!(get_ship_pos_from_iff());
+				let ship_pos = get!(ctx.datalink.get_ship_pos_from_iff());
 				let wait_point = ship_pos + self.wait_point;
-				flight_set_target_point(wait_point);
-				flight_set_target_point_velocity(Vector3::zero());
+				ctx.flight.target_point = wait_point;
+				ctx.flight.target_point_velocity = Vector3::zero();
 			}
 			MissilePhase::InterceptAttack => {
-				let ship_pos = get_err!(get_ship_pos_from_iff(), "No ship position found via IFF!");
-				let target = get_err!(get_dl_track(self.target_id), "Unable to resolve target {} for intercept attack", self.target_id);
-				let target_pos = target.position + target.velocity * (now() - target.last_update_timestamp);
+				let ship_pos = get_err!(ctx.datalink.get_ship_pos_from_iff(), "No ship position found via IFF!");
+				let target = get_err!(
+					ctx.datalink.get_track(self.target_id).copied(),
+					"Unable to resolve target {} for intercept attack",
+					self.target_id
+				);
+				let target_pos = target.get_current_position(ctx.clock.now());
 
 				let iat_dist = RING_RANGES[self.intercept_ring as usize];
 				let iat_dir = (target_pos - ship_pos).normalized();
 				let iat_point = ship_pos + iat_dir * iat_dist;
-				flight_set_target_point(iat_point);
-				set_flight_mode(GuidanceMode::FastStopAtPoint);
+				ctx.flight.target_point = iat_point;
+				ctx.flight.guidance_mode = GuidanceMode::FastStopAtPoint;
 
-				self.run_warhead_logic(target_pos);
+				self.run_warhead_logic(target_pos, ctx);
 
-				self.intercept_target_line.set_a(vehicle_get_position().into());
+				self.intercept_target_line.set_a(self.hw.vehicle_get_position().into());
 				self.intercept_target_line.set_b(target_pos);
-				self.intercept_target_line.set_color(255.0 / 255.0, 242.0 / 255.0, 0.0);
+				self.intercept_target_line.set_color(1.0, 242.0 / 255.0, 0.0);
 			}
 		}
 	}
 
-	fn resolve_target_params(&self) -> (Vector3, Vector3) {
-		let maybe_target = get_dl_track(self.target_id);
+	fn resolve_target_params(&self, ctx: &FleetContext) -> (Vector3, Vector3) {
+		let maybe_target = ctx.datalink.get_track(self.target_id).copied();
 		if let Some(target) = maybe_target {
 			if target.position.length_sq() != 0.0 {
-				let cur_target_pos = target.position + target.velocity * (now() - target.last_update_timestamp);
+				let cur_target_pos = target.get_current_position(ctx.clock.now());
 				return (cur_target_pos, target.velocity);
 			}
 		}
 
 		// No target, or target has no position
 		if self.allow_retarget {
-			let alt_target = self.find_ship_target_alternative();
+			let alt_target = self.find_ship_target_alternative(ctx);
 			if let Some(t) = alt_target {
-				let cur_target_pos = t.position + t.velocity * (now() - t.last_update_timestamp);
+				let cur_target_pos = t.get_current_position(ctx.clock.now());
 				return (cur_target_pos, t.velocity);
 			}
 		}
@@ -278,33 +283,28 @@ impl MissileControlSystem {
 		(Vector3::zero(), Vector3::zero())
 	}
 
-	fn find_ship_target_alternative(&self) -> Option<DatalinkTrack> {
-		let tracks = get_ship_tracks();
+	fn find_ship_target_alternative(&self, ctx: &FleetContext) -> Option<DatalinkTrack> {
+		let tracks = ctx.datalink.get_ship_tracks();
 		let valid_ship_track = tracks.iter().find(|t| !t.is_allied);
-		return valid_ship_track.copied();
+		valid_ship_track.copied()
 	}
 
-	fn run_warhead_logic(&mut self, target_position: Vector3) {
-		let distance_to_target = (target_position - vehicle_get_position().into()).length();
+	fn run_warhead_logic(&mut self, target_position: Vector3, ctx: &mut FleetContext) {
+		let distance_to_target = (target_position - self.hw.vehicle_get_position().into()).length();
 		if !self.armed && distance_to_target < self.last_distance_to_target {
 			self.armed = true;
-			warhead_arm();
+			self.hw.warhead_arm();
 			println!("Armed!");
 		}
 
 		match self.warhead_type {
-			MissileWarheadType::Nuclear => {
-				if self.armed && distance_to_target > self.last_distance_to_target && distance_to_target < 250.0 {
-					println!("Nuclear warhead detonating at distance {}", distance_to_target);
-					self_destruct();
-				}
+			MissileWarheadType::Nuclear if self.armed && distance_to_target > self.last_distance_to_target && distance_to_target < 250.0 => {
+				println!("Nuclear warhead detonating at distance {}", distance_to_target);
+				self.hw.self_destruct();
 			}
-			MissileWarheadType::Flak => {
-				if self.armed && distance_to_target < 250.0 {
-					println!("Flack warhead detonating at distance {} (proximity)", distance_to_target);
-					self_destruct();
-				}
-
+			MissileWarheadType::Flak if self.armed && distance_to_target < 250.0 => {
+				println!("Flack warhead detonating at distance {} (proximity)", distance_to_target);
+				self.hw.self_destruct();
 				// if self.armed && distance_to_target > self.last_distance_to_target && distance_to_target < 500.0 {
 				// 	println!("Flack warhead detonating at distance {} (closure)", distance_to_target);
 				// 	self_destruct();
@@ -314,19 +314,19 @@ impl MissileControlSystem {
 		}
 
 		if self.armed && distance_to_target < 100.0 {
-			datalink_disconnect();
+			ctx.datalink.disconnect();
 		}
 
 		self.last_distance_to_target = distance_to_target;
 	}
 
-	fn handle_intercept_task(&mut self, task: InterceptTaskAssign) {
-		if self.phase != MissilePhase::InterceptWait || task.contact_id == 0 || task.interceptor_id != own_dl_id() {
+	fn handle_intercept_task(&mut self, task: InterceptTaskAssign, ctx: &mut FleetContext) {
+		if self.phase != MissilePhase::InterceptWait || task.contact_id == 0 || task.interceptor_id != ctx.datalink.id() {
 			return;
 		}
 		println!(
 			"Missile {} starting intercept task against {} at ring {}. Contact Id: {}",
-			own_dl_id(),
+			ctx.datalink.id(),
 			task.target_id,
 			task.ring,
 			task.contact_id
@@ -335,32 +335,16 @@ impl MissileControlSystem {
 		self.target_id = task.target_id;
 		self.allow_retarget = false;
 		self.intercept_ring = task.ring;
-		set_radar_mode(RadarMode::STT(task.contact_id));
+		ctx.radar.mode = RadarMode::STT(task.contact_id);
 		self.phase = MissilePhase::InterceptAttack;
 	}
 
-	fn handle_dl_message(&mut self, message: Message) {
+	pub fn handle_dl_message(&mut self, message: Message, ctx: &mut FleetContext) {
 		match message {
 			Message::ReadyAttackTime(rat) => self.update_ready_attack_time(rat),
-			Message::AssignAttackTarget(aat) => self.setup_attack_mission(aat),
-			Message::InterceptTaskAssign(task) => self.handle_intercept_task(task),
+			Message::AssignAttackTarget(aat) => self.setup_attack_mission(aat, ctx),
+			Message::InterceptTaskAssign(task) => self.handle_intercept_task(task, ctx),
 			_ => {}
 		}
 	}
 }
-
-thread_local! {
-	static MCS: RefCell<MissileControlSystem> = RefCell::new(MissileControlSystem::new());
-}
-
-pub fn init_mcs() {
-	MCS.with(|f| f.borrow_mut().init());
-}
-
-pub fn mcs_handle_dl_message(message: Message) {
-	MCS.with(|f| f.borrow_mut().handle_dl_message(message));
-}
-
-pub fn update_mcs() {
-	MCS.with(|f| f.borrow_mut().update());
-}
@@ -1,301 +1,241 @@
-use crate::math::vector3::Vector3;
+use std::f32::consts::PI;
 
+use crate::math::{utils::rad, vector3::Vector3};
+
+const GOLDEN_ANGLE: f32 = 2.399_963; // PI * (3 - sqrt(5)), spreads Fibonacci points evenly
+const COVERAGE_OVERLAP: f32 = 2.0; // Fibonacci points are less regular than hexagonal packing's 1.21, this closes the gaps between them
+const EDGE_SPREAD: f32 = 0.5; // Points go this many beam half angles past the sector's edge, so the rim is covered too
+
+// Where the radar searches
+#[derive(Clone, Copy, Debug)]
+pub enum ScanSector {
+	Sphere,
+	Hemisphere(Vector3), // The half facing the direction
+	Cone(Vector3, f32),  // Around the direction, half angle in degrees
+}
+
+impl ScanSector {
+	// A zero direction has nowhere to face, it gets the same axis as the sphere instead of NaNs
+	fn axis(self) -> Vector3 {
+		match self {
+			ScanSector::Hemisphere(axis) | ScanSector::Cone(axis, _) if axis.length_sq() > 0.0 => axis.normalized(),
+			_ => Vector3::new(0.0, 1.0, 0.0),
+		}
+	}
+
+	// Radians
+	fn half_angle(self) -> f32 {
+		match self {
+			ScanSector::Sphere => PI,
+			ScanSector::Hemisphere(_) => PI / 2.0,
+			ScanSector::Cone(_, half_angle) => rad(half_angle.clamp(0.0, 180.0)),
+		}
+	}
+}
+
+// Part of the sector looked at more often than the rest, e.g. where the enemy is
+#[derive(Clone, Copy, Debug)]
+pub struct RevisitPriority {
+	pub direction: Vector3,
+	pub half_angle: f32, // Degrees
+	pub rate: u32,       // Looks per cycle in there, everywhere else gets one
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ScanPatternSpec {
+	pub sector: ScanSector,
+	pub beam_width: f32, // Full angle of the beam, degrees
+	pub priority: Option<RevisitPriority>,
+}
+
+impl ScanPatternSpec {
+	pub fn new(sector: ScanSector, beam_width: f32, priority: Option<RevisitPriority>) -> ScanPatternSpec {
+		ScanPatternSpec { sector, beam_width, priority }
+	}
+}
+
+// Beam directions covering a sector, with just enough of them that neighbouring beams overlap. Directions
+// are where the beam looks, not the (own - target) convention the radar is pointed with
 pub struct RadarScanPattern {
+	spec: ScanPatternSpec,
 	points: Vec<Vector3>,
-	pub size: usize,
+
+	// Stride scheduling, each look at a point pushes it back by 1 / its rate
+	strides: Vec<f32>,
+	passes: Vec<f32>,
 }
 
 impl RadarScanPattern {
-	pub fn new() -> RadarScanPattern {
-		let points = vec![
-			Vector3::new(0.0, 1.0, 0.0),
-			Vector3::new(
-				-0.14746625201921928,
-				0.9797979797979798,
-				-0.1350911666216622,
-			),
-			Vector3::new(0.02459998867216236, 0.9595959595959596, 0.2803041827808479),
-			Vector3::new(
-				0.20859696704755018,
-				0.9393939393939394,
-				-0.27207780499059253,
-			),
-			Vector3::new(-0.38778989211836534, 0.9191919191919192, 0.0685945716737325),
-			Vector3::new(0.36953896763626587, 0.898989898989899, 0.23507044244748004),
-			Vector3::new(
-				-0.12388644490434518,
-				0.8787878787878788,
-				-0.4608513999810168,
-			),
-			Vector3::new(
-				-0.23629311200054343,
-				0.8585858585858586,
-				0.45496800838903245,
-			),
-			Vector3::new(0.5120055392429732, 0.8383838383838383, -0.1869836017443622),
-			Vector3::new(
-				-0.5314613277182726,
-				0.8181818181818181,
-				-0.21937951029352104,
-			),
-			Vector3::new(0.25544528342416906, 0.797979797979798, 0.5458717332784936),
-			Vector3::new(0.1881116889321608, 0.7777777777777778, -0.5997297065196551),
-			Vector3::new(-0.5647642372728916, 0.7575757575757576, 0.3272924194525227),
-			Vector3::new(0.6597298249772899, 0.7373737373737373, 0.1450397513336291),
-			Vector3::new(-0.4008056847193881, 0.7171717171717171, -0.570104842275167),
-			Vector3::new(-0.09215509016145314, 0.696969696969697, 0.7111544704656669),
-			Vector3::new(0.5629320310676096, 0.6767676767676767, -0.474439711744893),
-			Vector3::new(
-				-0.7536247719876671,
-				0.6565656565656566,
-				-0.03116475036070447,
-			),
-			Vector3::new(0.5467833660637482, 0.6363636363636364, 0.5441224796955615),
-			Vector3::new(
-				-0.036381294874359316,
-				0.6161616161616161,
-				-0.7867790440475458,
-			),
-			Vector3::new(-0.5144986829326771, 0.595959595959596, 0.6165413735056355),
-			Vector3::new(0.8103186275230045, 0.5757575757575757, -0.10902722525586747),
-			Vector3::new(
-				-0.6825268133340903,
-				0.5555555555555556,
-				-0.47488437937183176,
-			),
-			Vector3::new(0.18538012732824197, 0.5353535353535354, 0.8240332521059061),
-			Vector3::new(0.42613329474450623, 0.5151515151515151, -0.7436594190537024),
-			Vector3::new(-0.8278154695967747, 0.4949494949494949, 0.2640957132281993),
-			Vector3::new(0.7989669998181989, 0.4747474747474747, 0.3691430189268139),
-			Vector3::new(-0.3438736948900274, 0.4545454545454546, -0.8216686142933455),
-			Vector3::new(-0.304859992431343, 0.43434343434343436, 0.8475884414369464),
-			Vector3::new(0.805710536469491, 0.41414141414141414, -0.4234588770057782),
-			Vector3::new(
-				-0.8887773463851543,
-				0.3939393939393939,
-				-0.23427885618473235,
-			),
-			Vector3::new(0.5016456981916894, 0.3737373737373737, 0.7801743195963727),
-			Vector3::new(0.1584380564140277, 0.3535353535353535, -0.92190570888805),
-			Vector3::new(-0.7454066778203379, 0.33333333333333337, 0.5772848287888189),
-			Vector3::new(0.9464672217388473, 0.31313131313131315, 0.07841287464828095),
-			Vector3::new(
-				-0.6492909240678554,
-				0.29292929292929293,
-				-0.7018644636018089,
-			),
-			Vector3::new(0.004693348436707129, 0.2727272727272727, 0.962079938046312),
-			Vector3::new(0.6501095730811262, 0.2525252525252525, -0.7166509190849708),
-			Vector3::new(-0.9684880955101889, 0.23232323232323238, 0.0897592590094909),
-			Vector3::new(0.7784324280224244, 0.21212121212121215, 0.5908024596862649),
-			Vector3::new(
-				-0.17565640922421086,
-				0.19191919191919193,
-				-0.9655629703295072,
-			),
-			Vector3::new(-0.5246944250120845, 0.1717171717171717, 0.8337919244623885),
-			Vector3::new(0.9533026721538091, 0.1515151515151515, -0.26126073973283376),
-			Vector3::new(
-				-0.8819824557590287,
-				0.13131313131313127,
-				-0.45261883442695305,
-			),
-			Vector3::new(0.3454643719150287, 0.11111111111111116, 0.9318308262367203),
-			Vector3::new(
-				0.37548139463481467,
-				0.09090909090909094,
-				-0.9223606992241036,
-			),
-			Vector3::new(
-				-0.9013941175375145,
-				0.07070707070707072,
-				0.42718749398922107,
-			),
-			Vector3::new(0.9543932088315659, 0.0505050505050505, 0.29424962669418897),
-			Vector3::new(
-				-0.5053901519770824,
-				0.030303030303030276,
-				-0.8623586960418704,
-			),
-			Vector3::new(
-				-0.20994166796485317,
-				0.010101010101010055,
-				0.9776617337541009,
-			),
-			Vector3::new(
-				0.8152054643909104,
-				-0.010101010101010166,
-				-0.579083776687052,
-			),
-			Vector3::new(
-				-0.9918675174806278,
-				-0.030303030303030276,
-				-0.12361453847048105,
-			),
-			Vector3::new(0.6473422941630368, -0.05050505050505061, 0.7605242889357439),
-			Vector3::new(
-				0.03635201027440332,
-				-0.07070707070707072,
-				-0.9968345105889116,
-			),
-			Vector3::new(-0.6990071942575047, -0.09090909090909083, 0.70931268109793),
-			Vector3::new(
-				0.9925114528466928,
-				-0.11111111111111116,
-				-0.05074777784101695,
-			),
-			Vector3::new(
-				-0.7642248396034683,
-				-0.13131313131313127,
-				-0.6314406195975941,
-			),
-			Vector3::new(0.13658481401054726, -0.1515151515151516, 0.9789728021978163),
-			Vector3::new(0.558696834130153, -0.1717171717171717, -0.8114006781303549),
-			Vector3::new(
-				-0.9564185101904275,
-				-0.19191919191919182,
-				0.22006966428430655,
-			),
-			Vector3::new(0.8502622976531694, -0.21212121212121215, 0.4817246273108536),
-			Vector3::new(
-				-0.3001357245994418,
-				-0.23232323232323226,
-				-0.9251726663396695,
-			),
-			Vector3::new(
-				-0.40153941165454154,
-				-0.2525252525252526,
-				0.8803391947000782,
-			),
-			Vector3::new(
-				0.8856810587479291,
-				-0.2727272727272727,
-				-0.37575111029229324,
-			),
-			Vector3::new(
-				-0.9012744054991981,
-				-0.29292929292929304,
-				-0.3192128997017686,
-			),
-			Vector3::new(0.4459300097862139, -0.31313131313131315, 0.8385077263261959),
-			Vector3::new(0.235862546901616, -0.33333333333333326, -0.9128295283665904),
-			Vector3::new(-0.784330611081401, -0.3535353535353536, 0.50974331415067),
-			Vector3::new(0.9148885742302129, -0.3737373737373737, 0.15264099126675884),
-			Vector3::new(
-				-0.5663283107791085,
-				-0.39393939393939403,
-				-0.7239364601349655,
-			),
-			Vector3::new(
-				-0.07072577480976129,
-				-0.41414141414141414,
-				0.9074606073381415,
-			),
-			Vector3::new(
-				0.6582151812328657,
-				-0.43434343434343425,
-				-0.6148971915998125,
-			),
-			Vector3::new(
-				-0.8906811486863095,
-				-0.4545454545454546,
-				0.008690289230071762,
-			),
-			Vector3::new(0.6547440638983898, -0.4747474747474747, 0.5881539305409061),
-			Vector3::new(
-				-0.08440761859904097,
-				-0.49494949494949503,
-				-0.8648123214730921,
-			),
-			Vector3::new(-0.514831321346626, -0.5151515151515151, 0.6852500470613523),
-			Vector3::new(
-				0.8302417168632598,
-				-0.5353535353535352,
-				-0.15522655624736997,
-			),
-			Vector3::new(
-				-0.7058858583876334,
-				-0.5555555555555556,
-				-0.43941231163875244,
-			),
-			Vector3::new(0.2199512587964219, -0.5757575757575757, 0.7874799411487436),
-			Vector3::new(0.36314460329981746, -0.595959595959596, -0.7162109724640451),
-			Vector3::new(
-				-0.7371567541746256,
-				-0.6161616161616161,
-				0.27738922571692637,
-			),
-			Vector3::new(0.7158678547352005, -0.6363636363636365, 0.28735785507076544),
-			Vector3::new(
-				-0.3263439050896623,
-				-0.6565656565656566,
-				-0.6800155838135901,
-			),
-			Vector3::new(
-				-0.21346785876708288,
-				-0.6767676767676767,
-				0.7045686516982416,
-			),
-			Vector3::new(0.6169059083365986, -0.696969696969697, -0.3655958721941003),
-			Vector3::new(-0.6820694567402541, -0.7171717171717171, -0.142989455104302),
-			Vector3::new(0.39386410565593816, -0.7373737373737375, 0.5487723004191579),
-			Vector3::new(
-				0.07756532525221611,
-				-0.7575757575757576,
-				-0.6481223587039989,
-			),
-			Vector3::new(
-				-0.47663717047446663,
-				-0.7777777777777777,
-				0.40973007714488846,
-			),
-			Vector3::new(0.6023838283936482, -0.797979797979798, 0.01902538582874433),
-			Vector3::new(
-				-0.41148573055743193,
-				-0.8181818181818181,
-				-0.40156942854792976,
-			),
-			Vector3::new(
-				0.030489215000372383,
-				-0.8383838383838385,
-				0.5442269262958632,
-			),
-			Vector3::new(
-				0.32461627083358946,
-				-0.8585858585858586,
-				-0.3968054940981511,
-			),
-			Vector3::new(
-				-0.47230744988700507,
-				-0.8787878787878789,
-				0.06824614917146947,
-			),
-			Vector3::new(0.3619341490546988, -0.898989898989899, 0.24661880151801535),
-			Vector3::new(-0.09017831280032454, -0.9191919191919191, -0.38334591114653),
-			Vector3::new(
-				-0.16754301826616347,
-				-0.9393939393939394,
-				0.29911262704907604,
-			),
-			Vector3::new(
-				0.26722305411035563,
-				-0.9595959595959596,
-				-0.0881330453294532,
-			),
-			Vector3::new(
-				-0.18235869564294396,
-				-0.9797979797979799,
-				-0.08210496274404802,
-			),
-			Vector3::new(0.0, -1.0, 0.0),
-		];
-
-		let size = points.len();
-
-		RadarScanPattern { points, size }
+	pub fn new(spec: ScanPatternSpec) -> RadarScanPattern {
+		let points = fibonacci_cap(spec.sector.axis(), spec.sector.half_angle(), rad(spec.beam_width.clamp(0.1, 180.0)) / 2.0);
+		let strides = points
+			.iter()
+			.map(|point| match spec.priority {
+				Some(priority) if point.dot(&priority.direction.normalized()) >= rad(priority.half_angle).cos() => 1.0 / priority.rate.max(1) as f32,
+				_ => 1.0,
+			})
+			.collect();
+		let passes = vec![0.0; points.len()];
+
+		RadarScanPattern { spec, points, strides, passes }
 	}
 
-	pub fn get_point(&self, index: usize) -> Vector3 {
+	// The 40 degree search over everything we had before anything is known
+	pub fn full_sphere() -> RadarScanPattern {
+		RadarScanPattern::new(ScanPatternSpec::new(ScanSector::Sphere, 40.0, None))
+	}
+
+	// Next direction to look in. The point furthest behind goes next, ties in pattern order
+	pub fn next_direction(&mut self) -> Vector3 {
+		let mut index = 0;
+		for i in 1..self.points.len() {
+			if self.passes[i] < self.passes[index] {
+				index = i;
+			}
+		}
+
+		self.passes[index] += self.strides[index];
 		self.points[index]
 	}
+
+	pub fn beam_width(&self) -> f32 {
+		self.spec.beam_width
+	}
+
+	pub fn spec(&self) -> ScanPatternSpec {
+		self.spec
+	}
+
+	pub fn len(&self) -> usize {
+		self.points.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.points.is_empty()
+	}
+
+	// Looks it takes to get through the whole sector once, priority points included
+	pub fn cycle_length(&self) -> f32 {
+		self.strides.iter().map(|stride| 1.0 / stride).sum()
+	}
+}
+
+// Evenly spread points over the cap of `half_angle` around the axis, as many as it takes beams of
+// `beam_half_angle` to cover it
+fn fibonacci_cap(axis: Vector3, half_angle: f32, beam_half_angle: f32) -> Vec<Vector3> {
+	if beam_half_angle >= half_angle {
+		return vec![axis];
+	}
+
+	let cap_area = 1.0 - (half_angle + beam_half_angle * EDGE_SPREAD).min(PI).cos();
+	let beam_area = 1.0 - beam_half_angle.cos();
+	let count = ((COVERAGE_OVERLAP * cap_area / beam_area).ceil() as usize).max(1);
+	if count == 1 {
+		return vec![axis];
+	}
+
+	// Any two directions square to the axis
+	let helper = if axis.x.abs() < 0.9 {
+		Vector3::new(1.0, 0.0, 0.0)
+	} else {
+		Vector3::new(0.0, 1.0, 0.0)
+	};
+	let u = axis.cross(&helper).normalized();
+	let v = axis.cross(&u);
+
+	(0..count)
+		.map(|i| {
+			// Equal steps in height are equal steps in area
+			let height = 1.0 - cap_area * (i as f32 + 0.5) / count as f32;
+			let radius = (1.0 - height * height).max(0.0).sqrt();
+			let angle = i as f32 * GOLDEN_ANGLE;
+			(u * (radius * angle.cos()) + v * (radius * angle.sin()) + axis * height).normalized()
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn angle_between(a: Vector3, b: Vector3) -> f32 {
+		a.dot(&b).clamp(-1.0, 1.0).acos()
+	}
+
+	// Directions spread over the whole sphere, far denser than any pattern
+	fn sample_directions() -> Vec<Vector3> {
+		fibonacci_cap(Vector3::new(0.0, 0.0, 1.0), PI, rad(2.0))
+	}
+
+	fn assert_covers(spec: ScanPatternSpec) {
+		let pattern = RadarScanPattern::new(spec);
+		let beam_half_angle = rad(spec.beam_width) / 2.0;
+		let axis = spec.sector.axis();
+		for direction in sample_directions().into_iter().filter(|d| angle_between(*d, axis) <= spec.sector.half_angle()) {
+			let nearest = pattern.points.iter().map(|p| angle_between(*p, direction)).fold(PI, f32::min);
+			assert!(
+				nearest <= beam_half_angle,
+				"{:?} is {} degrees from the nearest beam in {:?}",
+				direction,
+				nearest.to_degrees(),
+				spec.sector
+			);
+		}
+	}
+
+	#[test]
+	fn every_direction_in_the_sector_is_inside_a_beam() {
+		assert_covers(ScanPatternSpec::new(ScanSector::Sphere, 40.0, None));
+		assert_covers(ScanPatternSpec::new(ScanSector::Hemisphere(Vector3::new(1.0, 0.0, 0.0)), 20.0, None));
+		assert_covers(ScanPatternSpec::new(ScanSector::Cone(Vector3::new(0.0, -1.0, 1.0), 30.0), 10.0, None));
+	}
+
+	#[test]
+	fn narrower_beams_need_more_points() {
+		let count = |beam_width: f32| RadarScanPattern::new(ScanPatternSpec::new(ScanSector::Sphere, beam_width, None)).len();
+
+		assert_eq!(count(180.0), 4);
+		assert_eq!(count(90.0), 14);
+		assert_eq!(count(40.0), 67);
+		// Halving the beam takes about four times the points
+		let ratio = count(10.0) as f32 / count(20.0) as f32;
+		assert!((3.8..4.2).contains(&ratio), "Halving the beam multiplied the points by {}", ratio);
+
+		let cone = ScanPatternSpec::new(ScanSector::Cone(Vector3::new(0.0, 0.0, 1.0), 5.0), 20.0, None);
+		assert_eq!(RadarScanPattern::new(cone).len(), 1);
+	}
+
+	#[test]
+	fn priority_points_are_looked_at_rate_times_a_cycle() {
+		let priority = RevisitPriority {
+			direction: Vector3::new(1.0, 0.0, 0.0),
+			half_angle: 30.0,
+			rate: 4,
+		};
+		let mut pattern = RadarScanPattern::new(ScanPatternSpec::new(ScanSector::Sphere, 20.0, Some(priority)));
+		let in_priority = |d: Vector3| angle_between(d, priority.direction) <= rad(priority.half_angle) + 1e-4;
+		let priority_points = pattern.points.iter().filter(|p| in_priority(**p)).count();
+		let other_points = pattern.len() - priority_points;
+		assert!(priority_points > 0);
+		assert_eq!(pattern.cycle_length(), (other_points + priority_points * 4) as f32);
+
+		let cycles = 3;
+		let looks = cycles * pattern.cycle_length() as usize;
+		let priority_looks = (0..looks).filter(|_| in_priority(pattern.next_direction())).count();
+		assert_eq!(priority_looks, cycles * priority_points * 4);
+	}
+
+	#[test]
+	fn zero_axis_does_not_give_nan_directions() {
+		for sector in [ScanSector::Hemisphere(Vector3::zero()), ScanSector::Cone(Vector3::zero(), 30.0)] {
+			let mut pattern = RadarScanPattern::new(ScanPatternSpec::new(sector, 20.0, None));
+			let direction = pattern.next_direction();
+			assert!(
+				!direction.x.is_nan() && !direction.y.is_nan() && !direction.z.is_nan(),
+				"{:?} looks at {:?}",
+				sector,
+				direction
+			);
+		}
+	}
 }
@@ -1,21 +1,30 @@
-use std::cell::RefCell;
+use std::rc::Rc;
 
 use protologic_core::{
-	missile_launcher::{
-		missilelauncher_configure, missilelauncher_get_enginetype, missilelauncher_get_reloadtime, missilelauncher_get_warheadtype, missilelauncher_trigger,
-		MissileEngineType, MissileWarheadType,
-	},
-	physics::vehicle_get_position,
+	missile_launcher::{MissileEngineType, MissileWarheadType},
 	radar::RadarTargetType,
 };
 
 use crate::{
-	controllers::{flight_controller::flight_set_target_point, radar_controller::get_radar_tracks, turret_controller::TurretController},
+	clock::Clock,
+	controllers::{
+		radar_controller::RadarTrack,
+		radar_scheduler::{DwellRequest, DwellRequester, DwellTarget},
+		turret_controller::TurretController,
+	},
 	datalink::{
-		datalink::{dl_crunch_id, dl_net_id, send_message},
-		messages::{intercept_task_assign::InterceptTaskAssign, message::Message},
+		datalink::dl_crunch_id,
+		messages::{assign_attack_target::AssignAttackTarget, intercept_task_assign::InterceptTaskAssign, message::Message},
+		reliable_channel::{Delivery, DeliveryStatus},
+		slot_allocator::LinkRole,
 	},
-	math::{utils::now, vector3::Vector3},
+	fleet_context::FleetContext,
+	get,
+	hardware::traits::Hardware,
+	math::{utils::rad, vector3::Vector3},
+	radar_scan_pattern::{RevisitPriority, ScanPatternSpec, ScanSector},
+	threat_evaluation::{Threat, ThreatEvaluator},
+	weapon_assignment::{Weapon, WeaponAssigner, WeaponAssignment},
 };
 
 #[derive(Clone, Copy, Debug)]
@@ -25,14 +34,42 @@ struct QueuedLaunch {
 }
 
 const MISSILE_LAUNCH_RATE: f32 = 1.0;
+
+// Once the enemy is found the radar searches the half of the sky facing them, and looks at them more often
+const ENEMY_SCAN_BEAM_WIDTH: f32 = 40.0;
+const ENEMY_PRIORITY_HALF_ANGLE: f32 = 30.0;
+const ENEMY_PRIORITY_RATE: u32 = 3;
+const SCAN_REAIM_ANGLE: f32 = 20.0; // How far the enemy has to move across the sky before the pattern follows
+
+// Radar looks asked for on top of the usual revisits. Seconds between looks, and priority over other tracks
+const TURRET_DWELL_INTERVAL: f32 = 0.1;
+const TURRET_DWELL_PRIORITY: f32 = 1.0;
+const INTERCEPT_DWELL_INTERVAL: f32 = 0.25; // Interceptors get our picture of the threat over the datalink
+const INTERCEPT_DWELL_PRIORITY: f32 = 0.5;
+
+const WEAPON_ASSIGNMENT_INTERVAL: f32 = 0.25; // Seconds between weapon assignment decisions
 struct InterceptTask {
 	contact_id: i64,
-	#[allow(dead_code)]
 	interceptor_id: u8,
 	ring: u8,
+
+	delivery: Delivery, // Of the reliable order
+	delivered: bool,
+}
+
+// Last target order sent to a strike missile
+struct StrikeOrder {
+	strike_id: u8,
+	contact_id: i64,
+
+	delivery: Delivery,
+	delivered: bool,
 }
 
 pub struct ShipControlSystem {
+	hw: Rc<dyn Hardware>,
+	clock: Rc<dyn Clock>,
+
 	pub last_shot_time: f32,
 	turrets: Vec<TurretController>,
 
@@ -41,32 +78,43 @@ pub struct ShipControlSystem {
 
 	interceptors: Vec<u8>,
 	intercept_tasks: Vec<InterceptTask>,
+	strike_orders: Vec<StrikeOrder>,
 
-	has_started: bool,
+	threats: ThreatEvaluator,
+	weapons: WeaponAssigner,
+	last_assignment_time: f32,
+	scan_direction: Option<Vector3>, // Toward the enemy, what the radar's search is centered on
 }
 
 impl ShipControlSystem {
-	fn new() -> ShipControlSystem {
+	pub fn new(hw: Rc<dyn Hardware>, clock: Rc<dyn Clock>) -> ShipControlSystem {
 		ShipControlSystem {
 			last_shot_time: 0.0,
 			turrets: vec![
-				TurretController::new(0),
-				TurretController::new(1),
-				TurretController::new(2),
-				TurretController::new(3),
+				TurretController::new(hw.clone(), clock.clone(), 0),
+				TurretController::new(hw.clone(), clock.clone(), 1),
+				TurretController::new(hw.clone(), clock.clone(), 2),
+				TurretController::new(hw.clone(), clock.clone(), 3),
 			],
 			queued_launch_cells: Vec::new(),
 			last_missile_launch_time: 0.0,
 
 			interceptors: Vec::new(),
 			intercept_tasks: Vec::new(),
+			strike_orders: Vec::new(),
 
-			has_started: false,
+			threats: ThreatEvaluator::new(),
+			weapons: WeaponAssigner::new(),
+			last_assignment_time: f32::MIN,
+			scan_direction: None,
+
+			hw,
+			clock,
 		}
 	}
 
-	fn init(&mut self) {
-		if vehicle_get_position().2 > 0.0 {
+	pub fn init(&mut self, ctx: &mut FleetContext) {
+		if self.hw.vehicle_get_position().2 > 0.0 {
 			for _ in 0..1 {
 				self.fire_missile(MissileWarheadType::Flak, MissileEngineType::HighThrust);
 			}
@@ -78,57 +126,24 @@ impl ShipControlSystem {
 
 		// self.fire_missile(0);
 		// set_flight_mode(GuidanceMode::StopAtPoint);
-		let side = vehicle_get_position().2.signum();
-		flight_set_target_point(Vector3::new(350.0, 0.0, 500.0 * side));
-
-		self.has_started = true;
+		let side = self.hw.vehicle_get_position().2.signum();
+		ctx.flight.target_point = Vector3::new(350.0, 0.0, 500.0 * side);
 	}
 
-	fn update(&mut self) {
-		if !self.has_started {
-			return;
-		}
-
+	pub fn update(&mut self, ctx: &mut FleetContext) {
 		// println!(
 		// 	"Free interceptors: {}, Intercept tasks: {}",
 		// 	self.interceptors.len(),
 		// 	self.intercept_tasks.len()
 		// );
 
-		if self.interceptors.len() > 0 {
-			let tracks = get_radar_tracks();
-			for track in tracks {
-				if track.is_allied // Don't shoot down our missiles
-					|| track.rc_type != RadarTargetType::Missile // Only shoot down missiles
-					|| self.intercept_tasks.iter().any(|f| f.contact_id == track.id)
-				// Don't shoot down the same missile twice
-				{
-					continue;
-				}
-				// let dist = (pos - track.get_current_position()).length();
-				// if dist > 1000.0 {
-				// 	continue;
-				// }
-
-				let mut next_free_ring = 0;
-				for i in 0..15 {
-					if !self.intercept_tasks.iter().any(|f| f.ring == i) {
-						next_free_ring = i;
-						break;
-					}
-				}
+		self.evaluate_threats(ctx);
+		self.assign_weapons(ctx);
 
-				let interceptor = self.interceptors.pop().unwrap();
-				let message = InterceptTaskAssign::new(dl_net_id(track.id).unwrap(), dl_crunch_id(track.id), interceptor, next_free_ring);
-				send_message(Message::InterceptTaskAssign(message));
-				println!("Starting intercept with {} against {}", interceptor, track);
-				self.intercept_tasks.push(InterceptTask {
-					contact_id: track.id,
-					interceptor_id: interceptor,
-					ring: next_free_ring,
-				});
-			}
-		}
+		self.check_intercept_deliveries(ctx);
+		self.check_strike_deliveries(ctx);
+		self.update_scan_pattern(ctx);
+		self.request_dwells(ctx);
 
 		// if let Some(nearest_ship) = radar.get_nearest_ship() {
 		// 	self.turrets.iter_mut().for_each(|f| f.set_target(nearest_ship));
@@ -162,20 +177,230 @@ impl ShipControlSystem {
 			let last_shot_time = self.last_shot_time();
 
 			let turret = &mut self.turrets[i];
-			turret.update(last_shot_time);
+			turret.update(&ctx.radar, &ctx.datalink, last_shot_time);
 		}
 	}
 
-	fn handle_dl_message(&mut self, message: Message) {
-		match message {
-			Message::InterceptTaskAssign(task) => {
-				if task.contact_id == 0 && task.target_id == 0 {
-					// Yippy we've got another interceptor to use!
-					self.interceptors.push(task.interceptor_id);
+	pub fn handle_dl_message(&mut self, message: Message) {
+		if let Message::InterceptTaskAssign(task) = message {
+			if task.contact_id == 0 && task.target_id == 0 {
+				// Yippy we've got another interceptor to use!
+				self.interceptors.push(task.interceptor_id);
+			}
+		}
+	}
+
+	// Worst first, see ThreatEvaluator
+	pub fn threats(&self) -> &[Threat] {
+		self.threats.threats()
+	}
+
+	pub fn turret_targets(&self) -> Vec<Option<i64>> {
+		self.turrets.iter().map(|t| t.target()).collect()
+	}
+
+	// Decisions of the last weapon assignment cycle, see WeaponAssigner
+	pub fn weapon_assignments(&self) -> &[WeaponAssignment] {
+		self.weapons.assignments()
+	}
+
+	pub fn weapon_switches(&self) -> u32 {
+		self.weapons.switches()
+	}
+
+	fn evaluate_threats(&mut self, ctx: &FleetContext) {
+		let own_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let own_vel: Vector3 = self.hw.vehicle_get_velocity().into();
+		let tasks = &self.intercept_tasks;
+		self.threats.evaluate(
+			&ctx.radar.get_established_tracks(),
+			own_pos,
+			own_vel,
+			|id| tasks.iter().any(|t| t.contact_id == id),
+			self.clock.now(),
+		);
+	}
+
+	// Turrets, loitering interceptors and strike missiles all go in one assignment against the ranked threats.
+	// Interceptors leave the pool once sent, turrets and strikes are reconsidered every cycle
+	fn assign_weapons(&mut self, ctx: &mut FleetContext) {
+		let now = self.clock.now();
+		if now - self.last_assignment_time < WEAPON_ASSIGNMENT_INTERVAL {
+			return;
+		}
+		self.last_assignment_time = now;
+
+		let mut weapons: Vec<Weapon> = (0..self.turrets.len()).map(|i| Weapon::Turret(i as u8)).collect();
+		weapons.extend(self.interceptors.iter().map(|id| Weapon::Interceptor(*id)));
+		weapons.extend(ctx.datalink.members_with_role(LinkRole::Strike).into_iter().map(Weapon::Strike));
+		self.strike_orders.retain(|o| weapons.contains(&Weapon::Strike(o.strike_id)));
+
+		let assignments = self.weapons.assign(&weapons, self.threats.threats()).to_vec();
+		for assignment in assignments.iter().filter(|a| a.changed) {
+			println!(
+				"Assigning {:?} to {} (Pk {:.2}, value {:.3})",
+				assignment.weapon, assignment.track_id, assignment.kill_probability, assignment.value
+			);
+		}
+
+		for (i, turret) in self.turrets.iter_mut().enumerate() {
+			match self.weapons.target_of(Weapon::Turret(i as u8)).and_then(|id| ctx.radar.get_contact(id)) {
+				Some(track) => turret.set_target(track),
+				None => turret.clear_target(),
+			}
+		}
+
+		for assignment in assignments {
+			let Some(track) = ctx.radar.get_contact(assignment.track_id).copied() else {
+				continue;
+			};
+			match assignment.weapon {
+				Weapon::Interceptor(interceptor) => self.start_intercept(interceptor, &track, ctx),
+				Weapon::Strike(strike) => self.order_strike(strike, &track, ctx),
+				_ => {}
+			}
+		}
+	}
+
+	fn start_intercept(&mut self, interceptor: u8, track: &RadarTrack, ctx: &mut FleetContext) {
+		// Out of track ids, the interceptor stays in the pool for when one frees up
+		let Some(track_id) = ctx.datalink.net_id(track.id) else {
+			return;
+		};
+
+		let mut next_free_ring = 0;
+		for i in 0..15 {
+			if !self.intercept_tasks.iter().any(|f| f.ring == i) {
+				next_free_ring = i;
+				break;
+			}
+		}
+
+		self.interceptors.retain(|i| *i != interceptor);
+		let message = InterceptTaskAssign::new(track_id, dl_crunch_id(track.id), interceptor, next_free_ring);
+		let delivery = ctx.datalink.send_reliable(interceptor, Message::InterceptTaskAssign(message));
+		println!("Starting intercept with {} against {}", interceptor, track);
+		self.intercept_tasks.push(InterceptTask {
+			contact_id: track.id,
+			interceptor_id: interceptor,
+			ring: next_free_ring,
+			delivery,
+			delivered: false,
+		});
+	}
+
+	// Sent again every cycle until the missile has an order for its target that is delivered or on its way
+	fn order_strike(&mut self, strike: u8, track: &RadarTrack, ctx: &mut FleetContext) {
+		if self.strike_orders.iter().any(|o| o.strike_id == strike && o.contact_id == track.id) {
+			return;
+		}
+		let Some(track_id) = ctx.datalink.net_id(track.id) else {
+			return;
+		};
+
+		let delivery = ctx
+			.datalink
+			.send_reliable(strike, Message::AssignAttackTarget(AssignAttackTarget::new(track_id)));
+		self.strike_orders.retain(|o| o.strike_id != strike);
+		self.strike_orders.push(StrikeOrder {
+			strike_id: strike,
+			contact_id: track.id,
+			delivery,
+			delivered: false,
+		});
+	}
+
+	// Whatever the turrets are shooting at and the interceptors are chasing gets looked at more often
+	fn request_dwells(&self, ctx: &mut FleetContext) {
+		for task in self.intercept_tasks.iter() {
+			let request = DwellRequest::new(
+				DwellTarget::Contact(task.contact_id),
+				DwellRequester::Intercept(task.interceptor_id),
+				INTERCEPT_DWELL_INTERVAL,
+				INTERCEPT_DWELL_PRIORITY,
+			);
+			ctx.radar.request_dwell(request);
+		}
+
+		for (i, turret) in self.turrets.iter().enumerate() {
+			if let Some(target) = turret.target() {
+				let request = DwellRequest::new(
+					DwellTarget::Contact(target),
+					DwellRequester::Turret(i as u8),
+					TURRET_DWELL_INTERVAL,
+					TURRET_DWELL_PRIORITY,
+				);
+				ctx.radar.request_dwell(request);
+			}
+		}
+	}
+
+	// Threats come from the enemy's side, so once we know where that is the search goes there
+	fn update_scan_pattern(&mut self, ctx: &mut FleetContext) {
+		let now = self.clock.now();
+		let own_pos: Vector3 = self.hw.vehicle_get_position().into();
+		let enemy = ctx
+			.radar
+			.get_established_tracks()
+			.into_iter()
+			.filter(|t| t.rc_type == RadarTargetType::SpaceBattleShip && !t.is_allied)
+			.map(|t| t.get_current_position(now))
+			.chain(
+				ctx.datalink
+					.get_ship_tracks()
+					.iter()
+					.filter(|t| !t.is_allied)
+					.map(|t| t.get_current_position(now)),
+			)
+			.min_by(|a, b| (*a - own_pos).length().total_cmp(&(*b - own_pos).length()));
+		let direction = get!(enemy.map(|e| (e - own_pos).normalized()));
+
+		if self.scan_direction.is_some_and(|d| d.dot(&direction) > rad(SCAN_REAIM_ANGLE).cos()) {
+			return;
+		}
+
+		println!("Focusing radar search toward the enemy at {}", direction);
+		self.scan_direction = Some(direction);
+		let priority = RevisitPriority {
+			direction,
+			half_angle: ENEMY_PRIORITY_HALF_ANGLE,
+			rate: ENEMY_PRIORITY_RATE,
+		};
+		ctx.radar
+			.set_scan_pattern(ScanPatternSpec::new(ScanSector::Hemisphere(direction), ENEMY_SCAN_BEAM_WIDTH, Some(priority)));
+	}
+
+	// An interceptor that never got its order is presumed lost, the threat goes back up for assignment
+	fn check_intercept_deliveries(&mut self, ctx: &FleetContext) {
+		for task in self.intercept_tasks.iter_mut().filter(|t| !t.delivered) {
+			match ctx.datalink.delivery_status(task.delivery) {
+				DeliveryStatus::Delivered => {
+					task.delivered = true;
+					println!("Interceptor {} acknowledged its task", task.interceptor_id);
 				}
+				DeliveryStatus::Failed => println!("Interceptor {} never acknowledged its task, reassigning", task.interceptor_id),
+				DeliveryStatus::Pending => {}
 			}
-			_ => {}
 		}
+
+		self
+			.intercept_tasks
+			.retain(|t| t.delivered || ctx.datalink.delivery_status(t.delivery) != DeliveryStatus::Failed);
+	}
+
+	// A failed order is forgotten, the next assignment cycle sends it again
+	fn check_strike_deliveries(&mut self, ctx: &FleetContext) {
+		for order in self.strike_orders.iter_mut().filter(|o| !o.delivered) {
+			match ctx.datalink.delivery_status(order.delivery) {
+				DeliveryStatus::Delivered => order.delivered = true,
+				DeliveryStatus::Failed => println!("Strike missile {} never acknowledged its target, ordering again", order.strike_id),
+				DeliveryStatus::Pending => {}
+			}
+		}
+
+		self
+			.strike_orders
+			.retain(|o| o.delivered || ctx.datalink.delivery_status(o.delivery) != DeliveryStatus::Failed);
 	}
 
 	fn check_queued_launches(&mut self) {
@@ -187,7 +412,7 @@ impl ShipControlSystem {
 			if let Some(unfired) = maybe_unfired {
 				unfired_cells.push(unfired);
 			} else {
-				self.last_missile_launch_time = now();
+				self.last_missile_launch_time = self.clock.now();
 			}
 		}
 
@@ -195,9 +420,9 @@ impl ShipControlSystem {
 	}
 
 	fn maybe_fire_cell(&self, cell: &QueuedLaunch) -> Option<QueuedLaunch> {
-		let reload_time = missilelauncher_get_reloadtime(cell.cell);
+		let reload_time = self.hw.missilelauncher_get_reloadtime(cell.cell);
 
-		if now() - self.last_missile_launch_time < MISSILE_LAUNCH_RATE {
+		if self.clock.now() - self.last_missile_launch_time < MISSILE_LAUNCH_RATE {
 			let new_times_at_zero = if reload_time == 0.0 { 0 } else { cell.times_at_zero + 1 };
 			return Some(QueuedLaunch {
 				cell: cell.cell,
@@ -211,29 +436,29 @@ impl ShipControlSystem {
 
 		if cell.times_at_zero > 1 {
 			println!("Firing cell {}", cell.cell);
-			missilelauncher_trigger(cell.cell);
+			self.hw.missilelauncher_trigger(cell.cell);
 			return None;
 		}
 
-		return Some(QueuedLaunch {
+		Some(QueuedLaunch {
 			cell: cell.cell,
 			times_at_zero: cell.times_at_zero + 1,
-		});
+		})
 	}
 
 	fn fire_missile(&mut self, warhead: MissileWarheadType, engine: MissileEngineType) {
 		// Try to find an already loaded cell with a nuclear missile
 		let cell = (0..18).find(|&i| {
-			let matching_warhead = missilelauncher_get_warheadtype(i) == warhead;
-			let matching_engine = missilelauncher_get_enginetype(i) == engine;
-			let reload_time = missilelauncher_get_reloadtime(i);
+			let matching_warhead = self.hw.missilelauncher_get_warheadtype(i) == warhead;
+			let matching_engine = self.hw.missilelauncher_get_enginetype(i) == engine;
+			let reload_time = self.hw.missilelauncher_get_reloadtime(i);
 			if !matching_warhead || !matching_engine || reload_time > 0.0 {
 				return false;
 			}
 
 			// Make sure not queued by someone else
 			let not_queued = !self.queued_launch_cells.iter().any(|f| f.cell == i);
-			return not_queued;
+			not_queued
 		});
 
 		if let Some(cell) = cell {
@@ -243,11 +468,11 @@ impl ShipControlSystem {
 			// Find a non-queued cell to load
 			let cell = (0..19).find(|&i| {
 				let not_queued = !self.queued_launch_cells.iter().any(|f| f.cell == i);
-				return not_queued;
+				not_queued
 			});
 
 			if let Some(cell) = cell {
-				missilelauncher_configure(cell, engine, warhead, 1.0);
+				self.hw.missilelauncher_configure(cell, engine, warhead, 1.0);
 				self.queued_launch_cells.push(QueuedLaunch { cell, times_at_zero: 0 });
 				println!("Queued missile launch for cell {}", cell);
 			} else {
@@ -260,19 +485,3 @@ impl ShipControlSystem {
 		self.turrets.iter().map(|f| f.last_shot_time).fold(0.0, |a, b| a.max(b))
 	}
 }
-
-thread_local! {
-	static SCS: RefCell<ShipControlSystem> = RefCell::new(ShipControlSystem::new());
-}
-
-pub fn init_scs() {
-	SCS.with(|scs| scs.borrow_mut().init());
-}
-
-pub fn update_scs() {
-	SCS.with(|scs| scs.borrow_mut().update());
-}
-
-pub fn scs_handle_dl_message(message: Message) {
-	SCS.with(|f| f.borrow_mut().handle_dl_message(message));
-}
@@ -0,0 +1,103 @@
+use protologic_core::radar::RadarTargetType;
+
+use crate::{controllers::radar_controller::RadarTrack, math::vector3::Vector3};
+
+const IMPACT_RADIUS: f32 = 100.0; // Passing closer than this counts as hitting us
+const CPA_SCALE: f32 = 500.0; // Miss distance at which a threat counts for half
+const TIME_SCALE: f32 = 20.0; // Seconds to go at which a threat counts for half
+const ENGAGED_FACTOR: f32 = 0.5; // Something already being dealt with matters less than something that isn't
+
+// How much each kind of contact could hurt us, anything not listed is no threat at all
+fn type_value(rc_type: RadarTargetType) -> f32 {
+	match rc_type {
+		RadarTargetType::Missile => 1.0,
+		RadarTargetType::SpaceBattleShip => 0.3, // Its guns, but it's the missiles it sends that kill
+		_ => 0.0,
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Threat {
+	pub track_id: i64,
+	pub rc_type: RadarTargetType,
+	pub position: Vector3,
+	pub velocity: Vector3,
+	pub range: f32,
+
+	// Assuming neither of us changes course
+	pub time_to_impact: Option<f32>, // None if it misses
+	pub cpa_distance: f32,           // Closest it gets to us
+	pub time_to_cpa: f32,            // 0 if that's now, it's moving away
+
+	pub engaged: bool, // An interceptor is on it
+	pub score: f32,
+}
+
+impl Threat {
+	pub fn assess(track: &RadarTrack, own_pos: Vector3, own_vel: Vector3, engaged: bool, now: f32) -> Threat {
+		let position = track.get_current_position(now);
+		let velocity = track.get_current_velocity(now);
+		let offset = position - own_pos;
+		let closing = velocity - own_vel;
+
+		let speed_sq = closing.dot(&closing);
+		let time_to_cpa = if speed_sq > 0.0 { (-offset.dot(&closing) / speed_sq).max(0.0) } else { 0.0 };
+		let cpa_distance = (offset + closing * time_to_cpa).length();
+
+		// When it first gets within the impact radius
+		let time_to_impact = (cpa_distance <= IMPACT_RADIUS).then(|| {
+			let inside = (IMPACT_RADIUS * IMPACT_RADIUS - cpa_distance * cpa_distance).sqrt();
+			(time_to_cpa - inside / speed_sq.sqrt().max(f32::EPSILON)).max(0.0)
+		});
+
+		let time_to_go = time_to_impact.unwrap_or(time_to_cpa);
+		let mut score = type_value(track.rc_type) * CPA_SCALE / (CPA_SCALE + cpa_distance) * TIME_SCALE / (TIME_SCALE + time_to_go);
+		if engaged {
+			score *= ENGAGED_FACTOR;
+		}
+
+		Threat {
+			track_id: track.id,
+			rc_type: track.rc_type,
+			position,
+			velocity,
+			range: offset.length(),
+			time_to_impact,
+			cpa_distance,
+			time_to_cpa,
+			engaged,
+			score,
+		}
+	}
+}
+
+// Ranks every hostile track by how soon and how badly it could hurt us, so whatever we shoot with goes to the
+// worst of them first
+#[derive(Default)]
+pub struct ThreatEvaluator {
+	threats: Vec<Threat>,
+}
+
+impl ThreatEvaluator {
+	pub fn new() -> ThreatEvaluator {
+		ThreatEvaluator { threats: Vec::new() }
+	}
+
+	pub fn evaluate(&mut self, tracks: &[RadarTrack], own_pos: Vector3, own_vel: Vector3, is_engaged: impl Fn(i64) -> bool, now: f32) {
+		self.threats = tracks
+			.iter()
+			.filter(|t| t.is_established() && !t.is_allied && type_value(t.rc_type) > 0.0)
+			.map(|t| Threat::assess(t, own_pos, own_vel, is_engaged(t.id), now))
+			.collect();
+		self.threats.sort_by(|a, b| b.score.total_cmp(&a.score));
+	}
+
+	// Worst first
+	pub fn threats(&self) -> &[Threat] {
+		&self.threats
+	}
+
+	pub fn get(&self, track_id: i64) -> Option<&Threat> {
+		self.threats.iter().find(|t| t.track_id == track_id)
+	}
+}
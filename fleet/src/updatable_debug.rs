@@ -1,9 +1,13 @@
-use protologic_core::debugging::{debug_sphere_create, DebugShapeHandle};
+use std::rc::Rc;
 
-use crate::math::{utils::debug_line, vector3::Vector3};
+use crate::{
+	hardware::traits::{DebugShape, Hardware},
+	math::{utils::debug_line, vector3::Vector3},
+};
 
 pub struct UpdatableDebugLine {
-	handle: Option<DebugShapeHandle>,
+	hw: Rc<dyn Hardware>,
+	handle: Option<DebugShape>,
 	a: Vector3,
 	b: Vector3,
 
@@ -13,8 +17,9 @@ pub struct UpdatableDebugLine {
 }
 
 impl UpdatableDebugLine {
-	pub fn new() -> UpdatableDebugLine {
+	pub fn new(hw: Rc<dyn Hardware>) -> UpdatableDebugLine {
 		UpdatableDebugLine {
+			hw,
 			handle: None,
 			a: Vector3::zero(),
 			b: Vector3::zero(),
@@ -55,12 +60,13 @@ impl UpdatableDebugLine {
 	}
 
 	pub fn update(&mut self) {
-		self.handle = Some(debug_line(self.a, self.b, self.cr, self.cg, self.cb));
+		self.handle = Some(debug_line(&*self.hw, self.a, self.b, self.cr, self.cg, self.cb));
 	}
 }
 
 pub struct UpdatableSphere {
-	handle: Option<DebugShapeHandle>,
+	hw: Rc<dyn Hardware>,
+	handle: Option<DebugShape>,
 	pos: Vector3,
 	radius: f32,
 
@@ -70,8 +76,9 @@ pub struct UpdatableSphere {
 }
 
 impl UpdatableSphere {
-	pub fn new() -> UpdatableSphere {
+	pub fn new(hw: Rc<dyn Hardware>) -> UpdatableSphere {
 		UpdatableSphere {
+			hw,
 			handle: None,
 			pos: Vector3::zero(),
 			radius: 1.0,
@@ -112,7 +119,11 @@ impl UpdatableSphere {
 	}
 
 	pub fn update(&mut self) {
-		self.handle = Some(debug_sphere_create(self.pos.x, self.pos.y, self.pos.z, self.radius, self.cr, self.cg, self.cb));
+		self.handle = Some(
+			self
+				.hw
+				.debug_sphere_create(self.pos.x, self.pos.y, self.pos.z, self.radius, self.cr, self.cg, self.cb),
+		);
 	}
 
 	pub fn remove(&mut self) {
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use protologic_core::radar::RadarTargetType;
+
+use crate::{math::assignment::min_cost_assignment, threat_evaluation::Threat};
+
+pub const TURRET_RANGE: f32 = 2500.0; // Shells take too long to get any further
+const TURRET_PK: f32 = 0.5; // Point blank, falls off to nothing at TURRET_RANGE
+const INTERCEPTOR_PK: f32 = 0.7;
+const INTERCEPTOR_MIN_TIME: f32 = 5.0; // Less warning than this and an interceptor probably won't get there
+const LATE_INTERCEPTOR_PK: f32 = 0.2;
+const STRIKE_PK: f32 = 0.8;
+
+const MAX_PER_TARGET: usize = 4; // Most weapons worth stacking on one target
+const HYSTERESIS: f32 = 0.25; // Keeping a target is worth this much more, so near ties don't flip every cycle
+const IMPOSSIBLE: f32 = 1.0e6; // Cost of pairings that can't happen
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Weapon {
+	Turret(u8),
+	Interceptor(u8), // Loitering flak missile, dl_id
+	Strike(u8),      // Nuclear missile, dl_id
+}
+
+// Chance one weapon kills the target on its own
+pub fn kill_probability(weapon: Weapon, threat: &Threat) -> f32 {
+	match (weapon, threat.rc_type) {
+		(Weapon::Turret(_), _) if threat.range < TURRET_RANGE => TURRET_PK * (1.0 - threat.range / TURRET_RANGE),
+		// One interceptor per missile, a second is better kept back for the next one
+		(Weapon::Interceptor(_), RadarTargetType::Missile) if !threat.engaged => {
+			if threat.time_to_impact.is_some_and(|t| t < INTERCEPTOR_MIN_TIME) {
+				LATE_INTERCEPTOR_PK
+			} else {
+				INTERCEPTOR_PK
+			}
+		}
+		(Weapon::Strike(_), RadarTargetType::SpaceBattleShip) => STRIKE_PK,
+		_ => 0.0,
+	}
+}
+
+// One decision, kept for logging
+#[derive(Clone, Copy, Debug)]
+pub struct WeaponAssignment {
+	pub weapon: Weapon,
+	pub track_id: i64,
+	pub kill_probability: f32,
+	pub value: f32,    // Kill probability times threat score, less whatever the weapons before it on the target already cover
+	pub changed: bool, // New this cycle, the weapon was idle or on something else
+}
+
+// Spreads every weapon we have over the ranked threats to get the most threat killed in total. Each target is
+// offered to several weapons, each worth less than the one before as the target is more likely dead already,
+// and the whole lot solved as one assignment problem
+#[derive(Default)]
+pub struct WeaponAssigner {
+	assignments: Vec<WeaponAssignment>,
+	switches: u32,
+}
+
+impl WeaponAssigner {
+	pub fn new() -> WeaponAssigner {
+		WeaponAssigner { assignments: Vec::new(), switches: 0 }
+	}
+
+	pub fn assign(&mut self, weapons: &[Weapon], threats: &[Threat]) -> &[WeaponAssignment] {
+		let previous: HashMap<Weapon, i64> = self.assignments.iter().map(|a| (a.weapon, a.track_id)).collect();
+		let copies = weapons.len().min(MAX_PER_TARGET);
+		let target_columns = threats.len() * copies;
+
+		// (kill probability, value, with hysteresis) per weapon and target column
+		let values: Vec<Vec<(f32, f32, f32)>> = weapons
+			.iter()
+			.map(|weapon| {
+				threats
+					.iter()
+					.flat_map(|threat| {
+						let pk = kill_probability(*weapon, threat);
+						let kept = if previous.get(weapon) == Some(&threat.track_id) {
+							1.0 + HYSTERESIS
+						} else {
+							1.0
+						};
+						(0..copies).map(move |k| {
+							let value = pk * threat.score * (1.0 - pk).powi(k as i32);
+							(pk, value, value * kept)
+						})
+					})
+					.collect()
+			})
+			.collect();
+
+		// Target columns first, then an idle one per weapon
+		let costs: Vec<Vec<f32>> = values
+			.iter()
+			.enumerate()
+			.map(|(w, row)| {
+				let mut costs: Vec<f32> = row.iter().map(|(pk, _, kept)| if *pk > 0.0 { -kept } else { IMPOSSIBLE }).collect();
+				costs.extend((0..weapons.len()).map(|idle| if idle == w { 0.0 } else { IMPOSSIBLE }));
+				costs
+			})
+			.collect();
+
+		self.assignments = min_cost_assignment(&costs)
+			.into_iter()
+			.enumerate()
+			.filter(|(w, column)| *column < target_columns && values[*w][*column].0 > 0.0)
+			.map(|(w, column)| {
+				let weapon = weapons[w];
+				let threat = &threats[column / copies];
+				let (kill_probability, value, _) = values[w][column];
+				WeaponAssignment {
+					weapon,
+					track_id: threat.track_id,
+					kill_probability,
+					value,
+					changed: previous.get(&weapon) != Some(&threat.track_id),
+				}
+			})
+			.collect();
+
+		// Only a weapon dropping one target for another counts, picking one up or going idle doesn't
+		self.switches += self.assignments.iter().filter(|a| a.changed && previous.contains_key(&a.weapon)).count() as u32;
+
+		&self.assignments
+	}
+
+	// From the last cycle, weapons left idle aren't listed
+	pub fn assignments(&self) -> &[WeaponAssignment] {
+		&self.assignments
+	}
+
+	pub fn target_of(&self, weapon: Weapon) -> Option<i64> {
+		self.assignments.iter().find(|a| a.weapon == weapon).map(|a| a.track_id)
+	}
+
+	// Times a weapon went from one target to another
+	pub fn switches(&self) -> u32 {
+		self.switches
+	}
+}
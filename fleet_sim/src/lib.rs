@@ -0,0 +1,4 @@
+pub mod physics;
+pub mod radar;
+pub mod vehicle;
+pub mod world;
@@ -0,0 +1,55 @@
+use rs_chip_mafia::{
+	controllers::flight_controller::VehicleType,
+	hardware::mock_hardware::MockState,
+	math::{quaternion::Quaternion, vector3::Vector3},
+};
+
+pub struct BodyParams {
+	pub max_accel: f32,         // m/s^2 at full throttle
+	pub max_angular_accel: f32, // rad/s^2 per axis at full wheel torque
+	pub fuel_burn: f32,         // Fraction of the tank per second at full throttle
+}
+
+pub const SHIP_PARAMS: BodyParams = BodyParams {
+	max_accel: 5.0,
+	max_angular_accel: 1.0,
+	fuel_burn: 0.001,
+};
+
+pub const MISSILE_PARAMS: BodyParams = BodyParams {
+	max_accel: 60.0,
+	max_angular_accel: 4.0,
+	fuel_burn: 0.01,
+};
+
+pub fn params_for(kind: VehicleType) -> &'static BodyParams {
+	match kind {
+		VehicleType::Ship => &SHIP_PARAMS,
+		VehicleType::Missile => &MISSILE_PARAMS,
+	}
+}
+
+// Engines push along the local -Z axis, the same axis the flight controller points at its target
+pub fn forward(orientation: Quaternion) -> Vector3 {
+	orientation * Vector3::new(0.0, 0.0, -1.0)
+}
+
+// Semi-implicit Euler over the commands the fleet logic left in the mock
+pub fn integrate(state: &mut MockState, params: &BodyParams, dt: f32) {
+	let throttle = if state.fuel > 0.0 { state.throttle } else { 0.0 };
+	state.fuel = (state.fuel - throttle * params.fuel_burn * dt).max(0.0);
+
+	state.velocity += forward(state.orientation) * (throttle * params.max_accel * dt);
+	state.position += state.velocity * dt;
+
+	// Wheel torque is given in world space
+	state.angular_velocity += state.torque * (params.max_angular_accel * dt);
+	state.orientation = integrate_orientation(state.orientation, state.angular_velocity, dt);
+}
+
+fn integrate_orientation(q: Quaternion, angular_velocity: Vector3, dt: f32) -> Quaternion {
+	let omega = Quaternion::new(angular_velocity.x, angular_velocity.y, angular_velocity.z, 0.0);
+	let dq = omega * q;
+
+	Quaternion::new(q.x + 0.5 * dq.x * dt, q.y + 0.5 * dq.y * dt, q.z + 0.5 * dq.z * dt, q.w + 0.5 * dq.w * dt).normalized()
+}
@@ -0,0 +1,62 @@
+use std::f32::consts::PI;
+
+use protologic_core::radar::{RadarGetContactInfo, RadarTargetType};
+use rs_chip_mafia::math::{
+	quaternion::{AxisAngle, Quaternion},
+	utils::rad,
+	vector3::Vector3,
+};
+
+pub const RADAR_RANGE: f32 = 20000.0;
+
+pub struct RadarTarget {
+	pub id: i64,
+	pub target_type: RadarTargetType,
+	pub position: Vector3,
+}
+
+// Inverse of Vector3::angles() through the radar turret mount. The fleet code points the radar with
+// angles of (own position - target), same as the turrets, so the beam looks down the negated direction
+pub fn beam_direction(orientation: Quaternion, bearing: f32, elevation: f32) -> Vector3 {
+	let b = rad(bearing - 180.0);
+	let e = rad(elevation);
+	let radar_dir = Vector3::new(b.cos() * e.cos(), e.sin(), b.sin() * e.cos());
+
+	let turret_rotation = Quaternion::from_axis_angle(&AxisAngle {
+		axis: Vector3::new(0.0, 0.0, 1.0),
+		angle: PI / 2.0,
+	})
+	.normalized();
+
+	-(orientation * (turret_rotation * radar_dir))
+}
+
+// Everything inside the cone (full angle `angle` degrees) around the beam and within range is returned
+pub fn scan(origin: Vector3, beam: Vector3, angle: f32, targets: &[RadarTarget]) -> Vec<RadarGetContactInfo> {
+	let half_angle = rad(angle.clamp(0.0, 180.0) / 2.0);
+
+	targets
+		.iter()
+		.filter_map(|t| {
+			let offset = t.position - origin;
+			let dist = offset.length();
+			if !(0.001..=RADAR_RANGE).contains(&dist) {
+				return None;
+			}
+
+			let off_axis = (offset.dot(&beam) / dist).clamp(-1.0, 1.0).acos();
+			if off_axis > half_angle {
+				return None;
+			}
+
+			Some(RadarGetContactInfo {
+				id: t.id,
+				target_type: t.target_type,
+				signal_strength: 1.0 / (dist * dist),
+				x: t.position.x,
+				y: t.position.y,
+				z: t.position.z,
+			})
+		})
+		.collect()
+}
@@ -0,0 +1,54 @@
+use std::rc::Rc;
+
+use protologic_core::radar::RadarTargetType;
+use rs_chip_mafia::{clock::TickClock, controllers::flight_controller::VehicleType, core::Core, hardware::mock_hardware::MockHardware};
+
+pub enum Brain {
+	// Running the real fleet code
+	Fleet(Box<Core>),
+	// Keeps whatever velocity it has, for targets and threats
+	Ballistic,
+}
+
+impl Brain {
+	pub fn fleet(kind: VehicleType, hw: &MockHardware) -> Brain {
+		Brain::Fleet(Box::new(Core::new(kind, Rc::new(hw.clone()), Rc::new(TickClock::new()))))
+	}
+}
+
+pub struct SimVehicle {
+	pub id: i64,
+	pub kind: VehicleType,
+	pub team: u8,
+	pub hw: MockHardware,
+	pub alive: bool,
+
+	pub brain: Brain,
+}
+
+impl SimVehicle {
+	pub fn target_type(&self) -> RadarTargetType {
+		match self.kind {
+			VehicleType::Ship => RadarTargetType::SpaceBattleShip,
+			VehicleType::Missile => RadarTargetType::Missile,
+		}
+	}
+
+	pub fn is_fleet(&self) -> bool {
+		matches!(self.brain, Brain::Fleet(_))
+	}
+
+	pub fn core(&self) -> Option<&Core> {
+		match &self.brain {
+			Brain::Fleet(core) => Some(core),
+			Brain::Ballistic => None,
+		}
+	}
+
+	pub fn core_mut(&mut self) -> Option<&mut Core> {
+		match &mut self.brain {
+			Brain::Fleet(core) => Some(core),
+			Brain::Ballistic => None,
+		}
+	}
+}
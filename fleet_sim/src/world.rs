@@ -0,0 +1,330 @@
+use protologic_core::{missile_launcher::MissileWarheadType, radar::RadarGetContactInfo};
+use rs_chip_mafia::{controllers::flight_controller::VehicleType, hardware::mock_hardware::MockHardware, math::vector3::Vector3};
+
+use crate::{
+	physics::{forward, integrate, params_for},
+	radar::{beam_direction, scan, RadarTarget},
+	vehicle::{Brain, SimVehicle},
+};
+
+pub const TICK_RATE: u32 = 100;
+pub const DT: f32 = 1.0 / TICK_RATE as f32;
+
+const LAUNCHER_RELOAD_TIME: f32 = 5.0;
+const LAUNCH_EJECT_SPEED: f32 = 10.0;
+
+// Team, position, velocity and env of a missile leaving its launcher
+type Launch = (u8, Vector3, Vector3, Vec<(String, String)>);
+
+pub struct RadioRecord {
+	pub tick: u32,
+	pub sender: i64,
+	pub word: u64,
+}
+
+pub struct World {
+	pub tick: u32,
+	pub vehicles: Vec<SimVehicle>,
+	pub radio_log: Vec<RadioRecord>,
+
+	// Fake returns handed to a vehicle's radar on the next step, on top of whatever it really sees
+	clutter: Vec<(i64, RadarGetContactInfo)>,
+	// Decides whether a sent word is lost before anyone hears it, (sender, word) -> lost
+	radio_loss: Option<Box<dyn FnMut(i64, u64) -> bool>>,
+	// Every real return gets a fresh id instead of the target's, as if the ids were jammed
+	hide_ids: bool,
+	next_index: i64,
+	next_hidden_index: i64,
+}
+
+fn warhead_name(warhead: MissileWarheadType) -> &'static str {
+	match warhead {
+		MissileWarheadType::Nuclear => "Nuclear",
+		MissileWarheadType::Flak => "Flak",
+		MissileWarheadType::Jammer => "Jammer",
+		MissileWarheadType::Inert => "Inert",
+	}
+}
+
+fn blast_radius(warhead: Option<&String>) -> f32 {
+	match warhead.map(|w| w.as_str()) {
+		Some("Nuclear") => 500.0,
+		Some("Flak") => 250.0,
+		_ => 50.0,
+	}
+}
+
+impl Default for World {
+	fn default() -> Self {
+		World::new()
+	}
+}
+
+impl World {
+	pub fn new() -> World {
+		World {
+			tick: 0,
+			vehicles: Vec::new(),
+			radio_log: Vec::new(),
+			clutter: Vec::new(),
+			radio_loss: None,
+			hide_ids: false,
+			next_index: 0,
+			next_hidden_index: 0,
+		}
+	}
+
+	pub fn time(&self) -> f32 {
+		self.tick as f32 * DT
+	}
+
+	// Ids look like the simulator's, a unique upper half so dl_crunch_id stays distinct
+	fn next_id(&mut self) -> i64 {
+		self.next_index += 1;
+		((1000 + self.next_index) << 32) | self.next_index
+	}
+
+	pub fn spawn_fleet_vehicle(&mut self, kind: VehicleType, team: u8, position: Vector3, env: &[(&str, &str)]) -> i64 {
+		let hw = MockHardware::new();
+		{
+			let mut state = hw.state();
+			state.position = position;
+			state.env.insert(
+				"Type".to_string(),
+				match kind {
+					VehicleType::Ship => "Ship".to_string(),
+					VehicleType::Missile => "Missile".to_string(),
+				},
+			);
+			for (k, v) in env {
+				state.env.insert(k.to_string(), v.to_string());
+			}
+		}
+
+		let id = self.next_id();
+		let brain = Brain::fleet(kind, &hw);
+		self.vehicles.push(SimVehicle { id, kind, team, hw, alive: true, brain });
+
+		id
+	}
+
+	pub fn spawn_ballistic(&mut self, kind: VehicleType, team: u8, position: Vector3, velocity: Vector3) -> i64 {
+		let hw = MockHardware::new();
+		{
+			let mut state = hw.state();
+			state.position = position;
+			state.velocity = velocity;
+		}
+
+		let id = self.next_id();
+		self.vehicles.push(SimVehicle {
+			id,
+			kind,
+			team,
+			hw,
+			alive: true,
+			brain: Brain::Ballistic,
+		});
+
+		id
+	}
+
+	pub fn vehicle(&self, id: i64) -> Option<&SimVehicle> {
+		self.vehicles.iter().find(|v| v.id == id)
+	}
+
+	pub fn vehicle_mut(&mut self, id: i64) -> Option<&mut SimVehicle> {
+		self.vehicles.iter_mut().find(|v| v.id == id)
+	}
+
+	// Takes a vehicle out without a blast, as if it was killed by something the sim doesn't model
+	pub fn destroy(&mut self, id: i64) {
+		if let Some(v) = self.vehicle_mut(id) {
+			v.alive = false;
+			v.brain = Brain::Ballistic;
+		}
+	}
+
+	pub fn inject_radar_contact(&mut self, vehicle_id: i64, contact: RadarGetContactInfo) {
+		self.clutter.push((vehicle_id, contact));
+	}
+
+	pub fn hide_contact_ids(&mut self) {
+		self.hide_ids = true;
+	}
+
+	pub fn set_radio_loss(&mut self, loss: impl FnMut(i64, u64) -> bool + 'static) {
+		self.radio_loss = Some(Box::new(loss));
+	}
+
+	pub fn position_of(&self, id: i64) -> Option<Vector3> {
+		self.vehicle(id).filter(|v| v.alive).map(|v| v.hw.state().position)
+	}
+
+	pub fn step(&mut self) {
+		self.update_radar();
+
+		for v in self.vehicles.iter_mut().filter(|v| v.alive) {
+			if let Brain::Fleet(core) = &mut v.brain {
+				core.tick();
+			}
+		}
+
+		self.process_launches();
+		self.process_detonations();
+
+		for v in self.vehicles.iter().filter(|v| v.alive) {
+			let mut state = v.hw.state();
+			integrate(&mut state, params_for(v.kind), DT);
+
+			for gun in state.guns.iter_mut() {
+				gun.refire_time = (gun.refire_time - DT).max(0.0);
+				gun.reload_time = (gun.reload_time - DT).max(0.0);
+			}
+			for cell in state.launcher_cells.iter_mut() {
+				cell.reload_time = (cell.reload_time - DT).max(0.0);
+			}
+		}
+
+		self.deliver_radio();
+		self.tick += 1;
+	}
+
+	// Steps until `done` holds or `seconds` of sim time pass, returns whether `done` was reached
+	pub fn run_until(&mut self, seconds: f32, mut done: impl FnMut(&World) -> bool) -> bool {
+		let end_tick = self.tick + (seconds * TICK_RATE as f32) as u32;
+		while self.tick < end_tick {
+			self.step();
+			if done(self) {
+				return true;
+			}
+		}
+
+		false
+	}
+
+	fn update_radar(&mut self) {
+		let targets: Vec<RadarTarget> = self
+			.vehicles
+			.iter()
+			.filter(|v| v.alive)
+			.map(|v| RadarTarget {
+				id: v.id,
+				target_type: v.target_type(),
+				position: v.hw.state().position,
+			})
+			.collect();
+
+		for v in self.vehicles.iter().filter(|v| v.alive) {
+			let mut state = v.hw.state();
+			if !state.radar_triggered {
+				state.radar_contacts.clear();
+				continue;
+			}
+
+			let beam = beam_direction(state.orientation, state.radar_bearing, state.radar_elevation);
+			state.radar_contacts = scan(state.position, beam, state.radar_angle, &targets);
+			state.radar_triggered = false;
+
+			if self.hide_ids {
+				for contact in state.radar_contacts.iter_mut() {
+					self.next_hidden_index += 1;
+					contact.id = ((50000 + self.next_hidden_index) << 32) | self.next_hidden_index;
+				}
+			}
+		}
+
+		for (vehicle_id, contact) in self.clutter.drain(..) {
+			if let Some(v) = self.vehicles.iter().find(|v| v.id == vehicle_id && v.alive) {
+				v.hw.state().radar_contacts.push(contact);
+			}
+		}
+	}
+
+	fn process_launches(&mut self) {
+		let mut launches: Vec<Launch> = Vec::new();
+		for v in self.vehicles.iter().filter(|v| v.alive) {
+			let mut state = v.hw.state();
+			let fired: Vec<i32> = state.launches.drain(..).collect();
+			for cell_index in fired {
+				let cell = &mut state.launcher_cells[cell_index as usize];
+				cell.reload_time = LAUNCHER_RELOAD_TIME;
+				let warhead = warhead_name(cell.warhead).to_string();
+
+				// Missiles get the rest of the launcher's env, the same fleet settings
+				let mut env: Vec<(String, String)> = state
+					.env
+					.iter()
+					.filter(|(k, _)| k.as_str() != "Type" && k.as_str() != "WarheadType")
+					.map(|(k, v)| (k.clone(), v.clone()))
+					.collect();
+				env.push(("WarheadType".to_string(), warhead));
+
+				let dir = forward(state.orientation);
+				launches.push((v.team, state.position + dir * 20.0, state.velocity + dir * LAUNCH_EJECT_SPEED, env));
+			}
+		}
+
+		for (team, position, velocity, env) in launches {
+			let env: Vec<(&str, &str)> = env.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+			let id = self.spawn_fleet_vehicle(VehicleType::Missile, team, position, &env);
+			let missile = self.vehicles.last().unwrap();
+			missile.hw.state().velocity = velocity;
+			println!("[sim] Launched missile {} ({})", id, missile.hw.state().env["WarheadType"]);
+		}
+	}
+
+	fn process_detonations(&mut self) {
+		let blasts: Vec<(i64, Vector3, f32)> = self
+			.vehicles
+			.iter()
+			.filter(|v| v.alive && v.hw.state().destructed)
+			.map(|v| {
+				let state = v.hw.state();
+				(v.id, state.position, blast_radius(state.env.get("WarheadType")))
+			})
+			.collect();
+
+		for (source, position, radius) in blasts {
+			println!("[sim] {} detonated at {}", source, position);
+			for v in self.vehicles.iter_mut().filter(|v| v.alive) {
+				if v.id == source || (v.hw.state().position - position).length() < radius {
+					v.alive = false;
+				}
+			}
+		}
+
+		// Anything that died stops running fleet code
+		for v in self.vehicles.iter_mut().filter(|v| !v.alive && v.is_fleet()) {
+			v.brain = Brain::Ballistic;
+		}
+	}
+
+	// Words sent this tick show up in everyone else's inbox on the next one
+	fn deliver_radio(&mut self) {
+		let mut sent: Vec<(i64, Vector3, u64, f32)> = Vec::new();
+		for v in self.vehicles.iter() {
+			let mut state = v.hw.state();
+			let position = state.position;
+			for (word, range) in state.radio_outbox.drain(..) {
+				if v.alive {
+					sent.push((v.id, position, word, range));
+				}
+			}
+		}
+
+		for (sender, position, word, range) in sent {
+			self.radio_log.push(RadioRecord { tick: self.tick, sender, word });
+			if self.radio_loss.as_mut().is_some_and(|lost| lost(sender, word)) {
+				continue;
+			}
+
+			for v in self.vehicles.iter().filter(|v| v.alive && v.id != sender) {
+				let mut state = v.hw.state();
+				if (state.position - position).length() <= range {
+					state.radio_inbox.push(word);
+				}
+			}
+		}
+	}
+}
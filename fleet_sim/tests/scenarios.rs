@@ -0,0 +1,1046 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use fleet_sim::world::World;
+use protologic_core::radar::{RadarGetContactInfo, RadarTargetType};
+use rs_chip_mafia::{
+	controllers::{
+		flight_controller::VehicleType,
+		radar_controller::RadarController,
+		radar_scheduler::Dwell,
+		track_association::{AmbiguousAssociation, AssociationStrategy},
+	},
+	datalink::{
+		datalink::DatalinkStatus,
+		framing::{FramedMessage, FramedMessageKey, Reassembler},
+		messages::{
+			authenticated::Authenticated,
+			fragment::Fragment,
+			intercept_task_assign::InterceptTaskAssign,
+			message::{DatalinkMessage, Message, MessageKey},
+			single::Single,
+			track_position::TrackPosition,
+			track_state::TrackState,
+		},
+		slot_allocator::BLOCK_CAPACITY,
+		stats::DatalinkStats,
+	},
+	fleet_context::ControlSystem,
+	math::vector3::Vector3,
+	radar_scan_pattern::{RadarScanPattern, ScanSector},
+	ship_control_system::ShipControlSystem,
+	weapon_assignment::Weapon,
+};
+
+fn radar(world: &World, id: i64) -> &RadarController {
+	&world.vehicle(id).unwrap().core().unwrap().ctx.radar
+}
+
+fn control(world: &World, ship: i64) -> &ShipControlSystem {
+	match world.vehicle(ship).unwrap().core().unwrap().ctx.control_system() {
+		Some(ControlSystem::Ship(scs)) => scs,
+		_ => panic!("{} has no ship control system", ship),
+	}
+}
+
+fn link(world: &World, id: i64) -> DatalinkStats {
+	world.vehicle(id).unwrap().core().unwrap().ctx.datalink.stats()
+}
+
+fn missiles(world: &World) -> Vec<i64> {
+	world.vehicles.iter().filter(|v| v.kind == VehicleType::Missile).map(|v| v.id).collect()
+}
+
+// Ship with its interceptor on station, and a hostile missile coming straight at it from 8km. Returns the
+// world, ship and missile
+fn ship_with_incoming_missile(env: &[(&str, &str)]) -> (World, i64, i64) {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, env);
+
+	world.run_until(20.0, |_| false);
+	let threat = world.spawn_ballistic(
+		VehicleType::Missile,
+		1,
+		ship_pos + Vector3::new(8000.0, 0.0, 0.0),
+		Vector3::new(-100.0, 0.0, 0.0),
+	);
+
+	(world, ship, threat)
+}
+
+#[test]
+fn interceptor_joins_datalink_and_reports_ready() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+
+	let reported = world.run_until(15.0, |w| {
+		w.radio_log.iter().any(|r| {
+			r.sender != ship && matches!(Message::parse(r.word).unwrap(), Message::InterceptTaskAssign(task) if task.contact_id == 0 && task.interceptor_id > 0)
+		})
+	});
+
+	assert!(reported, "No interceptor reported ready after {}s", world.time());
+	assert_eq!(missiles(&world).len(), 1);
+}
+
+#[test]
+fn interceptor_holds_station_near_ship() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+
+	world.run_until(60.0, |_| false);
+
+	let ship_pos = world.position_of(ship).unwrap();
+	let interceptor = missiles(&world)[0];
+	let dist = (world.position_of(interceptor).expect("Interceptor was lost") - ship_pos).length();
+	assert!(dist < 1000.0, "Interceptor drifted {}m from the ship", dist);
+}
+
+#[test]
+fn inbound_missile_is_intercepted() {
+	let (mut world, _, threat) = ship_with_incoming_missile(&[]);
+
+	let killed = world.run_until(80.0, |w| w.position_of(threat).is_none());
+	assert!(killed, "Threat was not intercepted after {}s", world.time());
+}
+
+#[test]
+fn spurious_return_never_becomes_a_track() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+
+	let dropped = Rc::new(RefCell::new(Vec::new()));
+	let log = dropped.clone();
+	world
+		.vehicle_mut(ship)
+		.unwrap()
+		.core_mut()
+		.unwrap()
+		.ctx
+		.radar
+		.on_track_dropped(move |t| log.borrow_mut().push(t.id));
+
+	let ghost_id = (9999 << 32) | 9999;
+	world.run_until(1.0, |_| false);
+	world.inject_radar_contact(
+		ship,
+		RadarGetContactInfo {
+			id: ghost_id,
+			target_type: RadarTargetType::Missile,
+			x: 3000.0,
+			y: 0.0,
+			z: 5000.0,
+			signal_strength: 1.0,
+		},
+	);
+
+	world.run_until(1.0, |_| false);
+	let radar = &world.vehicle(ship).unwrap().core().unwrap().ctx.radar;
+	assert!(radar.get_established_tracks().iter().all(|t| t.id != ghost_id), "Ghost was confirmed");
+
+	world.run_until(5.0, |_| false);
+	assert!(dropped.borrow().contains(&ghost_id), "Ghost track was never dropped");
+}
+
+#[test]
+fn destroyed_threat_is_dropped_from_the_datalink() {
+	let (mut world, ship, threat) = ship_with_incoming_missile(&[]);
+	assert!(world.run_until(80.0, |w| w.position_of(threat).is_none()));
+
+	let killed_tick = world.tick;
+	let dropped = world.run_until(10.0, |w| {
+		w.radio_log
+			.iter()
+			.any(|r| r.tick >= killed_tick && r.sender == ship && matches!(Message::parse(r.word).unwrap(), Message::TrackDrop(_)))
+	});
+	assert!(dropped, "Ship never told the datalink the threat was gone");
+}
+
+#[test]
+fn interceptor_lock_is_fused_into_ship_picture() {
+	let (mut world, ship, threat) = ship_with_incoming_missile(&[]);
+
+	let fused = world.run_until(80.0, |w| {
+		let datalink = &w.vehicle(ship).unwrap().core().unwrap().ctx.datalink;
+		datalink.get_track_for_contact(threat).is_some_and(|t| t.sources > 1)
+	});
+	assert!(fused, "Ship never fused an interceptor's report on the threat");
+}
+
+#[test]
+fn interceptor_takes_over_as_host_when_ship_dies() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+
+	world.run_until(20.0, |_| false);
+	let interceptor = missiles(&world)[0];
+	world.destroy(ship);
+
+	let killed_tick = world.tick;
+	let took_over = world.run_until(10.0, |w| {
+		w.radio_log
+			.iter()
+			.any(|r| r.tick >= killed_tick && r.sender == interceptor && matches!(Message::parse(r.word).unwrap(), Message::NetInfo(_)))
+	});
+	assert!(took_over, "Interceptor never started sending NetInfo");
+
+	let datalink = &world.vehicle(interceptor).unwrap().core().unwrap().ctx.datalink;
+	assert!(datalink.is_host());
+}
+
+#[test]
+fn only_one_member_takes_over_as_host() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+	world.spawn_fleet_vehicle(VehicleType::Missile, 0, Vector3::new(200.0, 0.0, 5000.0), &[("WarheadType", "Flak")]);
+
+	world.run_until(20.0, |_| false);
+	world.destroy(ship);
+	world.run_until(10.0, |_| false);
+
+	let hosts: Vec<u8> = world
+		.vehicles
+		.iter()
+		.filter_map(|v| v.core())
+		.filter(|c| c.ctx.datalink.is_host())
+		.map(|c| c.ctx.datalink.id())
+		.collect();
+	assert_eq!(hosts, vec![1], "Expected the lowest id to be the only host");
+}
+
+#[test]
+fn track_state_survives_fragmentation() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+
+	// Further out than TrackPosition can encode, it has to come through as a framed TrackState
+	world.run_until(5.0, |_| false);
+	let threat = world.spawn_ballistic(
+		VehicleType::Missile,
+		1,
+		ship_pos + Vector3::new(12000.0, 0.0, 0.0),
+		Vector3::new(-100.0, 0.0, 0.0),
+	);
+
+	let mut reassembler = Reassembler::new();
+	let mut received = None;
+	let mut next_record = world.radio_log.len();
+	let found = world.run_until(30.0, |w| {
+		for record in &w.radio_log[next_record..] {
+			if let (true, Message::Fragment(fragment)) = (record.sender == ship, Message::parse(record.word).unwrap()) {
+				if let Ok(Some(FramedMessage::TrackState(state))) = reassembler.add(fragment, w.time()) {
+					received = Some((state, w.position_of(threat).unwrap()));
+				}
+			}
+		}
+		next_record = w.radio_log.len();
+		received.is_some()
+	});
+	assert!(found, "Ship never sent a TrackState for the threat");
+
+	let (state, threat_pos) = received.unwrap();
+	assert_eq!(state.contact_type, RadarTargetType::Missile);
+	let error = (state.position - threat_pos).length();
+	assert!(error < 50.0, "TrackState position is {}m off", error);
+}
+
+#[test]
+fn orders_jump_the_track_report_backlog() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+	world.run_until(5.0, |_| false);
+
+	// A pile of reports, with every track updated several times over, then an order behind them
+	let datalink = &mut world.vehicle_mut(ship).unwrap().core_mut().unwrap().ctx.datalink;
+	for _ in 0..5 {
+		for track_id in 100..120 {
+			datalink.send_message(Message::TrackPosition(TrackPosition::new(track_id, Vector3::new(track_id as f32, 0.0, 0.0))));
+		}
+	}
+	datalink.send_message(Message::InterceptTaskAssign(InterceptTaskAssign::new(7, 77, 3, 0)));
+
+	let start_tick = world.tick;
+	world.run_until(10.0, |_| false);
+
+	let sent: Vec<(u32, Message)> = world
+		.radio_log
+		.iter()
+		.filter(|r| r.sender == ship && r.tick >= start_tick)
+		.map(|r| (r.tick, Message::parse(r.word).unwrap()))
+		.collect();
+
+	let order_tick = sent
+		.iter()
+		.find(|(_, m)| matches!(m, Message::InterceptTaskAssign(task) if task.target_id == 7))
+		.map(|(tick, _)| *tick)
+		.expect("Order was never sent");
+	assert!(order_tick - start_tick < 10, "Order waited {} ticks", order_tick - start_tick);
+
+	// Only the newest position of each track goes out
+	let positions: Vec<&TrackPosition> = sent
+		.iter()
+		.filter_map(|(_, m)| match m {
+			Message::TrackPosition(p) if p.track_id >= 100 => Some(p),
+			_ => None,
+		})
+		.collect();
+	assert_eq!(positions.len(), 20);
+}
+
+#[test]
+fn lost_intercept_order_is_retransmitted() {
+	let (mut world, ship, threat) = ship_with_incoming_missile(&[]);
+	let interceptor = missiles(&world)[0];
+
+	// The first two tries at every reliable message from the ship never arrive
+	let mut lost = 0;
+	world.set_radio_loss(move |sender, word| match Message::parse(word).unwrap() {
+		Message::Fragment(fragment) if sender == ship && fragment.index == 0 && fragment.payload & 0xF == FramedMessageKey::Reliable as u64 && lost < 2 => {
+			lost += 1;
+			true
+		}
+		_ => false,
+	});
+
+	let killed = world.run_until(80.0, |w| w.position_of(threat).is_none());
+	assert!(killed, "Threat was not intercepted after {}s", world.time());
+
+	let acked = world
+		.radio_log
+		.iter()
+		.any(|r| r.sender == interceptor && matches!(Message::parse(r.word).unwrap(), Message::Ack(ack) if ack.destination == 0));
+	assert!(acked, "Interceptor never acknowledged its order");
+
+	let reliable_sends = world
+		.radio_log
+		.iter()
+		.filter(|r| {
+			r.sender == ship
+				&& matches!(Message::parse(r.word).unwrap(), Message::Fragment(f) if f.index == 0 && f.payload & 0xF == FramedMessageKey::Reliable as u64)
+		})
+		.count();
+	assert!(reliable_sends >= 3, "Order was only sent {} times", reliable_sends);
+}
+
+#[test]
+fn garbage_radio_words_are_dropped() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+	world.run_until(5.0, |_| false);
+
+	// On our channel, so they get past the radio filter
+	let channel = (world.vehicle(ship).unwrap().core().unwrap().ctx.datalink.channel() as u64) << 60;
+	let garbage = [
+		Fragment::new(9, 2, 0, 1, FramedMessageKey::Reliable as u64).serialize().value, // A Reliable cut short, without its checksum
+		Fragment::new(9, 0, 6, 2, 0).serialize().value,                                 // Index past the end of its message
+		Fragment::new(9, 1, 0, 1, 0xF).serialize().value,                               // Complete, but the checksum doesn't match
+	];
+	world.vehicle(ship).unwrap().hw.state().radio_inbox.extend(garbage.map(|w| w | channel));
+	world.run_until(1.0, |_| false);
+
+	let datalink = &world.vehicle(ship).unwrap().core().unwrap().ctx.datalink;
+	assert_eq!(datalink.decode_errors(), 3);
+	assert!(datalink.is_host());
+}
+
+#[test]
+fn opposing_fleets_ignore_each_other() {
+	let mut world = World::new();
+	let ours = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+	let theirs = world.spawn_fleet_vehicle(VehicleType::Ship, 1, Vector3::new(0.0, 0.0, -5000.0), &[]);
+
+	world.run_until(20.0, |_| false);
+
+	for (ship, enemy) in [(ours, theirs), (theirs, ours)] {
+		let datalink = &world.vehicle(ship).unwrap().core().unwrap().ctx.datalink;
+		assert!(datalink.is_host(), "Ship {} gave up hosting its own network", ship);
+		assert!(
+			!datalink.is_position_friendly(world.position_of(enemy).unwrap()),
+			"Ship {} took the enemy for a friend",
+			ship
+		);
+		assert_eq!(datalink.decode_errors(), 0);
+	}
+}
+
+#[test]
+fn out_of_range_fleet_channel_falls_back_to_the_default() {
+	let mut world = World::new();
+	let channels = [("3", 3), ("0", 1), ("16", 1), ("255", 1)].map(|(setting, expected)| {
+		let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[("FleetChannel", setting)]);
+		(setting, ship, expected)
+	});
+	world.run_until(1.0, |_| false);
+
+	for (setting, ship, expected) in channels {
+		assert_eq!(
+			world.vehicle(ship).unwrap().core().unwrap().ctx.datalink.channel(),
+			expected,
+			"FleetChannel {}",
+			setting
+		);
+	}
+}
+
+const KEY: (&str, &str) = ("DatalinkKey", "correct horse battery staple");
+
+fn is_signed_fragment(word: u64) -> bool {
+	matches!(Message::parse(word).unwrap(), Message::Fragment(f) if f.index == 0 && f.payload & 0xF == FramedMessageKey::Authenticated as u64)
+}
+
+#[test]
+fn keyed_fleet_signs_its_orders() {
+	let (mut world, ship, threat) = ship_with_incoming_missile(&[KEY]);
+	let interceptor = missiles(&world)[0];
+
+	let killed = world.run_until(80.0, |w| w.position_of(threat).is_none());
+	assert!(killed, "Threat was not intercepted after {}s", world.time());
+
+	// Both the interceptor's ready report and the order it got were signed, and no order went out bare
+	for sender in [ship, interceptor] {
+		assert!(
+			world.radio_log.iter().any(|r| r.sender == sender && is_signed_fragment(r.word)),
+			"{} sent nothing signed",
+			sender
+		);
+	}
+	let bare_orders = world
+		.radio_log
+		.iter()
+		.filter(|r| {
+			matches!(
+				Message::parse(r.word).unwrap(),
+				Message::InterceptTaskAssign(_) | Message::AssignAttackTarget(_)
+			)
+		})
+		.count();
+	assert_eq!(bare_orders, 0);
+	assert_eq!(world.vehicle(ship).unwrap().core().unwrap().ctx.datalink.auth_rejections(), 0);
+}
+
+#[test]
+fn forged_orders_are_rejected() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[KEY]);
+	world.run_until(20.0, |_| false);
+	let interceptor = missiles(&world)[0];
+	let channel = (world.vehicle(ship).unwrap().core().unwrap().ctx.datalink.channel() as u64) << 60;
+	let tick = world.vehicle(ship).unwrap().core().unwrap().ctx.datalink.tick as u16;
+
+	// An order sent bare, and one with a MAC made up without the key
+	let order = Message::InterceptTaskAssign(InterceptTaskAssign::new(7, 1234, 1, 0));
+	let payload = Authenticated::payload_of(&FramedMessage::Single(Single::new(order.clone())));
+	let forged = FramedMessage::Authenticated(Authenticated::new(tick, 0xDEADBEEF, payload));
+
+	let mut words = vec![order.serialize().value];
+	words.extend(forged.fragment(200, 3).unwrap().into_iter().map(|f| f.serialize().value));
+	world
+		.vehicle(interceptor)
+		.unwrap()
+		.hw
+		.state()
+		.radio_inbox
+		.extend(words.into_iter().map(|w| w | channel));
+	world.run_until(1.0, |_| false);
+
+	let datalink = &world.vehicle(interceptor).unwrap().core().unwrap().ctx.datalink;
+	assert_eq!(datalink.auth_rejections(), 2);
+	assert_eq!(datalink.decode_errors(), 0);
+}
+
+#[test]
+fn replayed_orders_are_rejected() {
+	let (mut world, ship, _) = ship_with_incoming_missile(&[KEY]);
+	let interceptor = missiles(&world)[0];
+	let ordered = world.run_until(30.0, |w| w.radio_log.iter().any(|r| r.sender == ship && is_signed_fragment(r.word)));
+	assert!(ordered, "Ship never sent a signed order");
+	world.run_until(0.5, |_| false);
+
+	// Record the whole signed message off the air and play it back to the interceptor
+	let first = world.radio_log.iter().find(|r| r.sender == ship && is_signed_fragment(r.word)).unwrap();
+	let sequence = match Message::parse(first.word).unwrap() {
+		Message::Fragment(f) => f.sequence,
+		_ => unreachable!(),
+	};
+	let recording: Vec<u64> = world
+		.radio_log
+		.iter()
+		.filter(|r| r.sender == ship && r.tick >= first.tick && matches!(Message::parse(r.word).unwrap(), Message::Fragment(f) if f.sequence == sequence))
+		.map(|r| r.word)
+		.collect();
+
+	let before = world.vehicle(interceptor).unwrap().core().unwrap().ctx.datalink.auth_rejections();
+	world.vehicle(interceptor).unwrap().hw.state().radio_inbox.extend(recording);
+	world.run_until(0.1, |_| false);
+
+	let datalink = &world.vehicle(interceptor).unwrap().core().unwrap().ctx.datalink;
+	assert_eq!(datalink.auth_rejections(), before + 1);
+}
+
+#[test]
+fn stats_report_link_health() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+	world.run_until(20.0, |_| false);
+	let interceptor = missiles(&world)[0];
+
+	let stats = world.vehicle(ship).unwrap().core().unwrap().ctx.datalink.stats();
+	assert!(stats.is_host);
+	assert!(stats.time_since_net_info < 0.1, "Host last sent NetInfo {}s ago", stats.time_since_net_info);
+	assert!(stats.packets_sent > 0 && stats.packets_received > 0);
+	assert_eq!(stats.packets_dropped, 0);
+	assert!(!stats.saturated);
+	assert!((0.0..=1.0).contains(&stats.slot_utilization));
+
+	let interceptor_id = world.vehicle(interceptor).unwrap().core().unwrap().ctx.datalink.id();
+	assert!(
+		stats.members.iter().any(|m| m.id == interceptor_id),
+		"Interceptor missing from {:?}",
+		stats.members
+	);
+
+	let stats = world.vehicle(interceptor).unwrap().core().unwrap().ctx.datalink.stats();
+	assert!(!stats.is_host);
+	assert!(
+		stats.time_since_net_info < 0.5,
+		"Interceptor last heard NetInfo {}s ago",
+		stats.time_since_net_info
+	);
+	assert!(stats.members.iter().any(|m| m.id == 0));
+}
+
+#[test]
+fn flooded_link_reports_saturation() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+	world.run_until(20.0, |_| false);
+
+	// More track reports than the ship's slots can carry, none of them replacing each other
+	let datalink = &mut world.vehicle_mut(ship).unwrap().core_mut().unwrap().ctx.datalink;
+	for track_id in 1..=60 {
+		let state = TrackState::new(track_id, track_id as u32, RadarTargetType::Missile, false, 0, Vector3::zero(), Vector3::zero());
+		datalink.send_framed(FramedMessage::TrackState(state));
+	}
+	world.run_until(2.5, |_| false);
+
+	let stats = world.vehicle(ship).unwrap().core().unwrap().ctx.datalink.stats();
+	assert!(stats.saturated, "Not saturated at {} utilization", stats.slot_utilization);
+	assert!(stats.slot_utilization > 0.9);
+	assert!(stats.queue_depth.iter().any(|(key, count)| *key == MessageKey::Fragment && *count > 0));
+}
+
+#[test]
+fn departed_members_give_back_their_slots_and_ids() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	world.run_until(20.0, |_| false);
+
+	let salvo: Vec<i64> = (0..6)
+		.map(|i| {
+			world.spawn_fleet_vehicle(
+				VehicleType::Missile,
+				0,
+				ship_pos + Vector3::new(100.0 * i as f32, 50.0, 0.0),
+				&[("WarheadType", "Flak")],
+			)
+		})
+		.collect();
+	let joined = world.run_until(30.0, |w| link(w, ship).members.len() == 7);
+	assert!(joined, "Only {} members joined", link(&world, ship).members.len());
+	let full_frame = link(&world, ship).total_blocks;
+	assert!(full_frame > 2);
+
+	// The last one in stays, the rest go quiet without saying goodbye
+	let survivor = *salvo.iter().max_by_key(|id| link(&world, **id).id).unwrap();
+	let retired: Vec<u8> = salvo.iter().filter(|id| **id != survivor).map(|id| link(&world, *id).id).collect();
+	for id in salvo.iter().filter(|id| **id != survivor) {
+		world.destroy(*id);
+	}
+
+	let compacted = world.run_until(15.0, |w| link(w, ship).total_blocks == 2 && link(w, survivor).total_blocks == 2);
+	assert!(compacted, "Frame is still {} blocks", link(&world, ship).total_blocks);
+	assert_eq!(link(&world, ship).members.len(), 2);
+	let survivor_block = world.vehicle(survivor).unwrap().core().unwrap().ctx.datalink.block();
+	assert!(survivor_block < 2, "Survivor left in block {}", survivor_block);
+
+	// Once they've sat out long enough the freed ids go to new joiners
+	world.run_until(30.0, |_| false);
+	let late = world.spawn_fleet_vehicle(VehicleType::Missile, 0, ship_pos + Vector3::new(0.0, -50.0, 0.0), &[("WarheadType", "Flak")]);
+	let rejoined = world.run_until(10.0, |w| link(w, late).status == DatalinkStatus::Joined);
+	assert!(rejoined);
+	assert!(
+		retired.contains(&link(&world, late).id),
+		"Got id {}, not one of {:?}",
+		link(&world, late).id,
+		retired
+	);
+}
+
+#[test]
+fn busy_ship_is_lent_spare_slots() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	world.run_until(20.0, |_| false);
+	for i in 0..6 {
+		world.spawn_fleet_vehicle(
+			VehicleType::Missile,
+			0,
+			ship_pos + Vector3::new(100.0 * i as f32, 50.0, 0.0),
+			&[("WarheadType", "Flak")],
+		);
+	}
+
+	let joined = world.run_until(30.0, |w| link(w, ship).members.len() == 7);
+	assert!(joined);
+	assert_eq!(link(&world, ship).total_blocks, 3);
+	assert_eq!(link(&world, ship).slots_per_frame, 1);
+
+	let datalink = &mut world.vehicle_mut(ship).unwrap().core_mut().unwrap().ctx.datalink;
+	for track_id in 1..=60 {
+		let state = TrackState::new(track_id, track_id as u32, RadarTargetType::Missile, false, 0, Vector3::zero(), Vector3::zero());
+		datalink.send_framed(FramedMessage::TrackState(state));
+	}
+	world.run_until(2.0, |_| false);
+
+	// The ship gets the room left in the last block, the idle interceptors get nothing extra
+	let stats = link(&world, ship);
+	assert_eq!(stats.slots_per_frame, 2);
+	assert!(stats.members.iter().all(|m| m.extra_slots == 0), "{:?}", stats.members);
+	let start = world.tick - 100;
+	let sent = world.radio_log.iter().filter(|r| r.sender == ship && r.tick >= start).count();
+	assert!(sent >= 95, "Ship only sent {} words in its last second", sent);
+
+	// And gives it back once its queue is empty
+	let released = world.run_until(20.0, |w| link(w, ship).slots_per_frame == 1);
+	assert!(released, "Ship kept its extra slots");
+}
+
+#[test]
+fn salvo_joins_within_a_few_frames() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	world.run_until(20.0, |_| false);
+
+	// Everyone asks on the same frame, the old protocol let one in per NetInfo
+	let launched = world.tick;
+	let salvo: Vec<i64> = (0..12)
+		.map(|i| {
+			world.spawn_fleet_vehicle(
+				VehicleType::Missile,
+				0,
+				ship_pos + Vector3::new(50.0 * i as f32, 50.0, 0.0),
+				&[("WarheadType", "Flak")],
+			)
+		})
+		.collect();
+	let joined = world.run_until(30.0, |w| salvo.iter().all(|id| link(w, *id).status == DatalinkStatus::Joined));
+	assert!(joined, "Only {} members joined", link(&world, ship).members.len());
+
+	let frames = world
+		.radio_log
+		.iter()
+		.filter(|r| r.tick >= launched && r.sender == ship && matches!(Message::parse(r.word).unwrap(), Message::NetInfo(_)))
+		.count();
+	assert!(frames <= 10, "Salvo took {} frames to join", frames);
+
+	let mut ids: Vec<u8> = salvo.iter().map(|id| link(&world, *id).id).collect();
+	ids.sort();
+	ids.dedup();
+	assert_eq!(ids.len(), salvo.len(), "Members share ids");
+	let listed = world.run_until(5.0, |w| link(w, ship).members.len() == 13);
+	assert!(listed, "Ship lists {} members", link(&world, ship).members.len());
+
+	// They all asked for the same block, the ones that didn't fit never sent in it
+	let mut senders: HashMap<u32, Vec<i64>> = HashMap::new();
+	for record in world.radio_log.iter().filter(|r| r.tick >= launched) {
+		if !matches!(Message::parse(record.word), Ok(Message::JoinRequest(_))) {
+			senders.entry(record.tick).or_default().push(record.sender);
+		}
+	}
+	for (tick, mut ids) in senders {
+		ids.sort();
+		ids.dedup();
+		assert!(ids.len() <= BLOCK_CAPACITY, "{} members sent on tick {}", ids.len(), tick);
+	}
+}
+
+#[test]
+fn track_ids_are_recycled_once_retired() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, 5000.0), &[]);
+	world.run_until(15.0, |_| false);
+
+	// More contacts than 12 bits of track id can tell apart, none of them ever reported on
+	let contact = |i: i64| ((20000 + i) << 32) | i;
+	let datalink = &mut world.vehicle_mut(ship).unwrap().core_mut().unwrap().ctx.datalink;
+	let mut issued: Vec<u16> = (0..4200).filter_map(|i| datalink.net_id(contact(i))).collect();
+	let stats = datalink.stats();
+	assert!(stats.track_id_exhaustions > 0, "Handed out {} ids without running out", issued.len());
+	assert!(issued.iter().all(|id| *id > 0 && *id < 4096));
+	let count = issued.len();
+	issued.sort();
+	issued.dedup();
+	assert_eq!(issued.len(), count, "Track ids were handed out twice");
+
+	// Nobody reports on them, so the host takes them back and tells everyone
+	let mut reassembler = Reassembler::new();
+	let mut retired = Vec::new();
+	let mut next_record = world.radio_log.len();
+	let announced = world.run_until(20.0, |w| {
+		for record in &w.radio_log[next_record..] {
+			if let (true, Message::Fragment(fragment)) = (record.sender == ship, Message::parse(record.word).unwrap()) {
+				if let Ok(Some(FramedMessage::TrackRetire(retire))) = reassembler.add(fragment, w.time()) {
+					retired.extend(retire.ids());
+				}
+			}
+		}
+		next_record = w.radio_log.len();
+		issued.iter().all(|id| retired.contains(id))
+	});
+	assert!(announced, "Only {} of {} ids were retired", retired.len(), issued.len());
+
+	// And hands them out again once they've sat out
+	world.run_until(35.0, |_| false);
+	let datalink = &mut world.vehicle_mut(ship).unwrap().core_mut().unwrap().ctx.datalink;
+	let reused = datalink.net_id(contact(5000));
+	assert!(reused.is_some_and(|id| issued.contains(&id)), "Got {:?} after the ids were retired", reused);
+	assert!(datalink.stats().track_ids_in_use < 100);
+}
+
+#[test]
+fn running_out_of_track_ids_holds_the_interceptor_back() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	world.run_until(15.0, |_| false);
+
+	// Every id handed out to contacts nobody reports on, they stay taken until the sweep gives up on them
+	let datalink = &mut world.vehicle_mut(ship).unwrap().core_mut().unwrap().ctx.datalink;
+	(0..4200).for_each(|i| {
+		datalink.net_id(((20000 + i) << 32) | i);
+	});
+	let exhaustions = link(&world, ship).track_id_exhaustions;
+	assert!(exhaustions > 0);
+
+	// A threat turns up that can't be given an id, so it can't be ordered against either
+	let threat = world.spawn_ballistic(
+		VehicleType::Missile,
+		1,
+		ship_pos + Vector3::new(5000.0, 0.0, 0.0),
+		Vector3::new(-100.0, 0.0, 0.0),
+	);
+	let ranked = world.run_until(10.0, |w| control(w, ship).threats().iter().any(|t| t.track_id == threat));
+	assert!(ranked, "Ship never saw the threat");
+	world.run_until(2.0, |_| false);
+
+	assert!(link(&world, ship).track_id_exhaustions > exhaustions);
+	assert!(control(&world, ship).threats().iter().all(|t| !t.engaged));
+	let interceptor_free = control(&world, ship)
+		.weapon_assignments()
+		.iter()
+		.any(|a| matches!(a.weapon, Weapon::Interceptor(_)));
+	assert!(interceptor_free, "Interceptor left the pool without an order");
+}
+
+#[test]
+fn radar_search_turns_toward_the_enemy() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	let enemy = world.spawn_ballistic(VehicleType::Ship, 1, Vector3::new(2000.0, 1000.0, -8000.0), Vector3::zero());
+
+	let radar = |w: &World| w.vehicle(ship).unwrap().core().unwrap().ctx.radar.scan_pattern().spec();
+	assert!(matches!(radar(&world).sector, ScanSector::Sphere));
+
+	let focused = world.run_until(30.0, |w| matches!(radar(w).sector, ScanSector::Hemisphere(_)));
+	assert!(focused, "Radar never stopped searching the whole sphere");
+
+	let toward_enemy = (world.position_of(enemy).unwrap() - world.position_of(ship).unwrap()).normalized();
+	let axis = match radar(&world).sector {
+		ScanSector::Hemisphere(axis) => axis,
+		_ => unreachable!(),
+	};
+	assert!(
+		axis.dot(&toward_enemy) > 0.95,
+		"Searching toward {} with the enemy toward {}",
+		axis,
+		toward_enemy
+	);
+
+	// Fewer looks to get around than the whole sphere took, with the enemy's side seen more than once
+	let pattern = &world.vehicle(ship).unwrap().core().unwrap().ctx.radar;
+	let full = RadarScanPattern::full_sphere();
+	assert!(pattern.scan_pattern().len() < full.len());
+	assert!(pattern.scan_pattern().cycle_length() > pattern.scan_pattern().len() as f32);
+}
+
+#[test]
+fn closing_missile_gets_more_radar_looks() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	let enemy = world.spawn_ballistic(VehicleType::Ship, 1, Vector3::new(0.0, 2000.0, -9000.0), Vector3::zero());
+	let threat = world.spawn_ballistic(
+		VehicleType::Missile,
+		1,
+		ship_pos + Vector3::new(6000.0, 0.0, 0.0),
+		Vector3::new(-250.0, 0.0, 0.0),
+	);
+
+	let established = |w: &World, id: i64| radar(w, ship).get_contact(id).is_some_and(|t| t.is_established());
+	let tracked = world.run_until(10.0, |w| established(w, enemy) && established(w, threat));
+	assert!(tracked, "Ship never established both tracks");
+
+	// Count the track looks spent on each of them over a couple of seconds
+	let before = radar(&world, ship).scheduler().counts();
+	let mut looks = [0, 0];
+	world.run_until(2.0, |w| {
+		if let Some(Dwell::Track { contact_id: Some(id), .. }) = radar(w, ship).scheduler().last_dwell() {
+			looks[0] += (id == enemy) as u32;
+			looks[1] += (id == threat) as u32;
+		}
+		false
+	});
+	assert!(looks[1] > looks[0], "Threat looked at {} times, the ship {}", looks[1], looks[0]);
+
+	// Search still gets its share
+	let after = radar(&world, ship).scheduler().counts();
+	let search = (after.search - before.search) as f32;
+	let total = search + (after.track - before.track) as f32;
+	assert!(
+		search / total >= radar(&world, ship).scheduler().search_fraction() - 0.01,
+		"Search got {} of {} looks",
+		search,
+		total
+	);
+}
+
+#[test]
+fn gated_association_tracks_without_contact_ids() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	let threat = world.spawn_ballistic(
+		VehicleType::Missile,
+		1,
+		ship_pos + Vector3::new(6000.0, 0.0, 0.0),
+		Vector3::new(-250.0, 0.0, 0.0),
+	);
+	world.hide_contact_ids();
+	let ship_radar = &mut world.vehicle_mut(ship).unwrap().core_mut().unwrap().ctx.radar;
+	ship_radar.set_association(AssociationStrategy::GlobalNearestNeighbour);
+
+	fn missile_tracks_near(w: &World, ship: i64, position: Vector3) -> Vec<i64> {
+		radar(w, ship)
+			.get_tracks()
+			.iter()
+			.filter(|t| t.rc_type == RadarTargetType::Missile && (t.position - position).length() < 300.0)
+			.map(|t| t.id)
+			.collect()
+	}
+
+	// Every return has an id never seen before, only gating can keep putting them on the same track
+	let tracked = world.run_until(10.0, |w| {
+		w.position_of(threat).is_some_and(|position| {
+			missile_tracks_near(w, ship, position)
+				.iter()
+				.any(|id| radar(w, ship).get_contact(*id).unwrap().is_established())
+		})
+	});
+	assert!(tracked, "Ship never established a track on the threat");
+
+	let position = world.position_of(threat).unwrap();
+	assert_eq!(missile_tracks_near(&world, ship, position).len(), 1, "Threat split over several tracks");
+}
+
+#[test]
+fn clutter_near_a_track_is_reported_ambiguous() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	let threat = world.spawn_ballistic(
+		VehicleType::Missile,
+		1,
+		ship_pos + Vector3::new(6000.0, 0.0, 0.0),
+		Vector3::new(-250.0, 0.0, 0.0),
+	);
+
+	let reports: Rc<RefCell<Vec<AmbiguousAssociation>>> = Rc::new(RefCell::new(Vec::new()));
+	let ship_radar = &mut world.vehicle_mut(ship).unwrap().core_mut().unwrap().ctx.radar;
+	ship_radar.set_association(AssociationStrategy::NearestNeighbour);
+	let sink = reports.clone();
+	ship_radar.on_ambiguous_association(move |a| sink.borrow_mut().push(a.clone()));
+
+	let tracked = world.run_until(10.0, |w| radar(w, ship).get_contact(threat).is_some_and(|t| t.is_established()));
+	assert!(tracked, "Ship never established a track on the threat");
+	assert!(reports.borrow().is_empty(), "Ambiguity reported without any clutter");
+
+	// Two false returns either side of the threat, both as good a match for its track
+	let position = world.position_of(threat).unwrap();
+	let clutter = [(-1, Vector3::new(0.0, 15.0, 0.0)), (-2, Vector3::new(0.0, -15.0, 0.0))];
+	for (id, offset) in clutter {
+		let at = position + offset;
+		world.inject_radar_contact(
+			ship,
+			RadarGetContactInfo {
+				id,
+				target_type: RadarTargetType::Missile,
+				x: at.x,
+				y: at.y,
+				z: at.z,
+				signal_strength: 1.0,
+			},
+		);
+	}
+	world.step();
+
+	let reports = reports.borrow();
+	let report = reports.iter().find(|a| a.track_id == Some(threat));
+	assert!(report.is_some(), "No ambiguity reported on the threat track: {:?}", reports);
+	assert!(report.unwrap().contacts.contains(&-1) && report.unwrap().contacts.contains(&-2));
+	assert_eq!(radar(&world, ship).ambiguous_associations(), reports.len() as u32);
+}
+
+#[test]
+fn worst_threat_is_engaged_first() {
+	let mut world = World::new();
+	let ship_pos = Vector3::new(0.0, 0.0, 5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+
+	// One coming straight at us, one next to it in the same radar beam that's heading away
+	// In the radar's returns ahead of the inbound one, so going by track order would get this wrong
+	let passing = world.spawn_ballistic(
+		VehicleType::Missile,
+		1,
+		ship_pos + Vector3::new(5000.0, 200.0, 0.0),
+		Vector3::new(0.0, 150.0, 0.0),
+	);
+	let inbound = world.spawn_ballistic(
+		VehicleType::Missile,
+		1,
+		ship_pos + Vector3::new(5000.0, 0.0, 0.0),
+		Vector3::new(-150.0, 0.0, 0.0),
+	);
+
+	let ranked = world.run_until(10.0, |w| control(w, ship).threats().len() == 2);
+	assert!(ranked, "Ship never ranked both threats");
+
+	let threats = control(&world, ship).threats();
+	assert_eq!(threats[0].track_id, inbound);
+	assert!(threats[0].time_to_impact.is_some_and(|t| t > 0.0 && t < 40.0), "{:?}", threats[0]);
+	assert_eq!(threats[1].track_id, passing);
+	assert!(threats[1].time_to_impact.is_none() && threats[1].time_to_cpa == 0.0, "{:?}", threats[1]);
+
+	// Both are picked up together, the only interceptor goes to the inbound missile
+	let engaged = world.run_until(20.0, |w| control(w, ship).threats().iter().any(|t| t.engaged));
+	assert!(engaged, "No threat was engaged");
+	let threats = control(&world, ship).threats();
+	assert!(threats.iter().all(|t| t.engaged == (t.track_id == inbound)), "{:?}", threats);
+
+	// Turrets pick it up once it's close enough
+	let covered = world.run_until(20.0, |w| control(w, ship).turret_targets().contains(&Some(inbound)));
+	assert!(covered, "No turret took the inbound missile");
+}
+
+#[test]
+fn weapons_are_assigned_without_thrashing() {
+	let mut world = World::new();
+	// Ships on this side launch a nuclear strike missile instead of an interceptor
+	let ship_pos = Vector3::new(0.0, 0.0, -5000.0);
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, ship_pos, &[]);
+	let enemy = world.spawn_ballistic(VehicleType::Ship, 1, Vector3::new(0.0, 0.0, 5000.0), Vector3::zero());
+
+	let strike_target = |w: &World| {
+		control(w, ship)
+			.weapon_assignments()
+			.iter()
+			.find(|a| matches!(a.weapon, Weapon::Strike(_)))
+			.map(|a| a.track_id)
+	};
+	let assigned = world.run_until(20.0, |w| strike_target(w).is_some());
+	assert!(assigned, "Strike missile was never assigned");
+	assert_eq!(strike_target(&world), Some(enemy));
+
+	// The order reaches the missile
+	let target_net_id = |w: &World| {
+		w.vehicle(ship)
+			.unwrap()
+			.core()
+			.unwrap()
+			.ctx
+			.datalink
+			.get_track_for_contact(enemy)
+			.unwrap()
+			.track_id
+	};
+	let retargeted = world.run_until(5.0, |w| {
+		w.vehicles
+			.iter()
+			.any(|v| matches!(v.core().and_then(|c| c.ctx.control_system()), Some(ControlSystem::Missile(mcs)) if mcs.target_id == target_net_id(w)))
+	});
+	assert!(retargeted, "Strike missile never took its target");
+
+	// Two missiles coming in side by side are worth the same, the turrets split between them and stay put
+	let inbound = [
+		world.spawn_ballistic(
+			VehicleType::Missile,
+			1,
+			ship_pos + Vector3::new(2000.0, 200.0, 0.0),
+			Vector3::new(-150.0, -15.0, 0.0),
+		),
+		world.spawn_ballistic(
+			VehicleType::Missile,
+			1,
+			ship_pos + Vector3::new(2000.0, -200.0, 0.0),
+			Vector3::new(-150.0, 15.0, 0.0),
+		),
+	];
+	let covered = |w: &World| inbound.iter().all(|id| control(w, ship).turret_targets().contains(&Some(*id)));
+	assert!(world.run_until(5.0, covered), "Turrets never covered both missiles");
+
+	let switches = control(&world, ship).weapon_switches();
+	let targets = control(&world, ship).turret_targets();
+	world.run_until(3.0, |w| {
+		assert_eq!(control(w, ship).turret_targets(), targets);
+		false
+	});
+	assert_eq!(control(&world, ship).weapon_switches(), switches);
+}
+
+#[test]
+fn strike_order_is_sent_again_after_it_fails() {
+	let mut world = World::new();
+	let ship = world.spawn_fleet_vehicle(VehicleType::Ship, 0, Vector3::new(0.0, 0.0, -5000.0), &[]);
+	let enemy = world.spawn_ballistic(VehicleType::Ship, 1, Vector3::new(0.0, 0.0, 5000.0), Vector3::zero());
+
+	// The first reliable messages from the ship are lost on every try, so the first order fails outright
+	let mut lost = 0;
+	world.set_radio_loss(move |sender, word| match Message::parse(word).unwrap() {
+		Message::Fragment(fragment) if sender == ship && fragment.index == 0 && fragment.payload & 0xF == FramedMessageKey::Reliable as u64 && lost < 8 => {
+			lost += 1;
+			true
+		}
+		_ => false,
+	});
+
+	let target_net_id = |w: &World| {
+		w.vehicle(ship)
+			.unwrap()
+			.core()
+			.unwrap()
+			.ctx
+			.datalink
+			.get_track_for_contact(enemy)
+			.map(|t| t.track_id)
+	};
+	let retargeted = world.run_until(30.0, |w| {
+		w.vehicles
+			.iter()
+			.any(|v| matches!(v.core().and_then(|c| c.ctx.control_system()), Some(ControlSystem::Missile(mcs)) if Some(mcs.target_id) == target_net_id(w)))
+	});
+	assert!(retargeted, "Strike missile never took its target after {}s", world.time());
+}